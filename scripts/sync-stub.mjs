@@ -0,0 +1,72 @@
+import http from 'http';
+
+// Local stand-in for the central feedback collection endpoint.
+// Usage: node scripts/sync-stub.mjs [port] [--fail-every N]
+// then set the sync endpoint to http://localhost:<port>/feedback/upload
+
+const args = process.argv.slice(2);
+const port = Number(args.find((a) => /^\d+$/.test(a)) || 8787);
+const failIndex = args.indexOf('--fail-every');
+const failEvery = failIndex >= 0 ? Number(args[failIndex + 1]) : 0;
+
+// Rows are upserted by table + primary key, like the real endpoint must do
+const primaryKeys = {
+  sessions: 'session_id',
+  messages: 'message_id',
+  feedbacks: 'feedback_id',
+  recommendations: 'recommendation_id',
+  operation_logs: 'log_id',
+  performance_metrics: 'metric_id',
+  template_usage: 'usage_id'
+};
+const store = new Map();
+let requests = 0;
+
+const server = http.createServer((req, res) => {
+  if (req.method === 'GET' && req.url === '/feedback/stats') {
+    const counts = {};
+    for (const key of store.keys()) {
+      const table = key.split(':')[0];
+      counts[table] = (counts[table] || 0) + 1;
+    }
+    res.writeHead(200, { 'Content-Type': 'application/json' });
+    res.end(JSON.stringify({ requests, counts }));
+    return;
+  }
+  if (req.method !== 'POST') {
+    res.writeHead(405).end();
+    return;
+  }
+
+  let body = '';
+  req.on('data', (chunk) => (body += chunk));
+  req.on('end', () => {
+    requests++;
+    if (failEvery > 0 && requests % failEvery === 0) {
+      console.log(`[SyncStub] Simulating failure for request ${requests}`);
+      res.writeHead(503).end('simulated outage');
+      return;
+    }
+
+    let batch;
+    try {
+      batch = JSON.parse(body);
+    } catch (e) {
+      res.writeHead(400).end('invalid JSON');
+      return;
+    }
+    for (const { table, row } of batch.records || []) {
+      store.set(`${table}:${row[primaryKeys[table]]}`, row);
+    }
+    console.log(
+      `[SyncStub] Batch ${batch.batchId} from ${batch.workstation?.name} (${batch.workstation?.id}): ` +
+        `${(batch.records || []).length} rows, seq ${batch.fromSeq}..${batch.toSeq}, anonymized=${batch.anonymized}`
+    );
+    res.writeHead(200, { 'Content-Type': 'application/json' });
+    res.end(JSON.stringify({ accepted: (batch.records || []).length }));
+  });
+});
+
+server.listen(port, () => {
+  console.log(`[SyncStub] Listening on http://localhost:${port}/feedback/upload`);
+});
@@ -0,0 +1,115 @@
+import http from 'http';
+import crypto from 'crypto';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+
+// Local stand-in for the department template server.
+// Usage: node scripts/template-bundle-stub.mjs [port] [--templates file] [--version v] [--tamper]
+// then subscribe to http://localhost:<port>/templates/bundle.json with the printed public key.
+// --tamper serves bundles whose signature does not match, to check they are rejected.
+
+const args = process.argv.slice(2);
+const option = (name, fallback) => {
+  const index = args.indexOf(name);
+  return index >= 0 ? args[index + 1] : fallback;
+};
+const port = Number(args.find((a) => /^\d+$/.test(a)) || 8788);
+const templatesFile = option('--templates', 'src/assets/templates.json');
+const fixedVersion = option('--version', null);
+const tamper = args.includes('--tamper');
+
+// Keep the key between runs so the app's subscription keeps verifying
+const keyFile = path.join(os.tmpdir(), 'template-bundle-stub-key.json');
+function loadKey() {
+  if (fs.existsSync(keyFile)) {
+    const saved = JSON.parse(fs.readFileSync(keyFile, 'utf8'));
+    return {
+      keyId: Buffer.from(saved.keyId, 'hex'),
+      privateKey: crypto.createPrivateKey({ key: saved.privateKey, format: 'pem' }),
+      publicKey: crypto.createPublicKey({ key: saved.publicKey, format: 'pem' })
+    };
+  }
+  const { privateKey, publicKey } = crypto.generateKeyPairSync('ed25519');
+  const keyId = crypto.randomBytes(8);
+  fs.writeFileSync(
+    keyFile,
+    JSON.stringify({
+      keyId: keyId.toString('hex'),
+      privateKey: privateKey.export({ type: 'pkcs8', format: 'pem' }),
+      publicKey: publicKey.export({ type: 'spki', format: 'pem' })
+    })
+  );
+  return { keyId, privateKey, publicKey };
+}
+const key = loadKey();
+
+// minisign public key: "Ed" || key id || raw Ed25519 key
+const rawPublicKey = Buffer.from(key.publicKey.export({ format: 'jwk' }).x, 'base64url');
+const minisignPublicKey = Buffer.concat([Buffer.from('Ed'), key.keyId, rawPublicKey]).toString('base64');
+
+// Prehashed minisign signature ("ED"): Ed25519 over BLAKE2b-512 of the file,
+// plus a global signature binding the trusted comment
+function sign(bytes, fileName) {
+  const digest = crypto.createHash('blake2b512').update(bytes).digest();
+  const signature = crypto.sign(null, digest, key.privateKey);
+  const trustedComment = `timestamp:${Math.floor(Date.now() / 1000)}\tfile:${fileName}`;
+  const globalSignature = crypto.sign(
+    null,
+    Buffer.concat([signature, Buffer.from(trustedComment)]),
+    key.privateKey
+  );
+  return [
+    'untrusted comment: signature from template-bundle-stub',
+    Buffer.concat([Buffer.from('ED'), key.keyId, signature]).toString('base64'),
+    `trusted comment: ${trustedComment}`,
+    globalSignature.toString('base64'),
+    ''
+  ].join('\n');
+}
+
+// Rebuilt from the templates file on every request, so edits show up on the next check
+function buildBundle() {
+  const stat = fs.statSync(templatesFile);
+  const templates = JSON.parse(fs.readFileSync(templatesFile, 'utf8'));
+  const bundle = {
+    version: fixedVersion || new Date(stat.mtimeMs).toISOString().replace(/[-:]/g, '').slice(0, 15),
+    name: 'Template bundle stub',
+    publishedAt: Math.round(stat.mtimeMs),
+    templates
+  };
+  const bytes = Buffer.from(JSON.stringify(bundle, null, 2));
+  const signature = sign(bytes, 'bundle.json');
+  return { bundle, bytes: tamper ? Buffer.concat([bytes, Buffer.from(' ')]) : bytes, signature };
+}
+
+const server = http.createServer((req, res) => {
+  if (req.method !== 'GET') {
+    res.writeHead(405).end();
+    return;
+  }
+  let built;
+  try {
+    built = buildBundle();
+  } catch (e) {
+    res.writeHead(500).end(`failed to build bundle: ${e.message}`);
+    return;
+  }
+
+  if (req.url === '/templates/bundle.json') {
+    console.log(`[BundleStub] Serving bundle ${built.bundle.version} (${built.bundle.templates.length} symptoms)`);
+    res.writeHead(200, { 'Content-Type': 'application/json' });
+    res.end(built.bytes);
+  } else if (req.url === '/templates/bundle.json.sig') {
+    res.writeHead(200, { 'Content-Type': 'text/plain' });
+    res.end(built.signature);
+  } else {
+    res.writeHead(404).end();
+  }
+});
+
+server.listen(port, () => {
+  console.log(`[BundleStub] Bundle URL: http://localhost:${port}/templates/bundle.json`);
+  console.log(`[BundleStub] Public key: ${minisignPublicKey}`);
+  if (tamper) console.log('[BundleStub] Serving tampered bundles');
+});
@@ -0,0 +1,10 @@
+-- Key/value settings for the feedback backend
+-- Created: 2026-10-18
+
+-- Table: app_settings
+-- Stores backend configuration (retention policy etc.) as JSON values
+CREATE TABLE IF NOT EXISTS app_settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL DEFAULT (unixepoch())
+);
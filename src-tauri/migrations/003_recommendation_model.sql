@@ -0,0 +1,6 @@
+-- Record which model produced each recommendation
+-- Created: 2026-10-18
+
+ALTER TABLE recommendations ADD COLUMN llm_model TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_recommendations_model ON recommendations(llm_model);
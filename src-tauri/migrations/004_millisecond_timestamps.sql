@@ -0,0 +1,39 @@
+-- Millisecond timestamps and per-session message sequence numbers
+-- Created: 2026-10-18
+--
+-- Timestamps used to be whole seconds. Values below 1e11 cannot be
+-- milliseconds of any date the app has run on (1e11 ms is March 1973), so
+-- they are scaled. The column defaults still read unixepoch(), but every
+-- insert supplies its own millisecond value.
+
+UPDATE sessions SET start_time = start_time * 1000 WHERE start_time < 100000000000;
+UPDATE sessions SET end_time = end_time * 1000 WHERE end_time < 100000000000;
+UPDATE sessions SET created_at = created_at * 1000 WHERE created_at < 100000000000;
+UPDATE messages SET created_at = created_at * 1000 WHERE created_at < 100000000000;
+UPDATE feedbacks SET created_at = created_at * 1000 WHERE created_at < 100000000000;
+UPDATE recommendations SET created_at = created_at * 1000 WHERE created_at < 100000000000;
+UPDATE operation_logs SET created_at = created_at * 1000 WHERE created_at < 100000000000;
+UPDATE performance_metrics SET created_at = created_at * 1000 WHERE created_at < 100000000000;
+UPDATE app_settings SET updated_at = updated_at * 1000 WHERE updated_at < 100000000000;
+
+-- Lifecycle timestamps recorded in session metadata
+UPDATE sessions
+SET metadata = json_set(metadata, '$.closedAt', json_extract(metadata, '$.closedAt') * 1000)
+WHERE json_valid(metadata) AND json_extract(metadata, '$.closedAt') < 100000000000;
+UPDATE sessions
+SET metadata = json_set(metadata, '$.resumedAt', json_extract(metadata, '$.resumedAt') * 1000)
+WHERE json_valid(metadata) AND json_extract(metadata, '$.resumedAt') < 100000000000;
+
+-- Order of a message within its session, independent of timestamp ties
+ALTER TABLE messages ADD COLUMN seq INTEGER;
+
+UPDATE messages
+SET seq = numbered.rn
+FROM (
+    SELECT message_id,
+           ROW_NUMBER() OVER (PARTITION BY session_id ORDER BY created_at, rowid) AS rn
+    FROM messages
+) AS numbered
+WHERE numbered.message_id = messages.message_id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_messages_session_seq ON messages(session_id, seq);
@@ -0,0 +1,33 @@
+-- Source workstation of imported rows and a log of imports
+-- Created: 2026-10-18
+--
+-- source_workstation is NULL for rows written on this workstation.
+
+ALTER TABLE sessions ADD COLUMN source_workstation TEXT;
+ALTER TABLE messages ADD COLUMN source_workstation TEXT;
+ALTER TABLE feedbacks ADD COLUMN source_workstation TEXT;
+ALTER TABLE recommendations ADD COLUMN source_workstation TEXT;
+ALTER TABLE operation_logs ADD COLUMN source_workstation TEXT;
+ALTER TABLE performance_metrics ADD COLUMN source_workstation TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_sessions_source ON sessions(source_workstation);
+CREATE INDEX IF NOT EXISTS idx_feedbacks_source ON feedbacks(source_workstation);
+CREATE INDEX IF NOT EXISTS idx_recommendations_source ON recommendations(source_workstation);
+CREATE INDEX IF NOT EXISTS idx_operations_source ON operation_logs(source_workstation);
+CREATE INDEX IF NOT EXISTS idx_metrics_source ON performance_metrics(source_workstation);
+
+-- Table: import_sources
+-- One row per imported export file; row counts are JSON objects keyed by table
+CREATE TABLE IF NOT EXISTS import_sources (
+    import_id TEXT PRIMARY KEY,
+    workstation_id TEXT NOT NULL,
+    workstation_name TEXT,
+    file_path TEXT NOT NULL,
+    format TEXT NOT NULL,
+    exported_at INTEGER,
+    inserted_rows TEXT NOT NULL,
+    skipped_rows TEXT NOT NULL,
+    imported_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_sources_workstation ON import_sources(workstation_id);
@@ -0,0 +1,124 @@
+-- Outbox for uploading local rows to the central collection endpoint
+-- Created: 2026-10-18
+--
+-- While sync is enabled, every insert or update of a local row (source_workstation
+-- IS NULL) queues its primary key here. The sync worker uploads queued rows in
+-- order and records the highest acknowledged seq as its high-water mark, so the
+-- queue survives restarts and periods without network access.
+
+CREATE TABLE IF NOT EXISTS sync_outbox (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_name TEXT NOT NULL,
+    row_id TEXT NOT NULL,
+    queued_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_outbox_row ON sync_outbox(table_name, row_id);
+
+CREATE TRIGGER IF NOT EXISTS trg_sessions_sync_insert
+AFTER INSERT ON sessions
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('sessions', NEW.session_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_sessions_sync_update
+AFTER UPDATE ON sessions
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('sessions', NEW.session_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_messages_sync_insert
+AFTER INSERT ON messages
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('messages', NEW.message_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_messages_sync_update
+AFTER UPDATE ON messages
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('messages', NEW.message_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_feedbacks_sync_insert
+AFTER INSERT ON feedbacks
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('feedbacks', NEW.feedback_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_feedbacks_sync_update
+AFTER UPDATE ON feedbacks
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('feedbacks', NEW.feedback_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_recommendations_sync_insert
+AFTER INSERT ON recommendations
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('recommendations', NEW.recommendation_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_recommendations_sync_update
+AFTER UPDATE ON recommendations
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('recommendations', NEW.recommendation_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_operation_logs_sync_insert
+AFTER INSERT ON operation_logs
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('operation_logs', NEW.log_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_operation_logs_sync_update
+AFTER UPDATE ON operation_logs
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('operation_logs', NEW.log_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_performance_metrics_sync_insert
+AFTER INSERT ON performance_metrics
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('performance_metrics', NEW.metric_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_performance_metrics_sync_update
+AFTER UPDATE ON performance_metrics
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('performance_metrics', NEW.metric_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
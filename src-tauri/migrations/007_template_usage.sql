@@ -0,0 +1,42 @@
+-- Which symptom templates and fields were shown and answered in each session
+-- Created: 2026-10-18
+--
+-- One row per session, symptom and field; the row for the symptom itself has
+-- an empty field_key. shown_at is when the item first appeared in the form and
+-- answered_at when it first got an answer (for the symptom row, the first
+-- answer to any of its fields); answered_at stays NULL for skipped items.
+
+CREATE TABLE IF NOT EXISTS template_usage (
+    usage_id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    symptom_key TEXT NOT NULL,
+    field_key TEXT NOT NULL DEFAULT '',
+    shown_at INTEGER NOT NULL,
+    answered_at INTEGER,
+    created_at INTEGER NOT NULL,
+    source_workstation TEXT,
+    FOREIGN KEY (session_id) REFERENCES sessions(session_id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_template_usage_item ON template_usage(session_id, symptom_key, field_key);
+CREATE INDEX IF NOT EXISTS idx_template_usage_symptom ON template_usage(symptom_key, field_key);
+CREATE INDEX IF NOT EXISTS idx_template_usage_created ON template_usage(created_at);
+CREATE INDEX IF NOT EXISTS idx_template_usage_source ON template_usage(source_workstation);
+
+CREATE TRIGGER IF NOT EXISTS trg_template_usage_sync_insert
+AFTER INSERT ON template_usage
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('template_usage', NEW.usage_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_template_usage_sync_update
+AFTER UPDATE ON template_usage
+WHEN NEW.source_workstation IS NULL
+    AND EXISTS (SELECT 1 FROM app_settings WHERE key = 'sync_config' AND json_extract(value, '$.enabled') = 1)
+BEGIN
+    INSERT INTO sync_outbox (table_name, row_id, queued_at)
+    VALUES ('template_usage', NEW.usage_id, CAST(unixepoch('subsec') * 1000 AS INTEGER));
+END;
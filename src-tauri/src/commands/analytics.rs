@@ -0,0 +1,1035 @@
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection};
+use similar::{capture_diff_slices, Algorithm, DiffOp, TextDiff};
+use std::collections::{BTreeMap, HashMap};
+use tauri::{command, AppHandle, Manager};
+
+use super::feedback::DbConnection;
+use crate::db::models::*;
+use crate::db::{settings, workstation};
+use crate::templates::{SymptomTemplate, TemplateRepository};
+
+// WHERE clauses and their positional parameters, built up from optional filters.
+// Clauses use anonymous `?` placeholders, so parameters bind in push order.
+#[derive(Default, Clone)]
+pub(crate) struct QueryFilter {
+    clauses: Vec<String>,
+    params: Vec<SqlValue>,
+}
+
+impl QueryFilter {
+    pub fn push(&mut self, clause: &str, value: impl Into<SqlValue>) -> &mut Self {
+        self.clauses.push(clause.to_string());
+        self.params.push(value.into());
+        self
+    }
+
+    pub fn push_opt<T: Into<SqlValue>>(&mut self, clause: &str, value: Option<T>) -> &mut Self {
+        if let Some(v) = value {
+            self.push(clause, v);
+        }
+        self
+    }
+
+    pub fn date_range(&mut self, column: &str, start: Option<i64>, end: Option<i64>) -> &mut Self {
+        self.push_opt(&format!("{} >= ?", column), start);
+        self.push_opt(&format!("{} <= ?", column), end);
+        self
+    }
+
+    // Rows imported from `workstation`. This workstation's own rows have no
+    // source recorded, so its id also matches NULL.
+    pub fn source(&mut self, workstation: Option<String>, local: &WorkstationInfo) -> &mut Self {
+        match workstation {
+            Some(id) if id == local.id => {
+                self.push("(source_workstation IS NULL OR source_workstation = ?)", id)
+            }
+            Some(id) => self.push("source_workstation = ?", id),
+            None => self,
+        }
+    }
+
+    pub fn where_sql(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+
+    // Filter parameters followed by any parameters that appear later in the query
+    pub fn params_with(&self, extra: &[SqlValue]) -> Vec<SqlValue> {
+        self.params.iter().chain(extra).cloned().collect()
+    }
+}
+
+fn rate(part: i64, total: i64) -> Option<f64> {
+    (total > 0).then(|| part as f64 / total as f64)
+}
+
+// Recommendation Analytics
+
+// Recommendations in scope joined with their outcome: the latest adopted /
+// rejected / modified feedback whose target_id is the recommendation id.
+fn recommendation_outcomes_cte(filter: &QueryFilter) -> String {
+    format!(
+        "WITH scoped AS (
+            SELECT * FROM recommendations {}
+        ),
+        outcomes AS (
+            SELECT target_id, feedback_type,
+                   ROW_NUMBER() OVER (PARTITION BY target_id ORDER BY created_at DESC) AS rn
+            FROM feedbacks
+            WHERE feedback_type IN ('adopted', 'rejected', 'modified')
+              AND target_type IN ('diagnosis', 'medication', 'examination')
+        ),
+        joined AS (
+            SELECT s.*, o.feedback_type AS outcome
+            FROM scoped s
+            LEFT JOIN outcomes o ON o.target_id = s.recommendation_id AND o.rn = 1
+        )",
+        filter.where_sql()
+    )
+}
+
+fn top_contents_by_outcome(
+    conn: &Connection,
+    filter: &QueryFilter,
+    outcome: &str,
+    limit: u32,
+) -> rusqlite::Result<Vec<RecommendationContentCount>> {
+    // Contents are stored as JSON; group by the item name when there is one
+    let query = format!(
+        "{}
+        SELECT rec_type,
+               CASE WHEN json_valid(content)
+                    THEN COALESCE(json_extract(content, '$.name'), content)
+                    ELSE content END AS label,
+               COUNT(*) AS count
+        FROM joined
+        WHERE outcome = ?
+        GROUP BY rec_type, label
+        ORDER BY count DESC, label
+        LIMIT ?",
+        recommendation_outcomes_cte(filter)
+    );
+    let params = filter.params_with(&[outcome.to_string().into(), i64::from(limit).into()]);
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(params_from_iter(params), |row| {
+        Ok(RecommendationContentCount {
+            rec_type: row.get(0)?,
+            content: row.get(1)?,
+            count: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn recommendation_statistics(
+    conn: &Connection,
+    filter: &QueryFilter,
+    top_n: u32,
+    bucket_count: u32,
+) -> rusqlite::Result<RecommendationStatistics> {
+    let cte = recommendation_outcomes_cte(filter);
+
+    // Acceptance and token usage per recommendation type
+    let type_query = format!(
+        "{}
+        SELECT rec_type,
+               COUNT(*),
+               SUM(CASE WHEN matched THEN 1 ELSE 0 END),
+               SUM(CASE WHEN outcome IS NOT NULL THEN 1 ELSE 0 END),
+               SUM(CASE WHEN outcome = 'adopted' THEN 1 ELSE 0 END),
+               SUM(CASE WHEN outcome = 'rejected' THEN 1 ELSE 0 END),
+               SUM(CASE WHEN outcome = 'modified' THEN 1 ELSE 0 END),
+               COALESCE(SUM(prompt_tokens), 0),
+               COALESCE(SUM(completion_tokens), 0),
+               AVG(prompt_tokens),
+               AVG(completion_tokens),
+               AVG(latency_ms)
+        FROM joined
+        GROUP BY rec_type
+        ORDER BY rec_type",
+        cte
+    );
+    let mut stmt = conn.prepare(&type_query)?;
+    let by_type = stmt
+        .query_map(params_from_iter(filter.params_with(&[])), |row| {
+            let with_outcome: i64 = row.get(3)?;
+            let adopted: i64 = row.get(4)?;
+            Ok(RecommendationTypeStatistics {
+                rec_type: row.get(0)?,
+                total: row.get(1)?,
+                matched_count: row.get(2)?,
+                with_outcome,
+                adopted_count: adopted,
+                rejected_count: row.get(5)?,
+                modified_count: row.get(6)?,
+                acceptance_rate: rate(adopted, with_outcome),
+                total_prompt_tokens: row.get(7)?,
+                total_completion_tokens: row.get(8)?,
+                avg_prompt_tokens: row.get(9)?,
+                avg_completion_tokens: row.get(10)?,
+                avg_latency_ms: row.get(11)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Calibration: does the matcher's confidence predict matches and adoption?
+    let calibration_query = format!(
+        "{}
+        SELECT MAX(0, MIN(CAST(match_confidence * {n} AS INTEGER), {n} - 1)) AS bucket,
+               COUNT(*),
+               AVG(match_confidence),
+               SUM(CASE WHEN matched THEN 1 ELSE 0 END),
+               SUM(CASE WHEN outcome = 'adopted' THEN 1 ELSE 0 END),
+               SUM(CASE WHEN outcome IS NOT NULL THEN 1 ELSE 0 END)
+        FROM joined
+        WHERE match_confidence IS NOT NULL
+        GROUP BY bucket",
+        cte,
+        n = bucket_count
+    );
+    let width = 1.0 / f64::from(bucket_count);
+    let mut calibration: Vec<ConfidenceBucket> = (0..bucket_count)
+        .map(|i| ConfidenceBucket {
+            lower: f64::from(i) * width,
+            upper: f64::from(i + 1) * width,
+            count: 0,
+            avg_confidence: None,
+            match_rate: None,
+            acceptance_rate: None,
+        })
+        .collect();
+    let mut stmt = conn.prepare(&calibration_query)?;
+    let mut rows = stmt.query(params_from_iter(filter.params_with(&[])))?;
+    while let Some(row) = rows.next()? {
+        let index: i64 = row.get(0)?;
+        let Some(bucket) = calibration.get_mut(index as usize) else {
+            continue;
+        };
+        let count: i64 = row.get(1)?;
+        bucket.count = count;
+        bucket.avg_confidence = row.get(2)?;
+        bucket.match_rate = rate(row.get(3)?, count);
+        bucket.acceptance_rate = rate(row.get(4)?, row.get(5)?);
+    }
+
+    let total_recommendations = by_type.iter().map(|t| t.total).sum();
+
+    Ok(RecommendationStatistics {
+        total_recommendations,
+        by_type,
+        calibration,
+        top_rejected: top_contents_by_outcome(conn, filter, "rejected", top_n)?,
+        top_modified: top_contents_by_outcome(conn, filter, "modified", top_n)?,
+    })
+}
+
+#[command]
+pub async fn get_recommendation_statistics(
+    app: AppHandle,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    llm_model: Option<String>,
+    top_n: Option<u32>,
+    bucket_count: Option<u32>,
+    source_workstation: Option<String>,
+) -> Result<RecommendationStatistics, String> {
+    let bucket_count = bucket_count.unwrap_or(10).clamp(1, 100);
+    let top_n = top_n.unwrap_or(10);
+
+    let mut filter = QueryFilter::default();
+    filter
+        .date_range("created_at", start_date, end_date)
+        .push_opt("llm_model = ?", llm_model);
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    filter.source(source_workstation, &workstation::local(&conn)?);
+    recommendation_statistics(&conn, &filter, top_n, bucket_count).map_err(|e| e.to_string())
+}
+
+// Modification Analytics
+
+// Longest fragment kept when counting common insertions/deletions
+const MAX_FRAGMENT_CHARS: usize = 50;
+
+fn is_separator(c: char) -> bool {
+    matches!(
+        c,
+        '，' | '。' | '；' | '、' | '：' | '！' | '？' | ',' | '.' | ';' | ':' | '!' | '?' | '\n'
+    )
+}
+
+// Clause-level segments: record text is separated by Chinese/ASCII punctuation
+fn split_segments(text: &str) -> Vec<&str> {
+    text.split(is_separator)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// Trim punctuation/whitespace so "，咳嗽" and "咳嗽" count as the same fragment
+fn normalize_fragment(fragment: &str) -> Option<String> {
+    let trimmed = fragment.trim_matches(|c: char| c.is_whitespace() || is_separator(c));
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.chars().take(MAX_FRAGMENT_CHARS).collect())
+}
+
+// Levenshtein distance over characters: each insertion, deletion or
+// substitution costs 1. Two rows of the table at a time, so memory stays
+// linear in the shorter text.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+
+    let mut previous: Vec<usize> = (0..=short.len()).collect();
+    let mut current = vec![0; short.len() + 1];
+    for (i, lc) in long.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, sc) in short.iter().enumerate() {
+            let substitution = previous[j] + usize::from(lc != sc);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[short.len()]
+}
+
+/// Character-level diff of one modification.
+///
+/// The inserted and deleted characters and fragments come from the Myers
+/// diff; the edit distance is the Levenshtein distance, so replacing one
+/// character counts 1.
+pub fn diff_modification(
+    feedback_id: &str,
+    target_type: &str,
+    original: &str,
+    modified: &str,
+    inserted_fragments: &mut Vec<String>,
+    deleted_fragments: &mut Vec<String>,
+) -> ModificationDiff {
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .diff_chars(original, modified);
+    let old_chars = diff.old_slices();
+    let new_chars = diff.new_slices();
+
+    let edit_distance = levenshtein(original, modified);
+    let mut inserted_chars = 0;
+    let mut deleted_chars = 0;
+    for op in diff.ops() {
+        if matches!(op, DiffOp::Equal { .. }) {
+            continue;
+        }
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        deleted_chars += old_range.len();
+        inserted_chars += new_range.len();
+        deleted_fragments.extend(normalize_fragment(&old_chars[old_range].concat()));
+        inserted_fragments.extend(normalize_fragment(&new_chars[new_range].concat()));
+    }
+
+    let original_segments = split_segments(original);
+    let modified_segments = split_segments(modified);
+    let mut inserted_segments = Vec::new();
+    let mut deleted_segments = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, &original_segments, &modified_segments) {
+        if !matches!(op, DiffOp::Equal { .. }) {
+            deleted_segments.extend(
+                original_segments[op.old_range()]
+                    .iter()
+                    .map(|s| s.to_string()),
+            );
+            inserted_segments.extend(
+                modified_segments[op.new_range()]
+                    .iter()
+                    .map(|s| s.to_string()),
+            );
+        }
+    }
+
+    let original_length = old_chars.len();
+    let modified_length = new_chars.len();
+    let longest = original_length.max(modified_length);
+
+    ModificationDiff {
+        feedback_id: feedback_id.to_string(),
+        target_type: target_type.to_string(),
+        original_length,
+        modified_length,
+        edit_distance,
+        normalized_distance: if longest > 0 {
+            edit_distance as f64 / longest as f64
+        } else {
+            0.0
+        },
+        inserted_chars,
+        deleted_chars,
+        inserted_segments,
+        deleted_segments,
+    }
+}
+
+fn top_fragments(fragments: Vec<String>, limit: usize) -> Vec<FragmentCount> {
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for fragment in fragments {
+        *counts.entry(fragment).or_default() += 1;
+    }
+    let mut ranked: Vec<FragmentCount> = counts
+        .into_iter()
+        .map(|(text, count)| FragmentCount { text, count })
+        .collect();
+    ranked.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+    ranked.truncate(limit);
+    ranked
+}
+
+#[derive(Default)]
+struct TargetAccumulator {
+    count: i64,
+    unchanged: i64,
+    edit_distance: usize,
+    normalized_distance: f64,
+    inserted_chars: usize,
+    deleted_chars: usize,
+    insertions: Vec<String>,
+    deletions: Vec<String>,
+}
+
+// One modified feedback: (feedback_id, target_type, original, modified)
+type ModifiedFeedback = (String, String, String, String);
+
+// Read the rows up front so the diffing runs without holding the connection
+pub fn load_modifications(
+    conn: &Connection,
+    filter: &QueryFilter,
+) -> rusqlite::Result<Vec<ModifiedFeedback>> {
+    let query = format!(
+        "SELECT feedback_id, target_type, original_value, modified_value
+         FROM feedbacks
+         {}
+         ORDER BY created_at",
+        filter.where_sql()
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(params_from_iter(filter.params_with(&[])), |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    })?;
+    rows.collect()
+}
+
+pub fn modification_analysis(
+    modifications: Vec<ModifiedFeedback>,
+    top_n: usize,
+    include_items: bool,
+) -> ModificationAnalysis {
+    let mut by_target: BTreeMap<String, TargetAccumulator> = BTreeMap::new();
+    let mut items = Vec::new();
+    let mut total_modified = 0;
+
+    for (feedback_id, target_type, original, modified) in modifications {
+        let acc = by_target.entry(target_type.clone()).or_default();
+        let diff = diff_modification(
+            &feedback_id,
+            &target_type,
+            &original,
+            &modified,
+            &mut acc.insertions,
+            &mut acc.deletions,
+        );
+
+        total_modified += 1;
+        acc.count += 1;
+        if diff.edit_distance == 0 {
+            acc.unchanged += 1;
+        }
+        acc.edit_distance += diff.edit_distance;
+        acc.normalized_distance += diff.normalized_distance;
+        acc.inserted_chars += diff.inserted_chars;
+        acc.deleted_chars += diff.deleted_chars;
+
+        if include_items {
+            items.push(diff);
+        }
+    }
+
+    let by_target_type = by_target
+        .into_iter()
+        .map(|(target_type, acc)| {
+            let count = acc.count.max(1) as f64;
+            ModificationTargetSummary {
+                target_type,
+                count: acc.count,
+                unchanged_count: acc.unchanged,
+                avg_edit_distance: acc.edit_distance as f64 / count,
+                avg_normalized_distance: acc.normalized_distance / count,
+                total_inserted_chars: acc.inserted_chars as i64,
+                total_deleted_chars: acc.deleted_chars as i64,
+                top_insertions: top_fragments(acc.insertions, top_n),
+                top_deletions: top_fragments(acc.deletions, top_n),
+            }
+        })
+        .collect();
+
+    ModificationAnalysis {
+        total_modified,
+        by_target_type,
+        items,
+    }
+}
+
+#[command]
+pub async fn get_modification_analysis(
+    app: AppHandle,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    target_type: Option<String>,
+    top_n: Option<u32>,
+    include_items: Option<bool>,
+    source_workstation: Option<String>,
+) -> Result<ModificationAnalysis, String> {
+    if let Some(t) = &target_type {
+        TargetType::parse(t)?;
+    }
+
+    let mut filter = QueryFilter::default();
+    filter
+        .push("feedback_type = ?", "modified".to_string())
+        .date_range("created_at", start_date, end_date)
+        .push_opt("target_type = ?", target_type);
+    filter
+        .clauses
+        .push("original_value IS NOT NULL".to_string());
+    filter
+        .clauses
+        .push("modified_value IS NOT NULL".to_string());
+
+    // Diffing every modification is CPU-bound; keep it off the async workers
+    tauri::async_runtime::spawn_blocking(move || {
+        let modifications = {
+            let db = app.state::<DbConnection>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            filter.source(source_workstation, &workstation::local(&conn)?);
+            load_modifications(&conn, &filter).map_err(|e| e.to_string())?
+        };
+        Ok(modification_analysis(
+            modifications,
+            top_n.unwrap_or(20) as usize,
+            include_items.unwrap_or(false),
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Operation Analytics
+
+const FUNNELS_SETTING: &str = "operation_funnels";
+
+// Used until funnels are configured with set_operation_funnels
+fn default_funnels() -> Vec<FunnelDefinition> {
+    vec![FunnelDefinition {
+        name: "consultation".to_string(),
+        steps: vec![
+            "start_consultation".to_string(),
+            "generate_record".to_string(),
+            "complete_consultation".to_string(),
+        ],
+    }]
+}
+
+fn load_funnels(conn: &Connection) -> Result<Vec<FunnelDefinition>, String> {
+    let stored: Option<Vec<FunnelDefinition>> = settings::load(conn, FUNNELS_SETTING)?;
+    Ok(stored.unwrap_or_else(default_funnels))
+}
+
+pub fn operation_statistics(
+    conn: &Connection,
+    filter: &QueryFilter,
+) -> rusqlite::Result<Vec<OperationStatistics>> {
+    let query = format!(
+        "SELECT operation_type, operation_name,
+                COUNT(*),
+                SUM(CASE WHEN success = 0 OR operation_type = 'error' THEN 1 ELSE 0 END),
+                AVG(duration_ms),
+                MAX(duration_ms)
+         FROM operation_logs
+         {}
+         GROUP BY operation_type, operation_name
+         ORDER BY COUNT(*) DESC, operation_name",
+        filter.where_sql()
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(params_from_iter(filter.params_with(&[])), |row| {
+        let total: i64 = row.get(2)?;
+        let error_count: i64 = row.get(3)?;
+        Ok(OperationStatistics {
+            operation_type: row.get(0)?,
+            operation_name: row.get(1)?,
+            total,
+            error_count,
+            error_rate: rate(error_count, total),
+            avg_duration_ms: row.get(4)?,
+            max_duration_ms: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+// Logs of every session in scope, ordered by session then time
+fn for_each_session_log(
+    conn: &Connection,
+    filter: &QueryFilter,
+    mut f: impl FnMut(&str, ClickPathStep),
+) -> rusqlite::Result<()> {
+    let query = format!(
+        "SELECT session_id, operation_type, operation_name, success, duration_ms, created_at
+         FROM operation_logs
+         {}
+         ORDER BY session_id, created_at, rowid",
+        filter.where_sql()
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let mut rows = stmt.query(params_from_iter(filter.params_with(&[])))?;
+    while let Some(row) = rows.next()? {
+        let session_id: String = row.get(0)?;
+        let step = ClickPathStep {
+            operation_type: row.get(1)?,
+            operation_name: row.get(2)?,
+            success: row.get(3)?,
+            duration_ms: row.get(4)?,
+            created_at: row.get(5)?,
+        };
+        f(&session_id, step);
+    }
+    Ok(())
+}
+
+pub fn session_click_paths(
+    conn: &Connection,
+    filter: &QueryFilter,
+    limit: usize,
+) -> rusqlite::Result<Vec<SessionClickPath>> {
+    // Pick the most recent sessions first so only their logs are read
+    let query = format!(
+        "WITH scoped AS (
+            SELECT rowid AS log_rowid, * FROM operation_logs {}
+        ),
+        recent AS (
+            SELECT session_id, MIN(created_at) AS started_at
+            FROM scoped
+            GROUP BY session_id
+            ORDER BY started_at DESC
+            LIMIT ?
+        )
+        SELECT s.session_id, s.operation_type, s.operation_name, s.success,
+               s.duration_ms, s.created_at
+        FROM scoped s
+        JOIN recent r ON r.session_id = s.session_id
+        ORDER BY r.started_at DESC, s.session_id, s.created_at, s.log_rowid",
+        filter.where_sql()
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let mut rows = stmt.query(params_from_iter(
+        filter.params_with(&[SqlValue::Integer(limit as i64)]),
+    ))?;
+
+    let mut paths: Vec<SessionClickPath> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let session_id: String = row.get(0)?;
+        let step = ClickPathStep {
+            operation_type: row.get(1)?,
+            operation_name: row.get(2)?,
+            success: row.get(3)?,
+            duration_ms: row.get(4)?,
+            created_at: row.get(5)?,
+        };
+        match paths.last_mut() {
+            Some(path) if path.session_id == session_id => path.steps.push(step),
+            _ => paths.push(SessionClickPath {
+                session_id,
+                steps: vec![step],
+            }),
+        }
+    }
+    Ok(paths)
+}
+
+/// Count how many sessions reach each funnel step.
+///
+/// Steps must happen in order within one session: a session reaches step N
+/// when a successful log named `steps[N]` follows the time it reached step N-1.
+pub fn funnel_report(
+    conn: &Connection,
+    filter: &QueryFilter,
+    funnel: &FunnelDefinition,
+) -> rusqlite::Result<FunnelReport> {
+    let step_count = funnel.steps.len();
+    let mut reached = vec![0i64; step_count];
+    let mut elapsed = vec![0i64; step_count];
+
+    // Progress of the session currently being scanned: (steps reached, time of last step)
+    let mut current: Option<(String, usize, i64)> = None;
+    let mut finish = |progress: &Option<(String, usize, i64)>| {
+        if let Some((_, depth, _)) = progress {
+            for count in reached.iter_mut().take(*depth) {
+                *count += 1;
+            }
+        }
+    };
+
+    let mut total_sessions = 0;
+    for_each_session_log(conn, filter, |session_id, step| {
+        if current.as_ref().map(|(id, _, _)| id.as_str()) != Some(session_id) {
+            finish(&current);
+            current = Some((session_id.to_string(), 0, 0));
+            total_sessions += 1;
+        }
+        let Some((_, depth, last_at)) = current.as_mut() else {
+            return;
+        };
+        if *depth < step_count && step.success && step.operation_name == funnel.steps[*depth] {
+            if *depth > 0 {
+                elapsed[*depth] += step.created_at - *last_at;
+            }
+            *depth += 1;
+            *last_at = step.created_at;
+        }
+    })?;
+    finish(&current);
+
+    let start = reached.first().copied().unwrap_or(0);
+    let steps = funnel
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let previous = if i == 0 {
+                total_sessions
+            } else {
+                reached[i - 1]
+            };
+            FunnelStepResult {
+                operation_name: name.clone(),
+                sessions: reached[i],
+                conversion_from_previous: rate(reached[i], previous),
+                conversion_from_start: rate(reached[i], start),
+                avg_ms_from_previous: (i > 0 && reached[i] > 0)
+                    .then(|| elapsed[i] as f64 / reached[i] as f64),
+            }
+        })
+        .collect();
+
+    Ok(FunnelReport {
+        name: funnel.name.clone(),
+        total_sessions,
+        steps,
+    })
+}
+
+#[command]
+pub async fn get_operation_statistics(
+    app: AppHandle,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    operation_type: Option<String>,
+    source_workstation: Option<String>,
+) -> Result<Vec<OperationStatistics>, String> {
+    if let Some(t) = &operation_type {
+        OperationType::parse(t)?;
+    }
+
+    let mut filter = QueryFilter::default();
+    filter
+        .date_range("created_at", start_date, end_date)
+        .push_opt("operation_type = ?", operation_type);
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    filter.source(source_workstation, &workstation::local(&conn)?);
+    operation_statistics(&conn, &filter).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_session_click_paths(
+    app: AppHandle,
+    session_id: Option<String>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    limit: Option<u32>,
+    source_workstation: Option<String>,
+) -> Result<Vec<SessionClickPath>, String> {
+    let mut filter = QueryFilter::default();
+    filter.clauses.push("session_id IS NOT NULL".to_string());
+    filter
+        .push_opt("session_id = ?", session_id)
+        .date_range("created_at", start_date, end_date);
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    filter.source(source_workstation, &workstation::local(&conn)?);
+    session_click_paths(&conn, &filter, limit.unwrap_or(50) as usize).map_err(|e| e.to_string())
+}
+
+#[command]
+pub async fn get_operation_funnels(app: AppHandle) -> Result<Vec<FunnelDefinition>, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_funnels(&conn)
+}
+
+#[command]
+pub async fn set_operation_funnels(
+    app: AppHandle,
+    funnels: Vec<FunnelDefinition>,
+) -> Result<(), String> {
+    for funnel in &funnels {
+        if funnel.name.trim().is_empty() {
+            return Err("Funnel name must not be empty".to_string());
+        }
+        if funnel.steps.is_empty() || funnel.steps.iter().any(|s| s.trim().is_empty()) {
+            return Err(format!(
+                "Funnel '{}' needs at least one named step",
+                funnel.name
+            ));
+        }
+    }
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::store(&conn, FUNNELS_SETTING, &funnels)
+}
+
+// Evaluate a configured funnel by name, or an ad-hoc list of steps
+#[command]
+pub async fn get_funnel_statistics(
+    app: AppHandle,
+    funnel_name: Option<String>,
+    steps: Option<Vec<String>>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    source_workstation: Option<String>,
+) -> Result<FunnelReport, String> {
+    let mut filter = QueryFilter::default();
+    filter.clauses.push("session_id IS NOT NULL".to_string());
+    filter.date_range("created_at", start_date, end_date);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        filter.source(source_workstation, &workstation::local(&conn)?);
+
+        let funnel = match (steps, funnel_name) {
+            (Some(steps), name) if !steps.is_empty() => FunnelDefinition {
+                name: name.unwrap_or_else(|| "custom".to_string()),
+                steps,
+            },
+            (_, name) => {
+                let funnels = load_funnels(&conn)?;
+                match name {
+                    Some(name) => funnels
+                        .into_iter()
+                        .find(|f| f.name == name)
+                        .ok_or_else(|| format!("Funnel '{}' is not configured", name))?,
+                    None => funnels
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| "No funnels are configured".to_string())?,
+                }
+            }
+        };
+
+        funnel_report(&conn, &filter, &funnel).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Template Usage Analytics
+
+/// How often each template symptom and field was used, and how often skipped.
+///
+/// A symptom counts as skipped in a session when it was shown but none of its
+/// fields were answered; a field when it was shown and never answered.
+/// `templates` supplies names and labels and the symptoms never shown.
+pub fn template_usage_statistics(
+    conn: &Connection,
+    filter: &QueryFilter,
+    templates: &[SymptomTemplate],
+) -> rusqlite::Result<TemplateUsageStatistics> {
+    let cte = format!(
+        "WITH scoped AS (SELECT * FROM template_usage {})",
+        filter.where_sql()
+    );
+    let params = || params_from_iter(filter.params_with(&[]));
+
+    let total_sessions: i64 = conn.query_row(
+        &format!("{} SELECT COUNT(DISTINCT session_id) FROM scoped", cte),
+        params(),
+        |row| row.get(0),
+    )?;
+
+    // Time spent on a symptom: from when it was shown to its last first answer
+    let mut symptom_times: HashMap<String, f64> = HashMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "{},
+        per_session AS (
+            SELECT symptom_key, MAX(answered_at) - MIN(shown_at) AS duration
+            FROM scoped
+            GROUP BY session_id, symptom_key
+        )
+        SELECT symptom_key, AVG(duration)
+        FROM per_session
+        GROUP BY symptom_key",
+        cte
+    ))?;
+    let mut rows = stmt.query(params())?;
+    while let Some(row) = rows.next()? {
+        if let Some(avg) = row.get::<_, Option<f64>>(1)? {
+            symptom_times.insert(row.get(0)?, avg);
+        }
+    }
+
+    let template_of = |key: &str| templates.iter().find(|t| t.key == key);
+    let mut symptoms: BTreeMap<String, SymptomUsageStatistics> = BTreeMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "{}
+        SELECT symptom_key, field_key, COUNT(*), COUNT(answered_at), AVG(answered_at - shown_at)
+        FROM scoped
+        GROUP BY symptom_key, field_key",
+        cte
+    ))?;
+    let mut rows = stmt.query(params())?;
+    while let Some(row) = rows.next()? {
+        let symptom_key: String = row.get(0)?;
+        let field_key: String = row.get(1)?;
+        let shown: i64 = row.get(2)?;
+        let answered: i64 = row.get(3)?;
+
+        let symptom =
+            symptoms
+                .entry(symptom_key.clone())
+                .or_insert_with(|| SymptomUsageStatistics {
+                    name: template_of(&symptom_key).map(|t| t.name.clone()),
+                    avg_answer_ms: symptom_times.get(&symptom_key).copied(),
+                    symptom_key: symptom_key.clone(),
+                    sessions: 0,
+                    answered_sessions: 0,
+                    usage_rate: None,
+                    skip_rate: None,
+                    fields: Vec::new(),
+                });
+        if field_key.is_empty() {
+            symptom.sessions = shown;
+            symptom.answered_sessions = answered;
+            symptom.usage_rate = rate(shown, total_sessions);
+            symptom.skip_rate = rate(shown - answered, shown);
+            continue;
+        }
+        let label = template_of(&symptom_key)
+            .into_iter()
+            .flat_map(|t| &t.config.sections)
+            .flat_map(|section| &section.fields)
+            .find(|field| field.key == field_key)
+            .map(|field| field.label.clone());
+        symptom.fields.push(FieldUsageStatistics {
+            field_key,
+            label,
+            shown,
+            answered,
+            skip_rate: rate(shown - answered, shown),
+            avg_answer_ms: row.get(4)?,
+        });
+    }
+
+    // Fields in template order, ones no longer in the template last
+    for symptom in symptoms.values_mut() {
+        let order: Vec<&str> = template_of(&symptom.symptom_key)
+            .into_iter()
+            .flat_map(|t| &t.config.sections)
+            .flat_map(|section| &section.fields)
+            .map(|field| field.key.as_str())
+            .collect();
+        symptom.fields.sort_by_key(|f| {
+            order
+                .iter()
+                .position(|key| *key == f.field_key)
+                .unwrap_or(usize::MAX)
+        });
+    }
+
+    let unused_symptoms = templates
+        .iter()
+        .filter(|t| !symptoms.contains_key(&t.key))
+        .map(|t| t.key.clone())
+        .collect();
+    let mut symptoms: Vec<SymptomUsageStatistics> = symptoms.into_values().collect();
+    symptoms.sort_by_key(|s| std::cmp::Reverse(s.sessions));
+
+    Ok(TemplateUsageStatistics {
+        total_sessions,
+        symptoms,
+        unused_symptoms,
+    })
+}
+
+#[command]
+pub async fn get_template_usage_statistics(
+    app: AppHandle,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    source_workstation: Option<String>,
+) -> Result<TemplateUsageStatistics, String> {
+    let mut filter = QueryFilter::default();
+    filter.date_range("created_at", start_date, end_date);
+
+    // Names, labels and unused symptoms come from the templates in effect
+    let templates = match app.try_state::<TemplateRepository>() {
+        Some(repository) => repository.load().map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    filter.source(source_workstation, &workstation::local(&conn)?);
+    template_usage_statistics(&conn, &filter, &templates).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein_counts_substitutions_once() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("咳嗽", ""), 2);
+        assert_eq!(levenshtein("", "咳嗽"), 2);
+        assert_eq!(levenshtein("发热3天", "发热5天"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("sitting", "kitten"), 3);
+    }
+
+    #[test]
+    fn diff_modification_reports_levenshtein_distance() {
+        let (mut inserted, mut deleted) = (Vec::new(), Vec::new());
+        let diff = diff_modification(
+            "f1",
+            "diagnosis",
+            "发热3天，咳嗽",
+            "发热5天，咳嗽，咳痰",
+            &mut inserted,
+            &mut deleted,
+        );
+        assert_eq!(diff.edit_distance, 4);
+        assert_eq!(diff.original_length, 7);
+        assert_eq!(diff.modified_length, 10);
+        assert_eq!(diff.deleted_chars, 1);
+        assert_eq!(diff.inserted_chars, 4);
+        assert_eq!(deleted, vec!["3"]);
+        assert_eq!(inserted, vec!["5", "咳痰"]);
+    }
+}
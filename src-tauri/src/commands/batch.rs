@@ -0,0 +1,326 @@
+use rusqlite::Connection;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager};
+
+use super::cost::{carries_tokens, check_budgets};
+use super::feedback::{current_timestamp, write_item, DbConnection};
+use crate::db::error::FeedbackError;
+use crate::db::models::{BatchWriteReport, WriteItem, WriteItemResult};
+
+// Buffered writes are flushed at least this often...
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+// ...or as soon as this many are pending
+const FLUSH_SIZE: usize = 100;
+// While flushes keep failing, the oldest writes beyond this many are dropped
+const MAX_PENDING: usize = 10_000;
+
+/// Write all items in one transaction.
+///
+/// Each item runs in its own savepoint, so a rejected item (bad enum value,
+/// unknown session, ...) is reported in its result without rolling back the
+/// others.
+pub fn write_batch(
+    conn: &mut Connection,
+    items: &[WriteItem],
+) -> Result<BatchWriteReport, FeedbackError> {
+    let mut tx = conn.transaction()?;
+    let mut results = Vec::with_capacity(items.len());
+    let mut failed = 0;
+
+    for (index, item) in items.iter().enumerate() {
+        let sp = tx.savepoint()?;
+        match write_item(&sp, item) {
+            Ok(id) => {
+                sp.commit()?;
+                results.push(WriteItemResult {
+                    index,
+                    id: Some(id),
+                    error: None,
+                });
+            }
+            Err(e) => {
+                // Dropping the savepoint rolls the item back
+                drop(sp);
+                failed += 1;
+                results.push(WriteItemResult {
+                    index,
+                    id: None,
+                    error: serde_json::to_value(&e).ok(),
+                });
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(BatchWriteReport {
+        written: items.len() - failed,
+        failed,
+        results,
+        dropped: 0,
+    })
+}
+
+#[command]
+pub async fn save_batch(
+    app: AppHandle,
+    items: Vec<WriteItem>,
+) -> Result<BatchWriteReport, FeedbackError> {
+    let db = app.state::<DbConnection>();
+    let mut conn = db.0.lock()?;
+    let report = write_batch(&mut conn, &items)?;
+    if items.iter().any(carries_tokens) {
+        check_budgets(&app, &conn);
+    }
+    Ok(report)
+}
+
+// Buffered Writer
+
+/// Pending writes queued by `enqueue_writes`, flushed by the worker thread
+/// started in `start_write_buffer`.
+///
+/// Enqueueing only takes the buffer lock, never the database mutex, so
+/// high-frequency logs and metrics don't contend with the UI's own queries.
+/// The buffer holds at most `MAX_PENDING` writes.
+#[derive(Default)]
+pub struct WriteBuffer {
+    pending: Mutex<Vec<WriteItem>>,
+    wake: Condvar,
+    // Dropped by the cap since the last flush report
+    dropped: AtomicUsize,
+}
+
+impl WriteBuffer {
+    fn take(&self) -> Vec<WriteItem> {
+        match self.pending.lock() {
+            Ok(mut pending) => std::mem::take(&mut *pending),
+            Err(e) => {
+                eprintln!("[Feedback] Write buffer lock poisoned: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    // Put back items whose flush failed, ahead of anything queued meanwhile
+    fn restore(&self, mut items: Vec<WriteItem>) {
+        match self.pending.lock() {
+            Ok(mut pending) => {
+                items.append(&mut pending);
+                *pending = items;
+                self.enforce_cap(&mut pending);
+            }
+            Err(e) => eprintln!(
+                "[Feedback] Write buffer lock poisoned, dropped {} writes: {}",
+                items.len(),
+                e
+            ),
+        }
+    }
+
+    // Drop the oldest writes beyond MAX_PENDING, e.g. while the database is unavailable
+    fn enforce_cap(&self, pending: &mut Vec<WriteItem>) {
+        let excess = pending.len().saturating_sub(MAX_PENDING);
+        if excess == 0 {
+            return;
+        }
+        pending.drain(..excess);
+        let total = self.dropped.fetch_add(excess, Ordering::Relaxed) + excess;
+        eprintln!(
+            "[Feedback] Write buffer full, dropped {} oldest writes ({} since the last flush)",
+            excess, total
+        );
+    }
+
+    fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+// A batch that fails as a whole (database lock, BEGIN, COMMIT) goes back into
+// the buffer for the next flush; only items rejected one by one are dropped
+fn flush(app: &AppHandle, buffer: &WriteBuffer) -> Result<BatchWriteReport, FeedbackError> {
+    let items = buffer.take();
+    if items.is_empty() {
+        return Ok(BatchWriteReport {
+            written: 0,
+            failed: 0,
+            results: Vec::new(),
+            dropped: buffer.take_dropped(),
+        });
+    }
+
+    let db = app.state::<DbConnection>();
+    let mut conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(e) => {
+            buffer.restore(items);
+            return Err(e.into());
+        }
+    };
+    let mut report = match write_batch(&mut conn, &items) {
+        Ok(report) => report,
+        Err(e) => {
+            buffer.restore(items);
+            return Err(e);
+        }
+    };
+    if items.iter().any(carries_tokens) {
+        check_budgets(app, &conn);
+    }
+    if report.failed > 0 {
+        eprintln!(
+            "[Feedback] Dropped {} of {} buffered writes",
+            report.failed,
+            items.len()
+        );
+    }
+    report.dropped = buffer.take_dropped();
+    Ok(report)
+}
+
+pub fn start_write_buffer(app: AppHandle) {
+    app.manage(WriteBuffer::default());
+
+    std::thread::spawn(move || {
+        let buffer = app.state::<WriteBuffer>();
+        loop {
+            // Sleep until the interval passes or an enqueue fills the buffer
+            match buffer.pending.lock() {
+                Ok(pending) => {
+                    let _ = buffer
+                        .wake
+                        .wait_timeout_while(pending, FLUSH_INTERVAL, |p| p.len() < FLUSH_SIZE);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "[Feedback] Write buffer lock poisoned, writer stopped: {}",
+                        e
+                    );
+                    return;
+                }
+            }
+
+            if let Err(e) = flush(&app, &buffer) {
+                eprintln!("[Feedback] Failed to flush buffered writes: {}", e);
+                // The items are back in the buffer; a full buffer would
+                // otherwise retry without waiting
+                std::thread::sleep(FLUSH_INTERVAL);
+            }
+        }
+    });
+}
+
+/// Write whatever is still buffered. Called when the main window closes and
+/// when the app exits, so the last interval of logs and metrics is not lost.
+pub fn flush_pending(app: &AppHandle) {
+    let Some(buffer) = app.try_state::<WriteBuffer>() else {
+        return;
+    };
+    match flush(app, &buffer) {
+        Ok(report) => {
+            if report.written > 0 {
+                println!("[Feedback] Flushed {} buffered writes", report.written);
+            }
+            if report.dropped > 0 {
+                eprintln!(
+                    "[Feedback] {} buffered writes were dropped while the buffer was full",
+                    report.dropped
+                );
+            }
+        }
+        Err(e) => eprintln!("[Feedback] Failed to flush buffered writes: {}", e),
+    }
+}
+
+/// Queue writes for the background flush and return immediately with the
+/// number of pending items. Items without `createdAt` are stamped now, so the
+/// flush delay does not shift their timestamps.
+#[command]
+pub async fn enqueue_writes(app: AppHandle, mut items: Vec<WriteItem>) -> Result<usize, String> {
+    let buffer = app
+        .try_state::<WriteBuffer>()
+        .ok_or_else(|| "Write buffer is not running".to_string())?;
+
+    let now = current_timestamp();
+    for item in &mut items {
+        item.created_at_mut().get_or_insert(now);
+    }
+
+    let mut pending = buffer.pending.lock().map_err(|e| e.to_string())?;
+    pending.extend(items);
+    buffer.enforce_cap(&mut pending);
+    let count = pending.len();
+    if count >= FLUSH_SIZE {
+        buffer.wake.notify_one();
+    }
+    Ok(count)
+}
+
+// Write everything pending now, e.g. before the window closes
+#[command]
+pub async fn flush_writes(app: AppHandle) -> Result<BatchWriteReport, FeedbackError> {
+    let buffer = app
+        .try_state::<WriteBuffer>()
+        .ok_or_else(|| FeedbackError::Database("Write buffer is not running".to_string()))?;
+    flush(&app, &buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(name: &str) -> WriteItem {
+        WriteItem::Log {
+            id: None,
+            session_id: None,
+            operation_type: "button_click".to_string(),
+            operation_name: name.to_string(),
+            details: None,
+            success: true,
+            duration_ms: None,
+            created_at: None,
+        }
+    }
+
+    fn name(item: &WriteItem) -> &str {
+        match item {
+            WriteItem::Log { operation_name, .. } => operation_name,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn restore_keeps_failed_items_ahead_of_new_ones() {
+        let buffer = WriteBuffer::default();
+        buffer.pending.lock().unwrap().push(log("queued"));
+        buffer.restore(vec![log("failed")]);
+
+        let pending = buffer.take();
+        assert_eq!(
+            pending.iter().map(name).collect::<Vec<_>>(),
+            ["failed", "queued"]
+        );
+        assert_eq!(buffer.take_dropped(), 0);
+    }
+
+    #[test]
+    fn restore_drops_oldest_items_beyond_the_cap() {
+        let buffer = WriteBuffer::default();
+        buffer.pending.lock().unwrap().push(log("newest"));
+
+        // A batch that keeps failing grows past the cap
+        let failed: Vec<WriteItem> = (0..MAX_PENDING + 5).map(|i| log(&i.to_string())).collect();
+        buffer.restore(failed);
+
+        let pending = buffer.take();
+        assert_eq!(pending.len(), MAX_PENDING);
+        assert_eq!(name(&pending[0]), "6");
+        assert_eq!(name(pending.last().unwrap()), "newest");
+
+        // Reported once, then reset
+        assert_eq!(buffer.take_dropped(), 6);
+        assert_eq!(buffer.take_dropped(), 0);
+    }
+}
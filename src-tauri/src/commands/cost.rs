@@ -0,0 +1,494 @@
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use tauri::{command, AppHandle, Emitter, Manager};
+
+use super::analytics::QueryFilter;
+use super::feedback::DbConnection;
+use crate::db::models::{
+    BudgetStatus, BudgetWarning, CostBreakdown, CostReport, CostSettings, ModelPrice, WriteItem,
+};
+use crate::db::{settings, workstation};
+
+const COST_SETTING: &str = "llm_cost";
+const BUDGET_ALERTS_SETTING: &str = "llm_budget_alerts";
+const BUDGET_WARNING_EVENT: &str = "llm-budget-warning";
+
+// One row per LLM call: assistant messages count as output, the user's and
+// system prompts as input; recommendations carry both.
+const USAGE_CTE: &str = "usage AS (
+    SELECT m.session_id, m.created_at, m.llm_model AS model, m.source_workstation,
+           CASE WHEN m.role = 'assistant' THEN 0 ELSE m.token_count END AS input_tokens,
+           CASE WHEN m.role = 'assistant' THEN m.token_count ELSE 0 END AS output_tokens
+    FROM messages m
+    WHERE m.token_count IS NOT NULL
+    UNION ALL
+    SELECT r.session_id, r.created_at, r.llm_model, r.source_workstation,
+           COALESCE(r.prompt_tokens, 0), COALESCE(r.completion_tokens, 0)
+    FROM recommendations r
+    WHERE r.prompt_tokens IS NOT NULL OR r.completion_tokens IS NOT NULL
+)";
+
+// Local calendar day / month of a millisecond timestamp
+const LOCAL_DAY_SQL: &str = "date(u.created_at / 1000, 'unixepoch', 'localtime')";
+const LOCAL_MONTH_SQL: &str = "strftime('%Y-%m', u.created_at / 1000, 'unixepoch', 'localtime')";
+
+// Budget warnings already emitted, so each threshold fires once per period
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct BudgetAlertState {
+    day: String,
+    day_thresholds: Vec<u32>,
+    month: String,
+    month_thresholds: Vec<u32>,
+}
+
+fn load_settings(conn: &Connection) -> CostSettings {
+    settings::load(conn, COST_SETTING).unwrap_or_default()
+}
+
+// Exact names win over wildcards; among wildcards the longest prefix wins
+fn find_price<'a>(prices: &'a [ModelPrice], model: &str) -> Option<&'a ModelPrice> {
+    let model = model.to_lowercase();
+    prices
+        .iter()
+        .find(|p| p.model.to_lowercase() == model)
+        .or_else(|| {
+            prices
+                .iter()
+                .filter_map(|p| {
+                    let prefix = p.model.strip_suffix('*')?.to_lowercase();
+                    model.starts_with(&prefix).then_some((prefix.len(), p))
+                })
+                .max_by_key(|(len, _)| *len)
+                .map(|(_, p)| p)
+        })
+}
+
+impl CostBreakdown {
+    fn add(&mut self, price: Option<&ModelPrice>, input_tokens: i64, output_tokens: i64) {
+        self.input_tokens += input_tokens;
+        self.output_tokens += output_tokens;
+        match price {
+            Some(p) => {
+                self.cost += input_tokens as f64 / 1000.0 * p.input_per_1k
+                    + output_tokens as f64 / 1000.0 * p.output_per_1k
+            }
+            None => self.unpriced_tokens += input_tokens + output_tokens,
+        }
+    }
+}
+
+fn sorted(groups: BTreeMap<String, CostBreakdown>) -> Vec<CostBreakdown> {
+    groups
+        .into_iter()
+        .map(|(key, mut breakdown)| {
+            breakdown.key = key;
+            breakdown
+        })
+        .collect()
+}
+
+/// Token usage priced with the configured table, grouped four ways.
+///
+/// Days are local calendar days. The doctor comes from `metadata.doctor` on
+/// the session (sent by the HIS with the consultation or set by the
+/// frontend); sessions without one are grouped under an empty key.
+pub fn cost_report(
+    conn: &Connection,
+    filter: &QueryFilter,
+    cost_settings: &CostSettings,
+) -> rusqlite::Result<CostReport> {
+    let query = format!(
+        "WITH {usage},
+        scoped AS (SELECT * FROM usage {filter})
+        SELECT {day},
+               COALESCE(u.model, ''),
+               COALESCE(s.session_type, ''),
+               COALESCE(CASE WHEN json_valid(s.metadata) THEN CAST(json_extract(s.metadata, '$.doctor') AS TEXT) END, ''),
+               SUM(u.input_tokens),
+               SUM(u.output_tokens)
+        FROM scoped u
+        LEFT JOIN sessions s ON s.session_id = u.session_id
+        GROUP BY 1, 2, 3, 4",
+        usage = USAGE_CTE,
+        filter = filter.where_sql(),
+        day = LOCAL_DAY_SQL,
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(params_from_iter(filter.params_with(&[])), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+        ))
+    })?;
+
+    let mut total = CostBreakdown::default();
+    let mut by_day = BTreeMap::<String, CostBreakdown>::new();
+    let mut by_model = BTreeMap::<String, CostBreakdown>::new();
+    let mut by_session_type = BTreeMap::<String, CostBreakdown>::new();
+    let mut by_doctor = BTreeMap::<String, CostBreakdown>::new();
+    let mut unpriced_models = BTreeSet::new();
+
+    for row in rows {
+        let (day, model, session_type, doctor, input_tokens, output_tokens) = row?;
+        let price = find_price(&cost_settings.prices, &model);
+        if price.is_none() {
+            unpriced_models.insert(model.clone());
+        }
+        total.add(price, input_tokens, output_tokens);
+        for (groups, key) in [
+            (&mut by_day, day),
+            (&mut by_model, model),
+            (&mut by_session_type, session_type),
+            (&mut by_doctor, doctor),
+        ] {
+            groups
+                .entry(key)
+                .or_default()
+                .add(price, input_tokens, output_tokens);
+        }
+    }
+
+    let mut by_model = sorted(by_model);
+    by_model.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+    let mut by_doctor = sorted(by_doctor);
+    by_doctor.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+
+    Ok(CostReport {
+        currency: cost_settings.currency.clone(),
+        total,
+        by_day: sorted(by_day),
+        by_model,
+        by_session_type: sorted(by_session_type),
+        by_doctor,
+        unpriced_models: unpriced_models.into_iter().collect(),
+        budget: budget_status(conn, cost_settings)?.0,
+    })
+}
+
+// Spend today and this month (local time), with the current day and month keys
+fn budget_status(
+    conn: &Connection,
+    cost_settings: &CostSettings,
+) -> rusqlite::Result<(BudgetStatus, String, String)> {
+    // The millisecond bound lets the created_at indexes skip older rows
+    let (today, month, month_start): (String, String, i64) = conn.query_row(
+        "SELECT date('now', 'localtime'),
+                strftime('%Y-%m', 'now', 'localtime'),
+                CAST(strftime('%s', 'now', 'localtime', 'start of month', 'utc') AS INTEGER) * 1000",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    let query = format!(
+        "WITH {usage}
+        SELECT COALESCE(u.model, ''), {day} = ?1, SUM(u.input_tokens), SUM(u.output_tokens)
+        FROM usage u
+        WHERE u.created_at >= ?3 AND u.source_workstation IS NULL AND {month} = ?2
+        GROUP BY 1, 2",
+        usage = USAGE_CTE,
+        day = LOCAL_DAY_SQL,
+        month = LOCAL_MONTH_SQL,
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let rows = stmt.query_map(params![&today, &month, month_start], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, bool>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let mut daily = CostBreakdown::default();
+    let mut monthly = CostBreakdown::default();
+    for row in rows {
+        let (model, is_today, input_tokens, output_tokens) = row?;
+        let price = find_price(&cost_settings.prices, &model);
+        monthly.add(price, input_tokens, output_tokens);
+        if is_today {
+            daily.add(price, input_tokens, output_tokens);
+        }
+    }
+
+    let status = BudgetStatus {
+        daily_budget: cost_settings.daily_budget,
+        daily_spent: daily.cost,
+        monthly_budget: cost_settings.monthly_budget,
+        monthly_spent: monthly.cost,
+    };
+    Ok((status, today, month))
+}
+
+// Writes that can move spend and so need a budget check
+pub(crate) fn carries_tokens(item: &WriteItem) -> bool {
+    match item {
+        WriteItem::Message { token_count, .. } => token_count.is_some(),
+        WriteItem::Recommendation {
+            prompt_tokens,
+            completion_tokens,
+            ..
+        } => prompt_tokens.is_some() || completion_tokens.is_some(),
+        _ => false,
+    }
+}
+
+/// Emit `llm-budget-warning` for every alert threshold the local spend has
+/// crossed today or this month and not yet warned about.
+///
+/// Called after writes that carry tokens. Failures are logged only; a budget
+/// check must never fail the write itself.
+pub(crate) fn check_budgets(app: &AppHandle, conn: &Connection) {
+    let cost_settings = load_settings(conn);
+    if cost_settings.daily_budget.is_none() && cost_settings.monthly_budget.is_none() {
+        return;
+    }
+
+    let result = (|| {
+        let (status, today, month) =
+            budget_status(conn, &cost_settings).map_err(|e| e.to_string())?;
+        let mut alerts: BudgetAlertState = settings::load(conn, BUDGET_ALERTS_SETTING)?;
+        if alerts.day != today {
+            alerts.day = today.clone();
+            alerts.day_thresholds.clear();
+        }
+        if alerts.month != month {
+            alerts.month = month.clone();
+            alerts.month_thresholds.clear();
+        }
+
+        let mut warnings = Vec::new();
+        for (period, key, budget, spent, fired) in [
+            (
+                "daily",
+                &today,
+                status.daily_budget,
+                status.daily_spent,
+                &mut alerts.day_thresholds,
+            ),
+            (
+                "monthly",
+                &month,
+                status.monthly_budget,
+                status.monthly_spent,
+                &mut alerts.month_thresholds,
+            ),
+        ] {
+            let Some(budget) = budget.filter(|b| *b > 0.0) else {
+                continue;
+            };
+            // Only the highest newly crossed threshold is reported
+            let crossed = cost_settings
+                .alert_thresholds
+                .iter()
+                .copied()
+                .filter(|t| spent >= budget * f64::from(*t) / 100.0 && !fired.contains(t))
+                .collect::<Vec<_>>();
+            if let Some(&threshold) = crossed.iter().max() {
+                fired.extend(crossed);
+                warnings.push(BudgetWarning {
+                    period: period.to_string(),
+                    period_key: key.clone(),
+                    threshold_percent: threshold,
+                    budget,
+                    spent,
+                    currency: cost_settings.currency.clone(),
+                });
+            }
+        }
+
+        if !warnings.is_empty() {
+            settings::store(conn, BUDGET_ALERTS_SETTING, &alerts)?;
+        }
+        Ok::<_, String>(warnings)
+    })();
+
+    match result {
+        Ok(warnings) => {
+            for warning in warnings {
+                println!(
+                    "[Feedback] LLM {} budget {}% reached: {:.2} of {:.2} {}",
+                    warning.period,
+                    warning.threshold_percent,
+                    warning.spent,
+                    warning.budget,
+                    warning.currency
+                );
+                if let Err(e) = app.emit(BUDGET_WARNING_EVENT, &warning) {
+                    eprintln!("[Feedback] Failed to emit budget warning: {}", e);
+                }
+            }
+        }
+        Err(e) => eprintln!("[Feedback] Budget check failed: {}", e),
+    }
+}
+
+// Cost Commands
+
+#[command]
+pub async fn get_cost_settings(app: AppHandle) -> Result<CostSettings, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_settings(&conn))
+}
+
+#[command]
+pub async fn set_cost_settings(app: AppHandle, cost_settings: CostSettings) -> Result<(), String> {
+    if let Some(price) = cost_settings
+        .prices
+        .iter()
+        .find(|p| p.model.trim().is_empty() || p.input_per_1k < 0.0 || p.output_per_1k < 0.0)
+    {
+        return Err(format!("Invalid price entry for model '{}'", price.model));
+    }
+    if cost_settings.daily_budget.is_some_and(|b| b < 0.0)
+        || cost_settings.monthly_budget.is_some_and(|b| b < 0.0)
+    {
+        return Err("Budgets cannot be negative".to_string());
+    }
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::store(&conn, COST_SETTING, &cost_settings)?;
+    // A lowered budget may already be exceeded
+    check_budgets(&app, &conn);
+    Ok(())
+}
+
+#[command]
+pub async fn get_cost_report(
+    app: AppHandle,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    source_workstation: Option<String>,
+) -> Result<CostReport, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let local = workstation::local(&conn)?;
+
+    let mut filter = QueryFilter::default();
+    filter
+        .date_range("created_at", start_date, end_date)
+        .source(source_workstation, &local);
+    cost_report(&conn, &filter, &load_settings(&conn)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::feedback::{insert_session, write_item};
+    use crate::db::models::SessionType;
+
+    fn priced_settings() -> CostSettings {
+        CostSettings {
+            prices: vec![
+                ModelPrice {
+                    model: "deepseek-*".to_string(),
+                    input_per_1k: 1.0,
+                    output_per_1k: 2.0,
+                },
+                ModelPrice {
+                    model: "gpt-4o-mini".to_string(),
+                    input_per_1k: 0.5,
+                    output_per_1k: 1.5,
+                },
+            ],
+            ..CostSettings::default()
+        }
+    }
+
+    fn message(session_id: &str, role: &str, tokens: i32, model: &str) -> WriteItem {
+        WriteItem::Message {
+            id: None,
+            session_id: session_id.to_string(),
+            role: role.to_string(),
+            content: "咳嗽三天".to_string(),
+            images: None,
+            token_count: Some(tokens),
+            llm_model: Some(model.to_string()),
+            latency_ms: Some(800),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn cost_report_prices_stored_messages_and_recommendations() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::run_migrations(&conn).unwrap();
+        let session_id = insert_session(
+            &conn,
+            SessionType::Consultation,
+            None,
+            None,
+            Some(&serde_json::json!({ "doctor": "D01" })),
+        )
+        .unwrap();
+
+        write_item(&conn, &message(&session_id, "user", 2000, "gpt-4o-mini")).unwrap();
+        write_item(
+            &conn,
+            &message(&session_id, "assistant", 400, "gpt-4o-mini"),
+        )
+        .unwrap();
+        write_item(
+            &conn,
+            &WriteItem::Recommendation {
+                id: None,
+                session_id: session_id.clone(),
+                rec_type: "diagnosis".to_string(),
+                content: r#"{"name":"急性上呼吸道感染"}"#.to_string(),
+                matched: true,
+                match_confidence: Some(1.0),
+                prompt_tokens: Some(1000),
+                completion_tokens: Some(500),
+                latency_ms: Some(1200),
+                llm_model: Some("deepseek-chat".to_string()),
+                created_at: None,
+            },
+        )
+        .unwrap();
+
+        let report = cost_report(&conn, &QueryFilter::default(), &priced_settings()).unwrap();
+
+        // gpt-4o-mini: 2000 in, 400 out; deepseek-chat: 1000 in, 500 out
+        let expected_mini = 2.0 * 0.5 + 0.4 * 1.5;
+        let expected_deepseek = 1.0 * 1.0 + 0.5 * 2.0;
+        assert_eq!(report.total.input_tokens, 3000);
+        assert_eq!(report.total.output_tokens, 900);
+        assert_eq!(report.total.unpriced_tokens, 0);
+        assert!((report.total.cost - (expected_mini + expected_deepseek)).abs() < 1e-9);
+        assert!(report.unpriced_models.is_empty());
+
+        let by_model: Vec<(&str, f64)> = report
+            .by_model
+            .iter()
+            .map(|b| (b.key.as_str(), b.cost))
+            .collect();
+        assert_eq!(by_model.len(), 2);
+        assert_eq!(by_model[0].0, "deepseek-chat");
+        assert!((by_model[0].1 - expected_deepseek).abs() < 1e-9);
+        assert!((by_model[1].1 - expected_mini).abs() < 1e-9);
+        assert_eq!(report.by_doctor[0].key, "D01");
+
+        // Written just now, so it all counts toward today's budget
+        assert!((report.budget.daily_spent - report.total.cost).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cost_report_leaves_unknown_models_unpriced() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::run_migrations(&conn).unwrap();
+        let session_id = insert_session(&conn, SessionType::Chat, None, None, None).unwrap();
+        write_item(&conn, &message(&session_id, "assistant", 300, "qwen-max")).unwrap();
+
+        let report = cost_report(&conn, &QueryFilter::default(), &priced_settings()).unwrap();
+        assert_eq!(report.total.cost, 0.0);
+        assert_eq!(report.total.unpriced_tokens, 300);
+        assert_eq!(report.unpriced_models, vec!["qwen-max".to_string()]);
+    }
+}
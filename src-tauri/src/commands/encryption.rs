@@ -0,0 +1,84 @@
+use tauri::{command, AppHandle, Manager};
+
+use crate::db::encryption::{self, EncryptionState, KeySource};
+use crate::db::models::EncryptionStatus;
+
+#[command]
+pub async fn get_encryption_status(app: AppHandle) -> Result<EncryptionStatus, String> {
+    let key_source = app
+        .try_state::<EncryptionState>()
+        .and_then(|state| state.key_source);
+    let key_path = match key_source {
+        Some(KeySource::KeyFile) => {
+            let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+            Some(
+                encryption::key_file_path(&config_dir)
+                    .to_string_lossy()
+                    .to_string(),
+            )
+        }
+        _ => None,
+    };
+
+    Ok(EncryptionStatus {
+        supported: encryption::is_supported(),
+        encrypted: key_source.is_some(),
+        key_source: key_source.map(|s| s.as_str().to_string()),
+        key_path,
+    })
+}
+
+// Rotate the database key: the new key is written next to the old one first so
+// it is never lost, then the database is re-encrypted and the key file swapped.
+#[cfg(feature = "sqlcipher")]
+#[command]
+pub async fn rekey_database(app: AppHandle) -> Result<(), String> {
+    use super::feedback::DbConnection;
+    use crate::db::encryption::DbKey;
+
+    let key_source = app
+        .try_state::<EncryptionState>()
+        .and_then(|state| state.key_source);
+    match key_source {
+        Some(KeySource::KeyFile) => {}
+        Some(KeySource::Environment) => {
+            return Err(format!(
+                "The database key comes from {}; rotate it there",
+                encryption::KEY_ENV_VAR
+            ))
+        }
+        None => return Err("The database is not encrypted".to_string()),
+    }
+
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let key_path = encryption::key_file_path(&config_dir);
+    let pending_path = encryption::pending_key_file_path(&config_dir);
+
+    let new_key = DbKey::generate();
+    encryption::write_key_file(&pending_path, &new_key)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Err(e) = encryption::rekey(&conn, &new_key) {
+            let _ = std::fs::remove_file(&pending_path);
+            return Err(format!("Failed to re-key database: {}", e));
+        }
+        std::fs::rename(&pending_path, &key_path).map_err(|e| {
+            format!(
+                "Database re-keyed but the key file could not be replaced; the new key is in {:?}: {}",
+                pending_path, e
+            )
+        })?;
+        println!("[Feedback] Database key rotated");
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+#[command]
+pub async fn rekey_database(_app: AppHandle) -> Result<(), String> {
+    Err("This build has no SQLCipher support; build with --features sqlcipher".to_string())
+}
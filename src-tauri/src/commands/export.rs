@@ -0,0 +1,616 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection, Row};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager};
+
+use super::feedback::{current_timestamp, open_reader, DbConnection};
+use crate::db::anonymize::Anonymizer;
+use crate::db::models::ExportSummary;
+use crate::db::{encryption, settings, workstation};
+
+// app_settings key holding the export header inside SQLite snapshots
+pub(crate) const EXPORT_INFO_SETTING: &str = "export_info";
+
+const ANONYMIZATION_KEY_FILE: &str = "export_anonymization.key";
+
+// A BLOB value is exported as {"$base64": "..."} so imports can restore it
+pub(crate) const BLOB_TAG: &str = "$base64";
+
+// Tables included in an export, parents first so snapshots satisfy foreign keys
+pub(crate) const EXPORT_TABLES: [&str; 7] = [
+    "sessions",
+    "messages",
+    "feedbacks",
+    "recommendations",
+    "operation_logs",
+    "performance_metrics",
+    "template_usage",
+];
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Ndjson,
+    Csv,
+    Sqlite,
+}
+
+impl ExportFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Sqlite => "sqlite",
+        }
+    }
+
+    // CSV exports are one file per table, bundled in a zip archive
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "zip",
+            ExportFormat::Sqlite => "db",
+        }
+    }
+}
+
+// Date range applied to sessions.start_time; child rows follow their session
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportRange {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+impl ExportRange {
+    fn filter(&self, column: &str) -> (String, Vec<i64>) {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => (format!("{} BETWEEN ?1 AND ?2", column), vec![start, end]),
+            (Some(start), None) => (format!("{} >= ?1", column), vec![start]),
+            (None, Some(end)) => (format!("{} <= ?1", column), vec![end]),
+            (None, None) => (String::new(), vec![]),
+        }
+    }
+
+    fn to_json(self) -> Value {
+        json!({ "start": self.start, "end": self.end })
+    }
+}
+
+// Build the SELECT for one table. Rows without a session (logs, metrics) are
+// filtered by their own created_at so they are not silently dropped.
+fn table_query(table: &str, range: &ExportRange) -> (String, Vec<i64>) {
+    let (session_filter, params) = range.filter("start_time");
+    if session_filter.is_empty() {
+        return (format!("SELECT * FROM {}", table), params);
+    }
+
+    let query = match table {
+        "sessions" => format!("SELECT * FROM sessions WHERE {}", session_filter),
+        "operation_logs" | "performance_metrics" => {
+            let (created_filter, _) = range.filter("created_at");
+            format!(
+                "SELECT * FROM {} WHERE session_id IN (SELECT session_id FROM sessions WHERE {})
+                 OR (session_id IS NULL AND {})",
+                table, session_filter, created_filter
+            )
+        }
+        _ => format!(
+            "SELECT * FROM {} WHERE session_id IN (SELECT session_id FROM sessions WHERE {})",
+            table, session_filter
+        ),
+    };
+    (query, params)
+}
+
+pub(crate) fn row_to_json(row: &Row, columns: &[String]) -> rusqlite::Result<Map<String, Value>> {
+    let mut map = Map::new();
+    for (i, column) in columns.iter().enumerate() {
+        let value = match row.get_ref(i)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(v) => json!(v),
+            ValueRef::Real(v) => json!(v),
+            ValueRef::Text(v) => Value::String(String::from_utf8_lossy(v).into_owned()),
+            // No column is declared BLOB, but SQLite lets any column hold one;
+            // keep such values byte for byte
+            ValueRef::Blob(v) => json!({ BLOB_TAG: BASE64.encode(v) }),
+        };
+        map.insert(column.clone(), value);
+    }
+    Ok(map)
+}
+
+pub(crate) fn json_to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => SqlValue::Text(s.clone()),
+        Value::Object(map) => match blob_bytes(map) {
+            Some(bytes) => SqlValue::Blob(bytes),
+            None => SqlValue::Text(value.to_string()),
+        },
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn blob_bytes(map: &Map<String, Value>) -> Option<Vec<u8>> {
+    match map.get(BLOB_TAG) {
+        Some(Value::String(encoded)) if map.len() == 1 => BASE64.decode(encoded).ok(),
+        _ => None,
+    }
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Destination for exported rows; each format streams rows as they are read
+trait ExportSink {
+    fn begin_table(&mut self, table: &str, columns: &[String]) -> Result<(), String>;
+    fn write_row(
+        &mut self,
+        table: &str,
+        columns: &[String],
+        row: &Map<String, Value>,
+    ) -> Result<(), String>;
+    fn end_table(&mut self, table: &str) -> Result<(), String>;
+    fn finish(self: Box<Self>, row_counts: &Map<String, Value>) -> Result<(), String>;
+}
+
+struct JsonSink {
+    out: BufWriter<File>,
+    first_row: bool,
+}
+
+impl JsonSink {
+    fn new(file: File, header: &Map<String, Value>) -> Result<Self, String> {
+        let mut out = BufWriter::new(file);
+        // Write the header object without its closing brace, tables are appended as keys
+        let header = Value::Object(header.clone()).to_string();
+        out.write_all(header.strip_suffix('}').unwrap_or(&header).as_bytes())
+            .map_err(|e| e.to_string())?;
+        Ok(JsonSink {
+            out,
+            first_row: true,
+        })
+    }
+}
+
+impl ExportSink for JsonSink {
+    fn begin_table(&mut self, table: &str, _columns: &[String]) -> Result<(), String> {
+        self.first_row = true;
+        write!(self.out, ",{}:[", Value::String(table.to_string())).map_err(|e| e.to_string())
+    }
+
+    fn write_row(
+        &mut self,
+        _table: &str,
+        _columns: &[String],
+        row: &Map<String, Value>,
+    ) -> Result<(), String> {
+        if !self.first_row {
+            self.out.write_all(b",").map_err(|e| e.to_string())?;
+        }
+        self.first_row = false;
+        serde_json::to_writer(&mut self.out, row).map_err(|e| e.to_string())
+    }
+
+    fn end_table(&mut self, _table: &str) -> Result<(), String> {
+        self.out.write_all(b"]").map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>, row_counts: &Map<String, Value>) -> Result<(), String> {
+        write!(
+            self.out,
+            ",\"rowCounts\":{}}}",
+            Value::Object(row_counts.clone())
+        )
+        .map_err(|e| e.to_string())?;
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
+
+// One JSON object per line: a header line, then {"table": ..., "row": {...}} lines
+struct NdjsonSink {
+    out: BufWriter<File>,
+}
+
+impl NdjsonSink {
+    fn new(file: File, header: &Map<String, Value>) -> Result<Self, String> {
+        let mut out = BufWriter::new(file);
+        writeln!(out, "{}", Value::Object(header.clone())).map_err(|e| e.to_string())?;
+        Ok(NdjsonSink { out })
+    }
+}
+
+impl ExportSink for NdjsonSink {
+    fn begin_table(&mut self, _table: &str, _columns: &[String]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(
+        &mut self,
+        table: &str,
+        _columns: &[String],
+        row: &Map<String, Value>,
+    ) -> Result<(), String> {
+        writeln!(self.out, "{}", json!({ "table": table, "row": row })).map_err(|e| e.to_string())
+    }
+
+    fn end_table(&mut self, _table: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>, _row_counts: &Map<String, Value>) -> Result<(), String> {
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
+
+// A zip archive with <table>.csv entries and a manifest.json
+struct CsvZipSink {
+    // Writes into the current zip entry; taken back out to start the next one
+    csv: Option<csv::Writer<zip::ZipWriter<BufWriter<File>>>>,
+    header: Map<String, Value>,
+}
+
+impl CsvZipSink {
+    fn new(file: File, header: &Map<String, Value>) -> Self {
+        CsvZipSink {
+            csv: Some(csv::Writer::from_writer(zip::ZipWriter::new(
+                BufWriter::new(file),
+            ))),
+            header: header.clone(),
+        }
+    }
+
+    fn options() -> zip::write::SimpleFileOptions {
+        zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+    }
+
+    fn take_zip(&mut self) -> Result<zip::ZipWriter<BufWriter<File>>, String> {
+        let csv = self
+            .csv
+            .take()
+            .ok_or_else(|| "CSV export is already finished".to_string())?;
+        csv.into_inner().map_err(|e| e.to_string())
+    }
+
+    fn csv(&mut self) -> Result<&mut csv::Writer<zip::ZipWriter<BufWriter<File>>>, String> {
+        self.csv
+            .as_mut()
+            .ok_or_else(|| "CSV export is already finished".to_string())
+    }
+}
+
+impl ExportSink for CsvZipSink {
+    fn begin_table(&mut self, table: &str, columns: &[String]) -> Result<(), String> {
+        let mut zip = self.take_zip()?;
+        zip.start_file(format!("{}.csv", table), Self::options())
+            .map_err(|e| e.to_string())?;
+        // BOM so Excel opens the Chinese content as UTF-8
+        zip.write_all("\u{feff}".as_bytes())
+            .map_err(|e| e.to_string())?;
+        let mut csv = csv::Writer::from_writer(zip);
+        csv.write_record(columns).map_err(|e| e.to_string())?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    fn write_row(
+        &mut self,
+        _table: &str,
+        columns: &[String],
+        row: &Map<String, Value>,
+    ) -> Result<(), String> {
+        let record = columns
+            .iter()
+            .map(|c| csv_field(row.get(c).unwrap_or(&Value::Null)));
+        self.csv()?.write_record(record).map_err(|e| e.to_string())
+    }
+
+    fn end_table(&mut self, _table: &str) -> Result<(), String> {
+        self.csv()?.flush().map_err(|e| e.to_string())
+    }
+
+    fn finish(mut self: Box<Self>, row_counts: &Map<String, Value>) -> Result<(), String> {
+        let mut manifest = self.header.clone();
+        manifest.insert("rowCounts".to_string(), Value::Object(row_counts.clone()));
+        let mut zip = self.take_zip()?;
+        zip.start_file("manifest.json", Self::options())
+            .map_err(|e| e.to_string())?;
+        serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| e.to_string())?;
+        let mut out = zip.finish().map_err(|e| e.to_string())?;
+        out.flush().map_err(|e| e.to_string())
+    }
+}
+
+// A standalone SQLite database with the feedback schema and the exported rows
+struct SqliteSink {
+    conn: Connection,
+    insert_sql: String,
+}
+
+impl SqliteSink {
+    fn new(path: &Path, header: &Map<String, Value>) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        crate::db::run_migrations(&conn).map_err(|e| e.to_string())?;
+        settings::store(&conn, EXPORT_INFO_SETTING, header)?;
+        conn.execute_batch("BEGIN").map_err(|e| e.to_string())?;
+        Ok(SqliteSink {
+            conn,
+            insert_sql: String::new(),
+        })
+    }
+}
+
+impl ExportSink for SqliteSink {
+    fn begin_table(&mut self, table: &str, columns: &[String]) -> Result<(), String> {
+        let placeholders = (1..=columns.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.insert_sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns.join(", "),
+            placeholders
+        );
+        Ok(())
+    }
+
+    fn write_row(
+        &mut self,
+        _table: &str,
+        columns: &[String],
+        row: &Map<String, Value>,
+    ) -> Result<(), String> {
+        let values = columns
+            .iter()
+            .map(|c| json_to_sql(row.get(c).unwrap_or(&Value::Null)));
+        self.conn
+            .prepare_cached(&self.insert_sql)
+            .and_then(|mut stmt| stmt.execute(params_from_iter(values)))
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn end_table(&mut self, _table: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>, _row_counts: &Map<String, Value>) -> Result<(), String> {
+        self.conn.execute_batch("COMMIT").map_err(|e| e.to_string())
+    }
+}
+
+// Stream every export table from `conn` into `sink`, returning row counts per table
+fn stream_tables(
+    conn: &Connection,
+    range: &ExportRange,
+    sink: &mut dyn ExportSink,
+    mut anonymizer: Option<&mut Anonymizer>,
+) -> Result<Map<String, Value>, String> {
+    let mut row_counts = Map::new();
+
+    for table in EXPORT_TABLES {
+        let (query, params_vec) = table_query(table, range);
+        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt
+            .query(params_from_iter(params_vec.iter()))
+            .map_err(|e| e.to_string())?;
+
+        sink.begin_table(table, &columns)?;
+        let mut count: u64 = 0;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let mut record = row_to_json(row, &columns).map_err(|e| e.to_string())?;
+            if let Some(anonymizer) = anonymizer.as_deref_mut() {
+                anonymizer.apply(table, &mut record);
+            }
+            sink.write_row(table, &columns, &record)?;
+            count += 1;
+        }
+        sink.end_table(table)?;
+
+        row_counts.insert(table.to_string(), json!(count));
+    }
+
+    Ok(row_counts)
+}
+
+fn create_file(path: &Path) -> Result<File, String> {
+    File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))
+}
+
+pub fn export_to_path(
+    conn: &Connection,
+    format: ExportFormat,
+    range: ExportRange,
+    path: &Path,
+    mut anonymizer: Option<Anonymizer>,
+) -> Result<ExportSummary, String> {
+    let exported_at = current_timestamp();
+    let anonymized = anonymizer.is_some();
+    let mut header = Map::new();
+    header.insert("exportDate".to_string(), json!(exported_at));
+    header.insert("format".to_string(), json!(format.as_str()));
+    header.insert("dateRange".to_string(), range.to_json());
+    header.insert("anonymized".to_string(), json!(anonymized));
+    header.insert("workstation".to_string(), json!(workstation::local(conn)?));
+
+    // Write next to the target and rename at the end so a failed export never
+    // leaves a truncated file under the chosen name
+    let mut part_path = path.as_os_str().to_owned();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+    if part_path.exists() {
+        std::fs::remove_file(&part_path).map_err(|e| e.to_string())?;
+    }
+
+    let result = (|| {
+        let mut sink: Box<dyn ExportSink> = match format {
+            ExportFormat::Json => Box::new(JsonSink::new(create_file(&part_path)?, &header)?),
+            ExportFormat::Ndjson => Box::new(NdjsonSink::new(create_file(&part_path)?, &header)?),
+            ExportFormat::Csv => Box::new(CsvZipSink::new(create_file(&part_path)?, &header)),
+            ExportFormat::Sqlite => Box::new(SqliteSink::new(&part_path, &header)?),
+        };
+        let row_counts = stream_tables(conn, &range, sink.as_mut(), anonymizer.as_mut())?;
+        sink.finish(&row_counts)?;
+        Ok::<_, String>(row_counts)
+    })();
+
+    let row_counts = match result {
+        Ok(counts) => counts,
+        Err(e) => {
+            let _ = std::fs::remove_file(&part_path);
+            return Err(e);
+        }
+    };
+
+    std::fs::rename(&part_path, path).map_err(|e| e.to_string())?;
+    let bytes_written = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    Ok(ExportSummary {
+        path: path.to_string_lossy().to_string(),
+        format: format.as_str().to_string(),
+        row_counts: Value::Object(row_counts),
+        bytes_written,
+        exported_at,
+        anonymized,
+    })
+}
+
+fn read_anonymization_key(path: &Path) -> Option<Vec<u8>> {
+    let existing = std::fs::read_to_string(path).ok()?;
+    hex::decode(existing.trim())
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+// Key for pseudonymising patient ids, so the same patient gets the same
+// pseudonym in every export from this workstation. Anyone holding it can
+// re-identify exports, so like the database key it lives in the config dir,
+// readable only by the user, rather than next to feedback.db.
+pub(crate) fn load_or_create_anonymization_key(app: &AppHandle) -> Result<Vec<u8>, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
+    let key_path = config_dir.join(ANONYMIZATION_KEY_FILE);
+
+    if let Some(key) = read_anonymization_key(&key_path) {
+        encryption::restrict_permissions(&key_path)?;
+        return Ok(key);
+    }
+
+    // Earlier versions kept the key in the data dir; move it so existing
+    // pseudonyms stay valid
+    let legacy_path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(ANONYMIZATION_KEY_FILE);
+    let legacy_key = read_anonymization_key(&legacy_path);
+    let moved = legacy_key.is_some();
+    let key = legacy_key.unwrap_or_else(|| rand::random::<[u8; 32]>().to_vec());
+    std::fs::write(&key_path, hex::encode(&key)).map_err(|e| e.to_string())?;
+    encryption::restrict_permissions(&key_path)?;
+
+    if moved {
+        if let Err(e) = std::fs::remove_file(&legacy_path) {
+            eprintln!("[Feedback] Failed to remove {:?}: {}", legacy_path, e);
+        }
+        println!(
+            "[Feedback] Moved export anonymization key to {:?}",
+            key_path
+        );
+    } else {
+        println!(
+            "[Feedback] Created export anonymization key at {:?}",
+            key_path
+        );
+    }
+    Ok(key)
+}
+
+// Export Data Command
+
+#[command]
+pub async fn export_data(
+    app: AppHandle,
+    format: ExportFormat,
+    output_path: Option<String>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    anonymize: Option<bool>,
+    anonymization_key: Option<String>,
+) -> Result<Option<ExportSummary>, String> {
+    // An explicit key lets several workstations produce matching pseudonyms
+    let anonymizer = if anonymize.unwrap_or(false) {
+        let key = match anonymization_key.filter(|k| !k.is_empty()) {
+            Some(k) => k.into_bytes(),
+            None => load_or_create_anonymization_key(&app)?,
+        };
+        Some(Anonymizer::new(&key))
+    } else {
+        None
+    };
+
+    let path = match output_path {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let handle = rfd::AsyncFileDialog::new()
+                .set_file_name(format!(
+                    "feedback_export_{}.{}",
+                    current_timestamp(),
+                    format.extension()
+                ))
+                .add_filter(format.as_str().to_uppercase(), &[format.extension()])
+                .save_file()
+                .await;
+            match handle {
+                Some(h) => h.path().to_path_buf(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let range = ExportRange {
+        start: start_date,
+        end: end_date,
+    };
+
+    // Large exports can take a while; keep them off the async runtime workers
+    tauri::async_runtime::spawn_blocking(move || {
+        // The export header reads the workstation identity; make sure it
+        // exists, since the snapshot connection below cannot create it
+        {
+            let db = app.state::<DbConnection>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            workstation::local(&conn)?;
+        }
+
+        // Read from a snapshot on a separate connection so that writes go on
+        // while a large export streams; all tables come from the same point
+        let mut conn = open_reader(&app)?;
+        let snapshot = conn.transaction().map_err(|e| e.to_string())?;
+        export_to_path(&snapshot, format, range, &path, anonymizer).map(Some)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -6,11 +6,18 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::{command, AppHandle, Manager};
 use uuid::Uuid;
 
+use super::analytics::QueryFilter;
+use super::cost::{carries_tokens, check_budgets};
+use super::lifecycle::REASON_IDLE;
+use crate::db::error::FeedbackError;
 use crate::db::models::*;
+use crate::db::{encryption, workstation};
+
+const DB_FILE_NAME: &str = "feedback.db";
 
 // Database connection manager
 #[allow(dead_code)]
-pub struct DbConnection(Mutex<Connection>);
+pub struct DbConnection(pub(crate) Mutex<Connection>);
 
 #[allow(dead_code)]
 impl DbConnection {
@@ -28,96 +35,473 @@ fn get_db_path(app: &AppHandle) -> PathBuf {
         .expect("Failed to get app data dir");
     println!("[Feedback] App data dir: {:?}", app_data_dir);
     std::fs::create_dir_all(&app_data_dir).expect("Failed to create app data directory");
-    let db_path = app_data_dir.join("feedback.db");
+    let db_path = app_data_dir.join(DB_FILE_NAME);
     println!("[Feedback] Database path: {:?}", db_path);
     db_path
 }
 
 // Helper to initialize database
-pub fn init_database(app: &AppHandle) -> SqlResult<()> {
+pub fn init_database(app: &AppHandle) -> Result<(), String> {
     println!("[Feedback] Starting database initialization...");
     let db_path = get_db_path(app);
 
+    // SQLCipher builds keep the key in the config dir, away from feedback.db
+    let key = if encryption::is_supported() {
+        let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+        Some(encryption::load_or_create_key(&config_dir)?)
+    } else {
+        None
+    };
+
     println!("[Feedback] Opening database connection...");
-    let conn = Connection::open(&db_path)?;
+    let conn = match &key {
+        #[cfg(feature = "sqlcipher")]
+        Some((file_key, encryption::KeySource::KeyFile)) => {
+            let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+            encryption::open_with_key_file(&db_path, &config_dir, file_key)?
+        }
+        _ => encryption::open(&db_path, key.as_ref().map(|(k, _)| k))?,
+    };
     println!("[Feedback] Database connection opened successfully");
 
+    // Foreign keys are off by default in SQLite; without this the schema's
+    // ON DELETE CASCADE clauses do nothing
+    conn.pragma_update(None, "foreign_keys", true)
+        .map_err(|e| e.to_string())?;
+
+    // WAL lets readers on other connections (exports) keep a snapshot
+    // without blocking writes, and vice versa
+    let journal_mode: String = conn
+        .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if !journal_mode.eq_ignore_ascii_case("wal") {
+        eprintln!(
+            "[Feedback] Warning: database is in {} journal mode, not WAL",
+            journal_mode
+        );
+    }
+
     // Run migrations
-    let migration_sql = include_str!("../../migrations/001_initial_schema.sql");
-    println!(
-        "[Feedback] Running migrations (SQL length: {} bytes)...",
-        migration_sql.len()
-    );
-    conn.execute_batch(migration_sql)?;
+    println!("[Feedback] Running migrations...");
+    crate::db::run_migrations(&conn).map_err(|e| e.to_string())?;
     println!("[Feedback] Migrations completed successfully");
 
+    // Rows written before enforcement was enabled may reference missing sessions
+    let orphans: i64 = conn
+        .query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| e.to_string())?;
+    if orphans > 0 {
+        eprintln!(
+            "[Feedback] Warning: {} rows reference sessions that do not exist",
+            orphans
+        );
+    }
+
     // Store connection in app state
     app.manage(DbConnection(Mutex::new(conn)));
+    app.manage(encryption::EncryptionState {
+        key_source: key.map(|(_, source)| source),
+    });
     println!("[Feedback] Database connection stored in app state");
 
     Ok(())
 }
 
-// Helper function to get current Unix timestamp
-fn current_timestamp() -> i64 {
+/// A second, read-only connection to the feedback database.
+///
+/// For long reads such as exports, which would otherwise hold the shared
+/// connection and block every write until they finish.
+pub(crate) fn open_reader(app: &AppHandle) -> Result<Connection, String> {
+    let db_path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(DB_FILE_NAME);
+    let encrypted = app
+        .try_state::<encryption::EncryptionState>()
+        .is_some_and(|state| state.key_source.is_some());
+    // Read the key again rather than keeping it in memory; a rotation
+    // replaces the key file
+    let key = if encrypted {
+        let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+        Some(encryption::load_or_create_key(&config_dir)?.0)
+    } else {
+        None
+    };
+    encryption::open_read_only(&db_path, key.as_ref())
+}
+
+// Helper function to get current Unix timestamp in milliseconds
+pub(crate) fn current_timestamp() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_secs() as i64
+        .as_millis() as i64
+}
+
+// Fail with a descriptive error instead of a bare FOREIGN KEY constraint failure
+fn ensure_session_exists(conn: &Connection, session_id: &str) -> Result<(), FeedbackError> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sessions WHERE session_id = ?1)",
+        params![session_id],
+        |row| row.get(0),
+    )?;
+    if exists {
+        Ok(())
+    } else {
+        Err(FeedbackError::SessionNotFound {
+            session_id: session_id.to_string(),
+        })
+    }
 }
 
 // Session Management Commands
 
-#[command]
-pub async fn create_session(
-    app: AppHandle,
-    session_type: String,
-    patient_id: Option<String>,
-    patient_name: Option<String>,
-) -> Result<String, String> {
+pub(crate) fn insert_session(
+    conn: &Connection,
+    session_type: SessionType,
+    patient_id: Option<&str>,
+    patient_name: Option<&str>,
+    metadata: Option<&serde_json::Value>,
+) -> Result<String, FeedbackError> {
     let session_id = Uuid::new_v4().to_string();
     let start_time = current_timestamp();
-
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let metadata = metadata.map(|m| m.to_string());
 
     conn.execute(
-        "INSERT INTO sessions (session_id, patient_id, patient_name, session_type, start_time, status, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, 'active', ?6)",
+        "INSERT INTO sessions (session_id, patient_id, patient_name, session_type, start_time, status, metadata, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'active', ?6, ?7)",
         params![
             &session_id,
-            &patient_id,
-            &patient_name,
-            &session_type,
+            patient_id,
+            patient_name,
+            session_type,
             start_time,
+            &metadata,
             start_time
         ],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     Ok(session_id)
 }
 
+// Close a session that is still active; returns false if it was already closed.
+// A completion also closes a session the idle sweep cancelled in the meantime,
+// since the consultation evidently went on.
+pub(crate) fn close_session(
+    conn: &Connection,
+    session_id: &str,
+    status: SessionStatus,
+) -> Result<bool, FeedbackError> {
+    let updated = conn.execute(
+        "UPDATE sessions
+         SET status = ?1,
+             end_time = ?2,
+             metadata = CASE WHEN status = 'active' THEN metadata
+                 ELSE json_remove(metadata, '$.closeReason', '$.closedAt') END
+         WHERE session_id = ?3
+           AND (status = 'active'
+                OR (?4 AND status = 'cancelled'
+                    AND json_valid(metadata)
+                    AND json_extract(metadata, '$.closeReason') = ?5))",
+        params![
+            status,
+            current_timestamp(),
+            session_id,
+            status == SessionStatus::Completed,
+            REASON_IDLE
+        ],
+    )?;
+    Ok(updated > 0)
+}
+
+#[command]
+pub async fn create_session(
+    app: AppHandle,
+    session_type: String,
+    patient_id: Option<String>,
+    patient_name: Option<String>,
+    metadata: Option<serde_json::Value>,
+) -> Result<String, FeedbackError> {
+    let session_type = SessionType::parse(&session_type)?;
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock()?;
+    insert_session(
+        &conn,
+        session_type,
+        patient_id.as_deref(),
+        patient_name.as_deref(),
+        metadata.as_ref(),
+    )
+}
+
 #[command]
 pub async fn update_session_status(
     app: AppHandle,
     session_id: String,
     status: String,
     end_time: Option<i64>,
-) -> Result<(), String> {
+) -> Result<(), FeedbackError> {
+    let status = SessionStatus::parse(&status)?;
     let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let conn = db.0.lock()?;
     let actual_end_time = end_time.unwrap_or_else(current_timestamp);
 
-    conn.execute(
+    let updated = conn.execute(
         "UPDATE sessions SET status = ?1, end_time = ?2 WHERE session_id = ?3",
-        params![&status, actual_end_time, &session_id],
-    )
-    .map_err(|e| e.to_string())?;
+        params![status, actual_end_time, &session_id],
+    )?;
+    if updated == 0 {
+        return Err(FeedbackError::SessionNotFound { session_id });
+    }
 
     Ok(())
 }
 
+// Single write path shared by the individual save commands and batch writes.
+// Validates the item and inserts it, returning the row id.
+pub(crate) fn write_item(conn: &Connection, item: &WriteItem) -> Result<String, FeedbackError> {
+    let new_id = |id: &Option<String>| id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let timestamp = |created_at: &Option<i64>| created_at.unwrap_or_else(current_timestamp);
+
+    match item {
+        WriteItem::Message {
+            id,
+            session_id,
+            role,
+            content,
+            images,
+            token_count,
+            llm_model,
+            latency_ms,
+            created_at,
+        } => {
+            let role = MessageRole::parse(role)?;
+            let message_id = new_id(id);
+            ensure_session_exists(conn, session_id)?;
+
+            // seq is assigned under the connection mutex, so it cannot race
+            conn.execute(
+                "INSERT INTO messages (message_id, session_id, role, content, images, token_count, llm_model, latency_ms, created_at, seq)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9,
+                         (SELECT COALESCE(MAX(seq), 0) + 1 FROM messages WHERE session_id = ?2))",
+                params![
+                    &message_id,
+                    session_id,
+                    role,
+                    content,
+                    images,
+                    token_count,
+                    llm_model,
+                    latency_ms,
+                    timestamp(created_at)
+                ],
+            )?;
+            Ok(message_id)
+        }
+        WriteItem::Feedback {
+            id,
+            session_id,
+            target_type,
+            target_id,
+            feedback_type,
+            rating,
+            reason,
+            original_value,
+            modified_value,
+            created_at,
+        } => {
+            let target_type = TargetType::parse(target_type)?;
+            let feedback_type = FeedbackType::parse(feedback_type)?;
+            if let Some(r) = rating.filter(|r| !(1..=5).contains(r)) {
+                return Err(FeedbackError::OutOfRange {
+                    field: "rating",
+                    value: r.to_string(),
+                    expected: "an integer between 1 and 5",
+                });
+            }
+            let feedback_id = new_id(id);
+            ensure_session_exists(conn, session_id)?;
+
+            conn.execute(
+                "INSERT INTO feedbacks (feedback_id, session_id, target_type, target_id, feedback_type, rating, reason, original_value, modified_value, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    &feedback_id,
+                    session_id,
+                    target_type,
+                    target_id,
+                    feedback_type,
+                    rating,
+                    reason,
+                    original_value,
+                    modified_value,
+                    timestamp(created_at)
+                ],
+            )?;
+            Ok(feedback_id)
+        }
+        WriteItem::Recommendation {
+            id,
+            session_id,
+            rec_type,
+            content,
+            matched,
+            match_confidence,
+            prompt_tokens,
+            completion_tokens,
+            latency_ms,
+            llm_model,
+            created_at,
+        } => {
+            let rec_type = RecommendationType::parse(rec_type)?;
+            let recommendation_id = new_id(id);
+            ensure_session_exists(conn, session_id)?;
+
+            conn.execute(
+                "INSERT INTO recommendations (recommendation_id, session_id, rec_type, content, matched, match_confidence, prompt_tokens, completion_tokens, latency_ms, llm_model, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    &recommendation_id,
+                    session_id,
+                    rec_type,
+                    content,
+                    matched,
+                    match_confidence,
+                    prompt_tokens,
+                    completion_tokens,
+                    latency_ms,
+                    llm_model,
+                    timestamp(created_at)
+                ],
+            )?;
+            Ok(recommendation_id)
+        }
+        WriteItem::Log {
+            id,
+            session_id,
+            operation_type,
+            operation_name,
+            details,
+            success,
+            duration_ms,
+            created_at,
+        } => {
+            let operation_type = OperationType::parse(operation_type)?;
+            let log_id = new_id(id);
+            if let Some(id) = session_id {
+                ensure_session_exists(conn, id)?;
+            }
+
+            conn.execute(
+                "INSERT INTO operation_logs (log_id, session_id, operation_type, operation_name, details, success, duration_ms, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    &log_id,
+                    session_id,
+                    operation_type,
+                    operation_name,
+                    details,
+                    success,
+                    duration_ms,
+                    timestamp(created_at)
+                ],
+            )?;
+            Ok(log_id)
+        }
+        WriteItem::Metric {
+            id,
+            session_id,
+            metric_type,
+            metric_value,
+            unit,
+            context,
+            created_at,
+        } => {
+            let metric_type = MetricType::parse(metric_type)?;
+            let metric_id = new_id(id);
+            if let Some(id) = session_id {
+                ensure_session_exists(conn, id)?;
+            }
+
+            conn.execute(
+                "INSERT INTO performance_metrics (metric_id, session_id, metric_type, metric_value, unit, context, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    &metric_id,
+                    session_id,
+                    metric_type,
+                    metric_value,
+                    unit,
+                    context,
+                    timestamp(created_at)
+                ],
+            )?;
+            Ok(metric_id)
+        }
+        WriteItem::TemplateUsage {
+            id,
+            session_id,
+            symptom_key,
+            field_key,
+            event,
+            created_at,
+        } => {
+            let event = UsageEvent::parse(event)?;
+            ensure_session_exists(conn, session_id)?;
+            let at = timestamp(created_at);
+            let field_key = field_key.as_deref().unwrap_or_default();
+
+            let answered_at = (event == UsageEvent::Answered).then_some(at);
+
+            // The symptom row is answered along with its first answered field
+            let mut rows = vec![(new_id(id), field_key)];
+            if answered_at.is_some() && !field_key.is_empty() {
+                rows.push((Uuid::new_v4().to_string(), ""));
+            }
+            // Later events for an existing row only fill in a missing answer
+            for (row_id, item_key) in rows {
+                conn.execute(
+                    "INSERT INTO template_usage (usage_id, session_id, symptom_key, field_key, shown_at, answered_at, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?5)
+                     ON CONFLICT(session_id, symptom_key, field_key) DO UPDATE
+                     SET answered_at = excluded.answered_at
+                     WHERE template_usage.answered_at IS NULL AND excluded.answered_at IS NOT NULL",
+                    params![
+                        row_id,
+                        session_id,
+                        symptom_key,
+                        item_key,
+                        at,
+                        answered_at
+                    ],
+                )?;
+            }
+
+            let usage_id = conn.query_row(
+                "SELECT usage_id FROM template_usage
+                 WHERE session_id = ?1 AND symptom_key = ?2 AND field_key = ?3",
+                params![session_id, symptom_key, field_key],
+                |row| row.get(0),
+            )?;
+            Ok(usage_id)
+        }
+    }
+}
+
+fn write_one(app: &AppHandle, item: WriteItem) -> Result<String, FeedbackError> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock()?;
+    let id = write_item(&conn, &item)?;
+    if carries_tokens(&item) {
+        check_budgets(app, &conn);
+    }
+    Ok(id)
+}
+
 // Message Management Commands
 
 #[command]
@@ -131,31 +515,21 @@ pub async fn save_message(
     token_count: Option<i32>,
     llm_model: Option<String>,
     latency_ms: Option<i32>,
-) -> Result<String, String> {
-    let message_id = Uuid::new_v4().to_string();
-    let created_at = current_timestamp();
-
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "INSERT INTO messages (message_id, session_id, role, content, images, token_count, llm_model, latency_ms, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![
-            &message_id,
-            &session_id,
-            &role,
-            &content,
-            &images,
-            &token_count,
-            &llm_model,
-            &latency_ms,
-            created_at
-        ],
+) -> Result<String, FeedbackError> {
+    write_one(
+        &app,
+        WriteItem::Message {
+            id: None,
+            session_id,
+            role,
+            content,
+            images,
+            token_count,
+            llm_model,
+            latency_ms,
+            created_at: None,
+        },
     )
-    .map_err(|e| e.to_string())?;
-
-    Ok(message_id)
 }
 
 // Feedback Management Commands
@@ -172,32 +546,22 @@ pub async fn save_feedback(
     reason: Option<String>,
     original_value: Option<String>,
     modified_value: Option<String>,
-) -> Result<String, String> {
-    let feedback_id = Uuid::new_v4().to_string();
-    let created_at = current_timestamp();
-
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "INSERT INTO feedbacks (feedback_id, session_id, target_type, target_id, feedback_type, rating, reason, original_value, modified_value, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        params![
-            &feedback_id,
-            &session_id,
-            &target_type,
-            &target_id,
-            &feedback_type,
-            &rating,
-            &reason,
-            &original_value,
-            &modified_value,
-            created_at
-        ],
+) -> Result<String, FeedbackError> {
+    write_one(
+        &app,
+        WriteItem::Feedback {
+            id: None,
+            session_id,
+            target_type,
+            target_id,
+            feedback_type,
+            rating,
+            reason,
+            original_value,
+            modified_value,
+            created_at: None,
+        },
     )
-    .map_err(|e| e.to_string())?;
-
-    Ok(feedback_id)
 }
 
 // Recommendation Management Commands
@@ -214,32 +578,24 @@ pub async fn save_recommendation(
     prompt_tokens: Option<i32>,
     completion_tokens: Option<i32>,
     latency_ms: Option<i32>,
-) -> Result<String, String> {
-    let recommendation_id = Uuid::new_v4().to_string();
-    let created_at = current_timestamp();
-
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "INSERT INTO recommendations (recommendation_id, session_id, rec_type, content, matched, match_confidence, prompt_tokens, completion_tokens, latency_ms, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        params![
-            &recommendation_id,
-            &session_id,
-            &rec_type,
-            &content,
+    llm_model: Option<String>,
+) -> Result<String, FeedbackError> {
+    write_one(
+        &app,
+        WriteItem::Recommendation {
+            id: None,
+            session_id,
+            rec_type,
+            content,
             matched,
-            &match_confidence,
-            &prompt_tokens,
-            &completion_tokens,
-            &latency_ms,
-            created_at
-        ],
+            match_confidence,
+            prompt_tokens,
+            completion_tokens,
+            latency_ms,
+            llm_model,
+            created_at: None,
+        },
     )
-    .map_err(|e| e.to_string())?;
-
-    Ok(recommendation_id)
 }
 
 // Operation Log Commands
@@ -253,29 +609,20 @@ pub async fn log_operation(
     details: Option<String>,
     success: bool,
     duration_ms: Option<i32>,
-) -> Result<(), String> {
-    let log_id = Uuid::new_v4().to_string();
-    let created_at = current_timestamp();
-
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "INSERT INTO operation_logs (log_id, session_id, operation_type, operation_name, details, success, duration_ms, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![
-            &log_id,
-            &session_id,
-            &operation_type,
-            &operation_name,
-            &details,
+) -> Result<(), FeedbackError> {
+    write_one(
+        &app,
+        WriteItem::Log {
+            id: None,
+            session_id,
+            operation_type,
+            operation_name,
+            details,
             success,
-            &duration_ms,
-            created_at
-        ],
-    )
-    .map_err(|e| e.to_string())?;
-
+            duration_ms,
+            created_at: None,
+        },
+    )?;
     Ok(())
 }
 
@@ -289,28 +636,19 @@ pub async fn record_performance_metric(
     metric_value: f64,
     unit: String,
     context: Option<String>,
-) -> Result<(), String> {
-    let metric_id = Uuid::new_v4().to_string();
-    let created_at = current_timestamp();
-
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "INSERT INTO performance_metrics (metric_id, session_id, metric_type, metric_value, unit, context, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
-            &metric_id,
-            &session_id,
-            &metric_type,
+) -> Result<(), FeedbackError> {
+    write_one(
+        &app,
+        WriteItem::Metric {
+            id: None,
+            session_id,
+            metric_type,
             metric_value,
-            &unit,
-            &context,
-            created_at
-        ],
-    )
-    .map_err(|e| e.to_string())?;
-
+            unit,
+            context,
+            created_at: None,
+        },
+    )?;
     Ok(())
 }
 
@@ -321,19 +659,17 @@ pub async fn get_session_statistics(
     app: AppHandle,
     start_date: Option<i64>,
     end_date: Option<i64>,
+    source_workstation: Option<String>,
 ) -> Result<SessionStatistics, String> {
     let db = app.state::<DbConnection>();
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    let (date_filter, params_vec) = match (start_date, end_date) {
-        (Some(start), Some(end)) => (
-            "WHERE start_time BETWEEN ?1 AND ?2".to_string(),
-            vec![start, end],
-        ),
-        (Some(start), None) => ("WHERE start_time >= ?1".to_string(), vec![start]),
-        (None, Some(end)) => ("WHERE start_time <= ?1".to_string(), vec![end]),
-        (None, None) => (String::new(), vec![]),
-    };
+    let mut filter = QueryFilter::default();
+    filter
+        .date_range("start_time", start_date, end_date)
+        .source(source_workstation, &workstation::local(&conn)?);
+    let date_filter = filter.where_sql();
+    let params_vec = filter.params_with(&[]);
 
     // Get basic statistics
     let query = format!(
@@ -343,7 +679,7 @@ pub async fn get_session_statistics(
             SUM(CASE WHEN status='completed' THEN 1 ELSE 0 END) as completed,
             SUM(CASE WHEN status='cancelled' THEN 1 ELSE 0 END) as cancelled,
             SUM(CASE WHEN status='error' THEN 1 ELSE 0 END) as error,
-            AVG(CASE WHEN end_time IS NOT NULL THEN (end_time - start_time) * 1000 ELSE NULL END) as avg_duration
+            AVG(CASE WHEN end_time IS NOT NULL THEN end_time - start_time ELSE NULL END) as avg_duration
          FROM sessions {}",
         date_filter
     );
@@ -412,19 +748,17 @@ pub async fn get_feedback_statistics(
     app: AppHandle,
     start_date: Option<i64>,
     end_date: Option<i64>,
+    source_workstation: Option<String>,
 ) -> Result<FeedbackStatistics, String> {
     let db = app.state::<DbConnection>();
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    let (date_filter, params_vec) = match (start_date, end_date) {
-        (Some(start), Some(end)) => (
-            "WHERE created_at BETWEEN ?1 AND ?2".to_string(),
-            vec![start, end],
-        ),
-        (Some(start), None) => ("WHERE created_at >= ?1".to_string(), vec![start]),
-        (None, Some(end)) => ("WHERE created_at <= ?1".to_string(), vec![end]),
-        (None, None) => (String::new(), vec![]),
-    };
+    let mut filter = QueryFilter::default();
+    filter
+        .date_range("created_at", start_date, end_date)
+        .source(source_workstation, &workstation::local(&conn)?);
+    let date_filter = filter.where_sql();
+    let params_vec = filter.params_with(&[]);
 
     let query = format!(
         "SELECT
@@ -510,19 +844,17 @@ pub async fn get_performance_statistics(
     app: AppHandle,
     start_date: Option<i64>,
     end_date: Option<i64>,
+    source_workstation: Option<String>,
 ) -> Result<PerformanceStatistics, String> {
     let db = app.state::<DbConnection>();
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    let (date_filter, params_vec) = match (start_date, end_date) {
-        (Some(start), Some(end)) => (
-            "WHERE created_at BETWEEN ?1 AND ?2".to_string(),
-            vec![start, end],
-        ),
-        (Some(start), None) => ("WHERE created_at >= ?1".to_string(), vec![start]),
-        (None, Some(end)) => ("WHERE created_at <= ?1".to_string(), vec![end]),
-        (None, None) => (String::new(), vec![]),
-    };
+    let mut filter = QueryFilter::default();
+    filter
+        .date_range("created_at", start_date, end_date)
+        .source(source_workstation, &workstation::local(&conn)?);
+    let date_filter = filter.where_sql();
+    let params_vec = filter.params_with(&[]);
 
     let query = format!(
         "SELECT
@@ -573,106 +905,3 @@ pub async fn get_performance_statistics(
         metrics_by_type: json!({}),
     })
 }
-
-// Export Data Command
-
-#[command]
-pub async fn export_data(
-    app: AppHandle,
-    format: String,
-    start_date: Option<i64>,
-    end_date: Option<i64>,
-) -> Result<String, String> {
-    let db = app.state::<DbConnection>();
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-
-    let (date_filter, params_vec) = match (start_date, end_date) {
-        (Some(start), Some(end)) => (
-            "WHERE start_time BETWEEN ?1 AND ?2".to_string(),
-            vec![start, end],
-        ),
-        (Some(start), None) => ("WHERE start_time >= ?1".to_string(), vec![start]),
-        (None, Some(end)) => ("WHERE start_time <= ?1".to_string(), vec![end]),
-        (None, None) => (String::new(), vec![]),
-    };
-
-    let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec
-        .iter()
-        .map(|p| p as &dyn rusqlite::ToSql)
-        .collect();
-
-    // Get sessions
-    let sessions_query = format!("SELECT * FROM sessions {}", date_filter);
-    let mut sessions_stmt = conn.prepare(&sessions_query).map_err(|e| e.to_string())?;
-    let mut sessions_rows = sessions_stmt
-        .query(&params_refs[..])
-        .map_err(|e| e.to_string())?;
-
-    let mut sessions = Vec::new();
-    while let Some(row) = sessions_rows.next().map_err(|e| e.to_string())? {
-        sessions.push(json!({
-            "session_id": row.get::<_, String>(0).map_err(|e| e.to_string())?,
-            "patient_id": row.get::<_, Option<String>>(1).map_err(|e| e.to_string())?,
-            "patient_name": row.get::<_, Option<String>>(2).map_err(|e| e.to_string())?,
-            "session_type": row.get::<_, String>(3).map_err(|e| e.to_string())?,
-            "start_time": row.get::<_, i64>(4).map_err(|e| e.to_string())?,
-            "end_time": row.get::<_, Option<i64>>(5).map_err(|e| e.to_string())?,
-            "status": row.get::<_, String>(6).map_err(|e| e.to_string())?,
-        }));
-    }
-
-    // Get messages
-    let messages_query = format!(
-        "SELECT * FROM messages WHERE session_id IN (SELECT session_id FROM sessions {})",
-        date_filter
-    );
-    let mut messages_stmt = conn.prepare(&messages_query).map_err(|e| e.to_string())?;
-    let mut messages_rows = messages_stmt
-        .query(&params_refs[..])
-        .map_err(|e| e.to_string())?;
-
-    let mut messages = Vec::new();
-    while let Some(row) = messages_rows.next().map_err(|e| e.to_string())? {
-        messages.push(json!({
-            "message_id": row.get::<_, String>(0).map_err(|e| e.to_string())?,
-            "session_id": row.get::<_, String>(1).map_err(|e| e.to_string())?,
-            "role": row.get::<_, String>(2).map_err(|e| e.to_string())?,
-            "content": row.get::<_, String>(3).map_err(|e| e.to_string())?,
-        }));
-    }
-
-    // Get feedbacks
-    let feedbacks_query = format!(
-        "SELECT * FROM feedbacks WHERE session_id IN (SELECT session_id FROM sessions {})",
-        date_filter
-    );
-    let mut feedbacks_stmt = conn.prepare(&feedbacks_query).map_err(|e| e.to_string())?;
-    let mut feedbacks_rows = feedbacks_stmt
-        .query(&params_refs[..])
-        .map_err(|e| e.to_string())?;
-
-    let mut feedbacks = Vec::new();
-    while let Some(row) = feedbacks_rows.next().map_err(|e| e.to_string())? {
-        feedbacks.push(json!({
-            "feedback_id": row.get::<_, String>(0).map_err(|e| e.to_string())?,
-            "session_id": row.get::<_, String>(1).map_err(|e| e.to_string())?,
-            "target_type": row.get::<_, String>(2).map_err(|e| e.to_string())?,
-            "target_id": row.get::<_, String>(3).map_err(|e| e.to_string())?,
-            "feedback_type": row.get::<_, String>(4).map_err(|e| e.to_string())?,
-        }));
-    }
-
-    let export_data = json!({
-        "exportDate": current_timestamp(),
-        "format": format,
-        "dateRange": {
-            "start": start_date,
-            "end": end_date
-        },
-        "sessions": sessions,
-        "messages": messages,
-        "feedbacks": feedbacks
-    });
-
-    Ok(export_data.to_string())
-}
@@ -0,0 +1,741 @@
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Transaction};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager};
+use uuid::Uuid;
+
+use super::export::{json_to_sql, row_to_json, BLOB_TAG, EXPORT_INFO_SETTING, EXPORT_TABLES};
+use super::feedback::{current_timestamp, DbConnection};
+use crate::db::models::{ImportReport, WorkstationInfo, WorkstationSummary};
+use crate::db::{settings, workstation};
+
+// Timestamp columns; files from before the switch to milliseconds hold seconds
+const TIMESTAMP_COLUMNS: [&str; 5] = [
+    "start_time",
+    "end_time",
+    "created_at",
+    "shown_at",
+    "answered_at",
+];
+const MIN_MILLIS: i64 = 100_000_000_000;
+
+#[derive(Clone, Copy)]
+enum ImportFormat {
+    Json,
+    Ndjson,
+    Sqlite,
+    CsvZip,
+}
+
+impl ImportFormat {
+    fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("json") => Ok(ImportFormat::Json),
+            Some("ndjson") => Ok(ImportFormat::Ndjson),
+            Some("db") | Some("sqlite") | Some("sqlite3") => Ok(ImportFormat::Sqlite),
+            Some("zip") => Ok(ImportFormat::CsvZip),
+            _ => Err(format!("Unrecognised import file {}", path.display())),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ImportFormat::Json => "json",
+            ImportFormat::Ndjson => "ndjson",
+            ImportFormat::Sqlite => "sqlite",
+            ImportFormat::CsvZip => "csv",
+        }
+    }
+}
+
+fn header_workstation(header: &Map<String, Value>) -> Option<WorkstationInfo> {
+    header
+        .get("workstation")
+        .and_then(|w| serde_json::from_value(w.clone()).ok())
+}
+
+// Inserts rows into the local tables, skipping ones whose primary key exists
+struct RowImporter<'a> {
+    tx: &'a Transaction<'a>,
+    source_id: String,
+    // Columns of each local table, so files from other versions still import
+    columns: HashMap<&'static str, HashSet<String>>,
+    inserted: Map<String, Value>,
+    skipped: Map<String, Value>,
+}
+
+impl<'a> RowImporter<'a> {
+    fn new(tx: &'a Transaction<'a>, source_id: String) -> rusqlite::Result<Self> {
+        let mut columns = HashMap::new();
+        for table in EXPORT_TABLES {
+            let mut stmt =
+                tx.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+            let names = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<HashSet<String>>>()?;
+            columns.insert(table, names);
+        }
+        Ok(RowImporter {
+            tx,
+            source_id,
+            columns,
+            inserted: Map::new(),
+            skipped: Map::new(),
+        })
+    }
+
+    fn bump(counts: &mut Map<String, Value>, table: &str) {
+        let n = counts.get(table).and_then(Value::as_u64).unwrap_or(0) + 1;
+        counts.insert(table.to_string(), json!(n));
+    }
+
+    fn insert(&mut self, table: &str, mut row: Map<String, Value>) -> Result<(), String> {
+        let Some(known) = self.columns.get(table) else {
+            // Not one of the feedback tables (e.g. app_settings in a snapshot)
+            return Ok(());
+        };
+
+        for column in TIMESTAMP_COLUMNS {
+            if let Some(seconds) = row
+                .get(column)
+                .and_then(Value::as_i64)
+                .filter(|v| *v < MIN_MILLIS)
+            {
+                row.insert(column.to_string(), json!(seconds * 1000));
+            }
+        }
+        // Rows merged elsewhere keep the workstation they originally came from
+        if row.get("source_workstation").is_none_or(Value::is_null) {
+            row.insert("source_workstation".to_string(), json!(self.source_id));
+        }
+
+        let columns: Vec<&String> = row.keys().filter(|c| known.contains(*c)).collect();
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let sql = format!(
+            "INSERT OR IGNORE INTO {} ({}) VALUES ({})",
+            table,
+            columns
+                .iter()
+                .map(|c| c.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            placeholders
+        );
+        let values = columns.iter().map(|c| json_to_sql(&row[c.as_str()]));
+
+        // A missing parent session fails the foreign key; count it as skipped
+        let changed = match self
+            .tx
+            .prepare_cached(&sql)
+            .and_then(|mut stmt| stmt.execute(params_from_iter(values)))
+        {
+            Ok(n) => n,
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                0
+            }
+            Err(e) => return Err(format!("Failed to import {} row: {}", table, e)),
+        };
+
+        if changed > 0 {
+            Self::bump(&mut self.inserted, table);
+        } else {
+            Self::bump(&mut self.skipped, table);
+        }
+        Ok(())
+    }
+}
+
+// Receives the file header, then its rows in table order
+struct ImportState<'a> {
+    tx: &'a Transaction<'a>,
+    // Explicit workstation chosen by the user, wins over the file header
+    workstation: Option<WorkstationInfo>,
+    source: WorkstationInfo,
+    exported_at: Option<i64>,
+    importer: Option<RowImporter<'a>>,
+}
+
+impl<'a> ImportState<'a> {
+    fn header(&mut self, header: &Map<String, Value>) {
+        self.exported_at = header.get("exportDate").and_then(Value::as_i64);
+        if let Some(info) = self
+            .workstation
+            .clone()
+            .or_else(|| header_workstation(header))
+        {
+            self.source = info;
+        }
+    }
+
+    fn row(&mut self, table: &str, row: Map<String, Value>) -> Result<(), String> {
+        if self.importer.is_none() {
+            let importer =
+                RowImporter::new(self.tx, self.source.id.clone()).map_err(|e| e.to_string())?;
+            self.importer = Some(importer);
+        }
+        match self.importer.as_mut() {
+            Some(importer) => importer.insert(table, row),
+            None => Ok(()),
+        }
+    }
+}
+
+// Streams the top-level export object: header fields come first, then each
+// table's rows are handed to the import as they are parsed
+struct JsonDocument<'s, 'a> {
+    state: &'s mut ImportState<'a>,
+}
+
+impl<'de> Visitor<'de> for JsonDocument<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an export_data JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut header = Map::new();
+        let mut header_applied = false;
+        while let Some(key) = map.next_key::<String>()? {
+            if let Some(table) = EXPORT_TABLES.iter().find(|t| **t == key) {
+                if !header_applied {
+                    self.state.header(&header);
+                    header_applied = true;
+                }
+                map.next_value_seed(TableRows {
+                    state: &mut *self.state,
+                    table,
+                })?;
+            } else if header_applied {
+                // Trailing fields such as rowCounts
+                map.next_value::<IgnoredAny>()?;
+            } else {
+                header.insert(key, map.next_value()?);
+            }
+        }
+        if !header_applied {
+            self.state.header(&header);
+        }
+        Ok(())
+    }
+}
+
+struct TableRows<'s, 'a> {
+    state: &'s mut ImportState<'a>,
+    table: &'static str,
+}
+
+impl<'de> DeserializeSeed<'de> for TableRows<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TableRows<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of {} rows", self.table)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(row) = seq.next_element::<Value>()? {
+            if let Value::Object(row) = row {
+                self.state.row(self.table, row).map_err(de::Error::custom)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_json(path: &Path, state: &mut ImportState) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+    de::Deserializer::deserialize_map(&mut deserializer, JsonDocument { state })
+        .map_err(|e| e.to_string())?;
+    deserializer.end().map_err(|e| e.to_string())
+}
+
+fn read_ndjson(path: &Path, state: &mut ImportState) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header = lines
+        .next()
+        .ok_or("Empty NDJSON file")?
+        .map_err(|e| e.to_string())?;
+    let header: Map<String, Value> = serde_json::from_str(&header).map_err(|e| e.to_string())?;
+    state.header(&header);
+
+    for (number, line) in lines.enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record: Map<String, Value> =
+            serde_json::from_str(&line).map_err(|e| format!("Line {}: {}", number + 2, e))?;
+        if let (Some(Value::String(table)), Some(Value::Object(row))) =
+            (record.remove("table"), record.remove("row"))
+        {
+            state.row(&table, row)?;
+        }
+    }
+    Ok(())
+}
+
+// Either an export_data snapshot or a copy of another workstation's feedback.db
+fn read_sqlite(path: &Path, state: &mut ImportState) -> Result<(), String> {
+    let snapshot = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    let has_table = |name: &str| -> Result<bool, String> {
+        snapshot
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                params![name],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Cannot read {} (encrypted?): {}", path.display(), e))
+    };
+
+    let mut header: Map<String, Value> = Map::new();
+    if has_table("app_settings")? {
+        header = settings::load(&snapshot, EXPORT_INFO_SETTING)?;
+        if let (false, Some(info)) = (
+            header.contains_key("workstation"),
+            workstation::stored(&snapshot),
+        ) {
+            header.insert("workstation".to_string(), json!(info));
+        }
+    }
+    state.header(&header);
+
+    for table in EXPORT_TABLES {
+        if !has_table(table)? {
+            continue;
+        }
+        let mut stmt = snapshot
+            .prepare(&format!("SELECT * FROM {}", table))
+            .map_err(|e| e.to_string())?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            state.row(
+                table,
+                row_to_json(row, &columns).map_err(|e| e.to_string())?,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Declared type and NOT NULL flag of each column of a local table
+fn column_types(tx: &Transaction, table: &str) -> Result<HashMap<String, (String, bool)>, String> {
+    let mut stmt = tx
+        .prepare(&format!(
+            "SELECT name, type, \"notnull\" FROM pragma_table_info('{}')",
+            table
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (row.get::<_, String>(1)?.to_ascii_uppercase(), row.get(2)?),
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<_>>()
+        .map_err(|e| e.to_string())
+}
+
+// CSV cells are text; give them back the type of the local column. Empty cells
+// were NULL unless the column cannot hold one.
+fn csv_value(cell: String, column: Option<&(String, bool)>) -> Value {
+    if cell.starts_with(&format!("{{\"{}\"", BLOB_TAG)) {
+        if let Ok(blob @ Value::Object(_)) = serde_json::from_str(&cell) {
+            return blob;
+        }
+    }
+    let Some((declared, not_null)) = column else {
+        return Value::String(cell);
+    };
+    let numeric = declared.contains("INT") || declared.contains("BOOL");
+    if cell.is_empty() {
+        return if *not_null && !numeric && !declared.contains("REAL") {
+            Value::String(cell)
+        } else {
+            Value::Null
+        };
+    }
+    if numeric {
+        if let Ok(v) = cell.parse::<i64>() {
+            return json!(v);
+        }
+    }
+    if declared.contains("REAL") {
+        if let Ok(v) = cell.parse::<f64>() {
+            return json!(v);
+        }
+    }
+    Value::String(cell)
+}
+
+// A CSV export: <table>.csv entries and a manifest.json holding the header
+fn read_csv_zip(path: &Path, state: &mut ImportState) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+
+    let header: Map<String, Value> = match archive.by_name("manifest.json") {
+        Ok(entry) => serde_json::from_reader(entry).map_err(|e| format!("manifest.json: {}", e))?,
+        Err(zip::result::ZipError::FileNotFound) => Map::new(),
+        Err(e) => return Err(e.to_string()),
+    };
+    state.header(&header);
+
+    for table in EXPORT_TABLES {
+        let entry = match archive.by_name(&format!("{}.csv", table)) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+        let types = column_types(state.tx, table)?;
+        let mut reader = csv::Reader::from_reader(entry);
+        let columns: Vec<String> = reader
+            .headers()
+            .map_err(|e| format!("{}.csv: {}", table, e))?
+            .iter()
+            .map(|c| c.trim_start_matches('\u{feff}').to_string())
+            .collect();
+        for record in reader.records() {
+            let record = record.map_err(|e| format!("{}.csv: {}", table, e))?;
+            let row = columns
+                .iter()
+                .zip(record.iter())
+                .map(|(column, cell)| {
+                    (
+                        column.clone(),
+                        csv_value(cell.to_string(), types.get(column)),
+                    )
+                })
+                .collect();
+            state.row(table, row)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merge an export file into the local database.
+///
+/// Rows are de-duplicated by primary key, so importing the same file twice
+/// (or overlapping exports) adds nothing the second time. Every imported row
+/// records its source workstation: taken from the file, overridden by
+/// `workstation`, or derived from the file name as a last resort.
+pub fn import_file(
+    conn: &mut Connection,
+    path: &Path,
+    workstation: Option<WorkstationInfo>,
+) -> Result<ImportReport, String> {
+    let format = ImportFormat::from_path(path)?;
+    let import_id = Uuid::new_v4().to_string();
+    let fallback = WorkstationInfo {
+        id: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| import_id.clone()),
+        name: String::new(),
+    };
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut state = ImportState {
+        tx: &tx,
+        workstation,
+        source: fallback,
+        exported_at: None,
+        importer: None,
+    };
+    match format {
+        ImportFormat::Json => read_json(path, &mut state)?,
+        ImportFormat::Ndjson => read_ndjson(path, &mut state)?,
+        ImportFormat::Sqlite => read_sqlite(path, &mut state)?,
+        ImportFormat::CsvZip => read_csv_zip(path, &mut state)?,
+    }
+
+    let ImportState {
+        source,
+        exported_at,
+        importer,
+        ..
+    } = state;
+    let (inserted, skipped) = importer
+        .map(|i| (i.inserted, i.skipped))
+        .unwrap_or_default();
+    let imported_at = current_timestamp();
+    let workstation_name = Some(source.name.clone()).filter(|n| !n.is_empty());
+
+    tx.execute(
+        "INSERT INTO import_sources (import_id, workstation_id, workstation_name, file_path, format, exported_at, inserted_rows, skipped_rows, imported_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            &import_id,
+            &source.id,
+            &workstation_name,
+            path.to_string_lossy(),
+            format.as_str(),
+            exported_at,
+            Value::Object(inserted.clone()).to_string(),
+            Value::Object(skipped.clone()).to_string(),
+            imported_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    println!(
+        "[Feedback] Imported {:?} from workstation {}: inserted {:?}, skipped {:?}",
+        path, source.id, inserted, skipped
+    );
+
+    Ok(ImportReport {
+        import_id,
+        workstation_id: source.id,
+        workstation_name,
+        path: path.to_string_lossy().to_string(),
+        format: format.as_str().to_string(),
+        inserted_rows: Value::Object(inserted),
+        skipped_rows: Value::Object(skipped),
+        imported_at,
+    })
+}
+
+// Import Commands
+
+#[command]
+pub async fn import_data(
+    app: AppHandle,
+    input_path: Option<String>,
+    workstation_id: Option<String>,
+    workstation_name: Option<String>,
+) -> Result<Option<ImportReport>, String> {
+    let path = match input_path {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let handle = rfd::AsyncFileDialog::new()
+                .set_title("导入反馈数据")
+                .add_filter(
+                    "Feedback export",
+                    &["json", "ndjson", "zip", "db", "sqlite", "sqlite3"],
+                )
+                .pick_file()
+                .await;
+            match handle {
+                Some(h) => h.path().to_path_buf(),
+                None => return Ok(None),
+            }
+        }
+    };
+    let workstation = workstation_id
+        .filter(|id| !id.trim().is_empty())
+        .map(|id| WorkstationInfo {
+            id,
+            name: workstation_name.unwrap_or_default(),
+        });
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbConnection>();
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        import_file(&mut conn, &path, workstation).map(Some)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Every workstation with data in this database, local one first
+#[command]
+pub async fn list_workstations(app: AppHandle) -> Result<Vec<WorkstationSummary>, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let local = workstation::local(&conn)?;
+
+    let local_sessions: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sessions WHERE source_workstation IS NULL OR source_workstation = ?1",
+            params![&local.id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let mut workstations = vec![WorkstationSummary {
+        workstation_id: local.id.clone(),
+        name: Some(local.name.clone()),
+        local: true,
+        session_count: local_sessions,
+        import_count: 0,
+        last_imported_at: None,
+    }];
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.workstation_id,
+                    MAX(i.workstation_name),
+                    (SELECT COUNT(*) FROM sessions s WHERE s.source_workstation = i.workstation_id),
+                    COUNT(*),
+                    MAX(i.imported_at)
+             FROM import_sources i
+             WHERE i.workstation_id != ?1
+             GROUP BY i.workstation_id
+             ORDER BY MAX(i.imported_at) DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![&local.id], |row| {
+            Ok(WorkstationSummary {
+                workstation_id: row.get(0)?,
+                name: row.get(1)?,
+                local: false,
+                session_count: row.get(2)?,
+                import_count: row.get(3)?,
+                last_imported_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        workstations.push(row.map_err(|e| e.to_string())?);
+    }
+
+    Ok(workstations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::export::{export_to_path, ExportFormat, ExportRange};
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+        crate::db::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn seed(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO sessions (session_id, patient_id, patient_name, session_type, start_time, status, metadata, created_at)
+             VALUES ('s1', 'P001', '张三', 'consultation', 1700000000000, 'completed', '{\"doctor\":\"D01\"}', 1700000000000);
+             INSERT INTO messages (message_id, session_id, role, content, images, token_count, llm_model, latency_ms, created_at)
+             VALUES ('m1', 's1', 'user', '', X'00FF10', NULL, 'gpt-4o-mini', 120, 1700000001000),
+                    ('m2', 's1', 'assistant', '头痛, \"持续\"三天
+伴发热', NULL, 42, NULL, NULL, 1700000002000);
+             INSERT INTO recommendations (recommendation_id, session_id, rec_type, content, matched, match_confidence, prompt_tokens, completion_tokens, latency_ms, llm_model, created_at)
+             VALUES ('r1', 's1', 'diagnosis', '{\"name\":\"偏头痛\",\"code\":\"G43.900\"}', 1, 0.75, 900, 300, 1500, 'deepseek-chat', 1700000003000);
+             INSERT INTO performance_metrics (metric_id, session_id, metric_type, metric_value, unit, context, created_at)
+             VALUES ('pm1', NULL, 'llm_latency', 12.5, 'ms', NULL, 1700000004000);",
+        )
+        .unwrap();
+    }
+
+    fn rows(conn: &Connection, table: &str) -> Vec<Map<String, Value>> {
+        let mut stmt = conn
+            .prepare(&format!("SELECT * FROM {} ORDER BY 1", table))
+            .unwrap();
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([]).unwrap();
+        let mut out = Vec::new();
+        while let Some(row) = rows.next().unwrap() {
+            let mut row = row_to_json(row, &columns).unwrap();
+            row.remove("source_workstation");
+            out.push(row);
+        }
+        out
+    }
+
+    fn round_trip(format: ExportFormat, extension: &str) {
+        let source = database();
+        seed(&source);
+        let path =
+            std::env::temp_dir().join(format!("feedback-import-{}.{}", Uuid::new_v4(), extension));
+        export_to_path(&source, format, ExportRange::default(), &path, None).unwrap();
+
+        let mut target = database();
+        let report = import_file(&mut target, &path, None);
+        let _ = std::fs::remove_file(&path);
+        let report = report.unwrap();
+
+        assert_eq!(
+            report.workstation_id,
+            workstation::local(&source).unwrap().id
+        );
+        assert_eq!(report.inserted_rows["messages"], json!(2));
+        for table in EXPORT_TABLES {
+            assert_eq!(rows(&source, table), rows(&target, table), "{}", table);
+        }
+        let image_type: String = target
+            .query_row(
+                "SELECT typeof(images) FROM messages WHERE message_id = 'm1'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(image_type, "blob");
+
+        // Importing the same file again adds nothing
+        export_to_path(&source, format, ExportRange::default(), &path, None).unwrap();
+        let again = import_file(&mut target, &path, None);
+        let _ = std::fs::remove_file(&path);
+        assert!(again.unwrap().inserted_rows.as_object().unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_export_round_trips() {
+        round_trip(ExportFormat::Json, "json");
+    }
+
+    #[test]
+    fn ndjson_export_round_trips() {
+        round_trip(ExportFormat::Ndjson, "ndjson");
+    }
+
+    #[test]
+    fn csv_zip_export_round_trips() {
+        round_trip(ExportFormat::Csv, "zip");
+    }
+
+    #[test]
+    fn sqlite_export_round_trips() {
+        round_trip(ExportFormat::Sqlite, "db");
+    }
+
+    #[test]
+    fn csv_cells_take_the_local_column_type() {
+        let integer = ("INTEGER".to_string(), true);
+        let real = ("REAL".to_string(), false);
+        let text = ("TEXT".to_string(), false);
+        let required_text = ("TEXT".to_string(), true);
+
+        assert_eq!(
+            csv_value("1700000000000".into(), Some(&integer)),
+            json!(1700000000000i64)
+        );
+        assert_eq!(csv_value("0.75".into(), Some(&real)), json!(0.75));
+        assert_eq!(csv_value("".into(), Some(&real)), Value::Null);
+        assert_eq!(csv_value("".into(), Some(&text)), Value::Null);
+        assert_eq!(csv_value("".into(), Some(&required_text)), json!(""));
+        assert_eq!(csv_value("042".into(), Some(&text)), json!("042"));
+        assert_eq!(
+            csv_value(r#"{"$base64":"AP8Q"}"#.into(), Some(&text)),
+            json!({ "$base64": "AP8Q" })
+        );
+        assert_eq!(csv_value("x".into(), None), json!("x"));
+    }
+}
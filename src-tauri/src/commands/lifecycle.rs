@@ -0,0 +1,234 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager};
+
+use super::feedback::{current_timestamp, DbConnection};
+use crate::db::models::{Session, SessionLifecyclePolicy, SessionSweepReport};
+use crate::db::settings;
+
+const LIFECYCLE_SETTING: &str = "session_lifecycle";
+
+// Reasons recorded in sessions.metadata.closeReason
+pub(crate) const REASON_IDLE: &str = "idle_timeout";
+const REASON_INTERRUPTED: &str = "interrupted";
+
+// Latest write belonging to each session; a session with no rows counts from its start
+const LAST_ACTIVITY_SQL: &str = "MAX(
+    s.start_time,
+    COALESCE((SELECT MAX(created_at) FROM messages WHERE session_id = s.session_id), 0),
+    COALESCE((SELECT MAX(created_at) FROM feedbacks WHERE session_id = s.session_id), 0),
+    COALESCE((SELECT MAX(created_at) FROM recommendations WHERE session_id = s.session_id), 0),
+    COALESCE((SELECT MAX(created_at) FROM operation_logs WHERE session_id = s.session_id), 0),
+    COALESCE((SELECT MAX(created_at) FROM performance_metrics WHERE session_id = s.session_id), 0),
+    COALESCE((SELECT MAX(COALESCE(answered_at, created_at)) FROM template_usage WHERE session_id = s.session_id), 0)
+)";
+
+// Cancel active local sessions matching `condition` (over `last_activity`),
+// ending them at their last activity and recording why in metadata. Sessions
+// imported from other workstations are left as they were recorded.
+fn cancel_sessions(
+    conn: &Connection,
+    condition: &str,
+    reason: &str,
+    idle_cutoff: i64,
+) -> rusqlite::Result<usize> {
+    conn.execute(
+        &format!(
+            "WITH activity AS (
+                SELECT s.session_id, {} AS last_activity
+                FROM sessions s
+                WHERE s.status = 'active'
+                  AND s.source_workstation IS NULL
+            )
+            UPDATE sessions
+            SET status = 'cancelled',
+                end_time = (SELECT last_activity FROM activity a WHERE a.session_id = sessions.session_id),
+                metadata = json_set(
+                    CASE WHEN json_valid(metadata) THEN metadata ELSE '{{}}' END,
+                    '$.closeReason', ?1,
+                    '$.closedAt', ?2
+                )
+            WHERE session_id IN (SELECT session_id FROM activity WHERE {})",
+            LAST_ACTIVITY_SQL, condition
+        ),
+        params![reason, current_timestamp(), idle_cutoff],
+    )
+}
+
+/// Cancel sessions that have been idle longer than the policy allows.
+///
+/// On startup every remaining active session belongs to a previous run, so the
+/// recent ones are cancelled too but marked `interrupted`, which makes them
+/// candidates for `resume_interrupted_session`.
+pub fn sweep_sessions(
+    conn: &Connection,
+    policy: &SessionLifecyclePolicy,
+    at_startup: bool,
+) -> rusqlite::Result<SessionSweepReport> {
+    let idle_cutoff = current_timestamp() - i64::from(policy.idle_timeout_minutes) * 60_000;
+
+    let idle_cancelled = cancel_sessions(conn, "last_activity < ?3", REASON_IDLE, idle_cutoff)?;
+    let interrupted_cancelled = if at_startup {
+        cancel_sessions(conn, "last_activity >= ?3", REASON_INTERRUPTED, idle_cutoff)?
+    } else {
+        0
+    };
+
+    Ok(SessionSweepReport {
+        idle_cancelled,
+        interrupted_cancelled,
+        swept_at: current_timestamp(),
+    })
+}
+
+fn load_policy(conn: &Connection) -> SessionLifecyclePolicy {
+    settings::load(conn, LIFECYCLE_SETTING).unwrap_or_default()
+}
+
+fn read_session(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    let metadata: Option<String> = row.get(7)?;
+    Ok(Session {
+        session_id: row.get(0)?,
+        patient_id: row.get(1)?,
+        patient_name: row.get(2)?,
+        session_type: row.get(3)?,
+        start_time: row.get(4)?,
+        end_time: row.get(5)?,
+        status: row.get(6)?,
+        metadata: metadata.and_then(|m| serde_json::from_str(&m).ok()),
+        created_at: row.get(8)?,
+    })
+}
+
+// Run once from setup, before the HTTP server can open new sessions
+pub fn sweep_on_startup(app: &AppHandle) {
+    let Some(db) = app.try_state::<DbConnection>() else {
+        return;
+    };
+    let result = db.0.lock().map_err(|e| e.to_string()).and_then(|conn| {
+        let policy = load_policy(&conn);
+        sweep_sessions(&conn, &policy, true).map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(report) => println!("[Feedback] Startup session sweep: {:?}", report),
+        Err(e) => eprintln!("[Feedback] Startup session sweep failed: {}", e),
+    }
+}
+
+// Periodic idle sweep, re-reading the policy every cycle
+pub fn start_session_sweeper(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let Some(db) = app.try_state::<DbConnection>() else {
+            eprintln!("[Feedback] Database not available, session sweeper stopped");
+            return;
+        };
+
+        let interval_minutes = match db.0.lock() {
+            Ok(conn) => {
+                let policy = load_policy(&conn);
+                match sweep_sessions(&conn, &policy, false) {
+                    Ok(report) if report.idle_cancelled > 0 => {
+                        println!(
+                            "[Feedback] Cancelled {} idle sessions",
+                            report.idle_cancelled
+                        )
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("[Feedback] Session sweep failed: {}", e),
+                }
+                policy.sweep_interval_minutes.max(1)
+            }
+            Err(e) => {
+                eprintln!("[Feedback] Failed to lock database: {}", e);
+                SessionLifecyclePolicy::default().sweep_interval_minutes
+            }
+        };
+
+        std::thread::sleep(Duration::from_secs(u64::from(interval_minutes) * 60));
+    });
+}
+
+// Session Lifecycle Commands
+
+#[command]
+pub async fn get_session_lifecycle_policy(
+    app: AppHandle,
+) -> Result<SessionLifecyclePolicy, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_policy(&conn))
+}
+
+#[command]
+pub async fn set_session_lifecycle_policy(
+    app: AppHandle,
+    policy: SessionLifecyclePolicy,
+) -> Result<(), String> {
+    if policy.idle_timeout_minutes == 0 || policy.sweep_interval_minutes == 0 {
+        return Err("idleTimeoutMinutes and sweepIntervalMinutes must be at least 1".to_string());
+    }
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::store(&conn, LIFECYCLE_SETTING, &policy)
+}
+
+#[command]
+pub async fn sweep_idle_sessions(app: AppHandle) -> Result<SessionSweepReport, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let policy = load_policy(&conn);
+    sweep_sessions(&conn, &policy, false).map_err(|e| e.to_string())
+}
+
+/// Reopen the most recent session cut off by the last shutdown or crash.
+///
+/// Only local sessions the startup sweep marked `interrupted` qualify, and
+/// only while they are still within the idle timeout. Returns None when there is
+/// nothing to resume.
+#[command]
+pub async fn resume_interrupted_session(app: AppHandle) -> Result<Option<Session>, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let policy = load_policy(&conn);
+    let idle_cutoff = current_timestamp() - i64::from(policy.idle_timeout_minutes) * 60_000;
+
+    let session_id: Option<String> = conn
+        .query_row(
+            "SELECT session_id FROM sessions
+             WHERE status = 'cancelled'
+               AND source_workstation IS NULL
+               AND json_valid(metadata)
+               AND json_extract(metadata, '$.closeReason') = ?1
+               AND end_time >= ?2
+             ORDER BY end_time DESC
+             LIMIT 1",
+            params![REASON_INTERRUPTED, idle_cutoff],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some(session_id) = session_id else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "UPDATE sessions
+         SET status = 'active',
+             end_time = NULL,
+             metadata = json_set(json_remove(metadata, '$.closeReason', '$.closedAt'), '$.resumedAt', ?1)
+         WHERE session_id = ?2",
+        params![current_timestamp(), &session_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    println!("[Feedback] Resumed interrupted session {}", session_id);
+    conn.query_row(
+        "SELECT session_id, patient_id, patient_name, session_type, start_time, end_time, status, metadata, created_at
+         FROM sessions WHERE session_id = ?1",
+        params![&session_id],
+        read_session,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
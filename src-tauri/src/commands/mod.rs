@@ -1,3 +1,14 @@
+pub mod analytics;
+pub mod batch;
+pub mod cost;
+pub mod encryption;
+pub mod export;
 pub mod feedback;
+pub mod import;
+pub mod lifecycle;
+pub mod retention;
+pub mod sync;
+pub mod template_subscription;
+pub mod templates;
 
 // No re-exports needed as they are accessed via full path in lib.rsck,
@@ -0,0 +1,300 @@
+use rusqlite::{params, Connection, ToSql, Transaction};
+use serde_json::{json, Map, Value};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager};
+
+use super::feedback::{current_timestamp, DbConnection};
+use crate::db::models::{DatabaseSizeReport, PurgeReport, RetentionPolicy};
+use crate::db::settings;
+
+const RETENTION_SETTING: &str = "retention_policy";
+
+// Tables whose rows belong to a session (all declare ON DELETE CASCADE)
+const SESSION_CHILD_TABLES: [&str; 6] = [
+    "messages",
+    "feedbacks",
+    "recommendations",
+    "operation_logs",
+    "performance_metrics",
+    "template_usage",
+];
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+fn cutoff(days: u32) -> i64 {
+    current_timestamp() - i64::from(days) * MS_PER_DAY
+}
+
+fn add_count(counts: &mut Map<String, Value>, table: &str, n: usize) {
+    let total = counts.get(table).and_then(Value::as_u64).unwrap_or(0) + n as u64;
+    counts.insert(table.to_string(), json!(total));
+}
+
+// Delete the sessions matched by `condition` together with every row that
+// references them, so the result is the same whether or not SQLite enforces
+// the cascades on this connection.
+fn delete_sessions_where(
+    tx: &Transaction,
+    condition: &str,
+    params: &[&dyn ToSql],
+    counts: &mut Map<String, Value>,
+) -> rusqlite::Result<()> {
+    for table in SESSION_CHILD_TABLES {
+        let n = tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE session_id IN (SELECT session_id FROM sessions WHERE {})",
+                table, condition
+            ),
+            params,
+        )?;
+        add_count(counts, table, n);
+    }
+    let n = tx.execute(&format!("DELETE FROM sessions WHERE {}", condition), params)?;
+    add_count(counts, "sessions", n);
+    Ok(())
+}
+
+pub fn purge_expired(
+    conn: &mut Connection,
+    policy: &RetentionPolicy,
+) -> Result<PurgeReport, String> {
+    let mut deleted = Map::new();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    // Active sessions are never purged, however old they are
+    if let Some(days) = policy.sessions_days {
+        delete_sessions_where(
+            &tx,
+            "status != 'active' AND COALESCE(end_time, start_time) < ?1",
+            params![cutoff(days)],
+            &mut deleted,
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let per_table = [
+        ("messages", policy.messages_days),
+        ("feedbacks", policy.feedbacks_days),
+        ("recommendations", policy.recommendations_days),
+        ("operation_logs", policy.operation_logs_days),
+        ("performance_metrics", policy.performance_metrics_days),
+        ("template_usage", policy.template_usage_days),
+    ];
+    for (table, days) in per_table {
+        if let Some(days) = days {
+            let n = tx
+                .execute(
+                    &format!("DELETE FROM {} WHERE created_at < ?1", table),
+                    params![cutoff(days)],
+                )
+                .map_err(|e| e.to_string())?;
+            add_count(&mut deleted, table, n);
+        }
+    }
+
+    // Images are the bulk of the file; they can expire before the message text
+    let images_cleared = match policy.message_images_days {
+        Some(days) => tx
+            .execute(
+                "UPDATE messages SET images = NULL WHERE images IS NOT NULL AND created_at < ?1",
+                params![cutoff(days)],
+            )
+            .map_err(|e| e.to_string())?,
+        None => 0,
+    };
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let removed_any = images_cleared > 0 || deleted.values().any(|v| v.as_u64().unwrap_or(0) > 0);
+    let vacuumed = policy.vacuum_after_purge && removed_any;
+    if vacuumed {
+        conn.execute_batch("VACUUM").map_err(|e| e.to_string())?;
+    }
+
+    Ok(PurgeReport {
+        deleted_rows: Value::Object(deleted),
+        images_cleared: images_cleared as i64,
+        vacuumed,
+        purged_at: current_timestamp(),
+    })
+}
+
+fn database_size(conn: &Connection) -> Result<DatabaseSizeReport, String> {
+    let pragma = |name: &str| -> Result<i64, String> {
+        conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))
+            .map_err(|e| e.to_string())
+    };
+
+    let mut row_counts = Map::new();
+    for table in std::iter::once("sessions").chain(SESSION_CHILD_TABLES) {
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| e.to_string())?;
+        row_counts.insert(table.to_string(), json!(count));
+    }
+
+    let file_size_bytes = conn
+        .path()
+        .and_then(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    Ok(DatabaseSizeReport {
+        file_size_bytes,
+        page_size: pragma("page_size")?,
+        page_count: pragma("page_count")?,
+        freelist_count: pragma("freelist_count")?,
+        row_counts: Value::Object(row_counts),
+    })
+}
+
+// Retention Policy Commands
+
+#[command]
+pub async fn get_retention_policy(app: AppHandle) -> Result<RetentionPolicy, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings::load(&conn, RETENTION_SETTING)
+}
+
+#[command]
+pub async fn set_retention_policy(app: AppHandle, policy: RetentionPolicy) -> Result<(), String> {
+    if policy.purge_interval_hours == 0 {
+        return Err("purgeIntervalHours must be at least 1".to_string());
+    }
+
+    {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        settings::store(&conn, RETENTION_SETTING, &policy)?;
+    }
+    if let Some(worker) = app.try_state::<PurgeWorker>() {
+        worker.policy_changed();
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn purge_expired_data(app: AppHandle) -> Result<PurgeReport, String> {
+    let db = app.state::<DbConnection>();
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let policy: RetentionPolicy = settings::load(&conn, RETENTION_SETTING)?;
+    purge_expired(&mut conn, &policy)
+}
+
+// Privacy requests: remove every session of a patient and everything attached to it
+#[command]
+pub async fn delete_patient_data(
+    app: AppHandle,
+    patient_id: String,
+) -> Result<serde_json::Value, String> {
+    if patient_id.trim().is_empty() {
+        return Err("patient_id must not be empty".to_string());
+    }
+
+    let db = app.state::<DbConnection>();
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut deleted = Map::new();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    delete_sessions_where(&tx, "patient_id = ?1", params![&patient_id], &mut deleted)
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    println!("[Retention] Deleted patient data on request: {:?}", deleted);
+    Ok(Value::Object(deleted))
+}
+
+#[command]
+pub async fn get_database_size(app: AppHandle) -> Result<DatabaseSizeReport, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    database_size(&conn)
+}
+
+#[command]
+pub async fn vacuum_database(app: AppHandle) -> Result<DatabaseSizeReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute_batch("VACUUM").map_err(|e| e.to_string())?;
+        database_size(&conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Wakes the purge worker when the policy changes, so the new policy applies
+/// right away instead of after the current interval.
+#[derive(Default)]
+pub struct PurgeWorker {
+    changed: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl PurgeWorker {
+    fn policy_changed(&self) {
+        match self.changed.lock() {
+            Ok(mut changed) => {
+                *changed = true;
+                self.wake.notify_one();
+            }
+            Err(e) => eprintln!("[Retention] Purge worker lock poisoned: {}", e),
+        }
+    }
+
+    // Sleep for `interval` or until the policy changes
+    fn wait(&self, interval: Duration) {
+        match self.changed.lock() {
+            Ok(changed) => {
+                if let Ok((mut changed, _)) =
+                    self.wake.wait_timeout_while(changed, interval, |c| !*c)
+                {
+                    *changed = false;
+                }
+            }
+            Err(e) => {
+                eprintln!("[Retention] Purge worker lock poisoned: {}", e);
+                std::thread::sleep(interval);
+            }
+        }
+    }
+}
+
+// Background purge, re-reading the policy every cycle so changes apply without a restart
+pub fn start_purge_worker(app: AppHandle) {
+    app.manage(PurgeWorker::default());
+
+    std::thread::spawn(move || {
+        let worker = app.state::<PurgeWorker>();
+        // Let startup finish before touching the database
+        worker.wait(Duration::from_secs(60));
+        loop {
+            let Some(db) = app.try_state::<DbConnection>() else {
+                eprintln!("[Retention] Database not available, purge worker stopped");
+                return;
+            };
+
+            let interval_hours = match db.0.lock() {
+                Ok(mut conn) => {
+                    let policy: RetentionPolicy =
+                        settings::load(&conn, RETENTION_SETTING).unwrap_or_default();
+                    match purge_expired(&mut conn, &policy) {
+                        Ok(report) => println!("[Retention] Purge finished: {:?}", report),
+                        Err(e) => eprintln!("[Retention] Purge failed: {}", e),
+                    }
+                    policy.purge_interval_hours.max(1)
+                }
+                Err(e) => {
+                    eprintln!("[Retention] Failed to lock database: {}", e);
+                    RetentionPolicy::default().purge_interval_hours
+                }
+            };
+
+            worker.wait(Duration::from_secs(u64::from(interval_hours) * 3600));
+        }
+    });
+}
@@ -0,0 +1,491 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager};
+use uuid::Uuid;
+
+use super::export::{load_or_create_anonymization_key, row_to_json, EXPORT_TABLES};
+use super::feedback::{current_timestamp, DbConnection};
+use crate::db::anonymize::Anonymizer;
+use crate::db::models::{SyncConfig, SyncReport, SyncState, SyncStatus};
+use crate::db::{settings, workstation};
+
+// Also read by the sync_outbox triggers (migration 006)
+const SYNC_CONFIG_SETTING: &str = "sync_config";
+const SYNC_STATE_SETTING: &str = "sync_state";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_BATCH_SIZE: u32 = 5000;
+// Cap on error bodies kept in the status
+const MAX_ERROR_LEN: usize = 300;
+
+fn primary_key(table: &str) -> Option<&'static str> {
+    match table {
+        "sessions" => Some("session_id"),
+        "messages" => Some("message_id"),
+        "feedbacks" => Some("feedback_id"),
+        "recommendations" => Some("recommendation_id"),
+        "operation_logs" => Some("log_id"),
+        "performance_metrics" => Some("metric_id"),
+        "template_usage" => Some("usage_id"),
+        _ => None,
+    }
+}
+
+fn validate_config(config: &SyncConfig) -> Result<(), String> {
+    if config.interval_minutes == 0 || config.max_retries == 0 {
+        return Err("intervalMinutes and maxRetries must be at least 1".to_string());
+    }
+    if config.batch_size == 0 || config.batch_size > MAX_BATCH_SIZE {
+        return Err(format!(
+            "batchSize must be between 1 and {}",
+            MAX_BATCH_SIZE
+        ));
+    }
+
+    let Some(endpoint) = config.endpoint.as_deref().filter(|e| !e.trim().is_empty()) else {
+        return if config.enabled {
+            Err("An endpoint is required to enable sync".to_string())
+        } else {
+            Ok(())
+        };
+    };
+    check_endpoint(endpoint)
+}
+
+// Remote endpoints must use https; plain http is only accepted for a local stub
+pub(crate) fn check_endpoint(endpoint: &str) -> Result<(), String> {
+    let url = url::Url::parse(endpoint).map_err(|e| format!("Invalid endpoint: {}", e))?;
+    let local = matches!(
+        url.host_str(),
+        Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+    );
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if local => Ok(()),
+        _ => Err("The endpoint must use https (plain http only for localhost)".to_string()),
+    }
+}
+
+fn load_config(conn: &Connection) -> SyncConfig {
+    settings::load(conn, SYNC_CONFIG_SETTING).unwrap_or_default()
+}
+
+fn load_state(conn: &Connection) -> SyncState {
+    settings::load(conn, SYNC_STATE_SETTING).unwrap_or_default()
+}
+
+// Queue every local row not already waiting, for when sync is switched on.
+// Rows that were uploaded before are sent again; the endpoint upserts by id.
+fn enqueue_existing(conn: &Connection, high_water_mark: i64) -> rusqlite::Result<usize> {
+    let now = current_timestamp();
+    let mut queued = 0;
+    for table in EXPORT_TABLES {
+        let Some(key) = primary_key(table) else {
+            continue;
+        };
+        queued += conn.execute(
+            &format!(
+                "INSERT INTO sync_outbox (table_name, row_id, queued_at)
+                 SELECT ?1, t.{key}, ?2 FROM {table} t
+                 WHERE t.source_workstation IS NULL
+                   AND NOT EXISTS (
+                       SELECT 1 FROM sync_outbox o
+                       WHERE o.table_name = ?1 AND o.row_id = t.{key} AND o.seq > ?3
+                   )
+                 ORDER BY t.rowid",
+            ),
+            params![table, now, high_water_mark],
+        )?;
+    }
+    Ok(queued)
+}
+
+// Distinct rows still waiting per table, plus when the oldest was queued
+fn pending_rows(
+    conn: &Connection,
+    high_water_mark: i64,
+) -> rusqlite::Result<(Map<String, Value>, i64, Option<i64>)> {
+    let mut stmt = conn.prepare(
+        "SELECT table_name, COUNT(DISTINCT row_id), MIN(queued_at)
+         FROM sync_outbox WHERE seq > ?1
+         GROUP BY table_name",
+    )?;
+    let rows = stmt.query_map(params![high_water_mark], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+
+    let mut pending = Map::new();
+    let mut total = 0;
+    let mut oldest: Option<i64> = None;
+    for row in rows {
+        let (table, count, queued_at) = row?;
+        total += count;
+        oldest = Some(oldest.map_or(queued_at, |o| o.min(queued_at)));
+        pending.insert(table, json!(count));
+    }
+    Ok((pending, total, oldest))
+}
+
+struct Batch {
+    records: Vec<Value>,
+    to_seq: i64,
+}
+
+/// Read the next outbox entries after the high-water mark into upload records.
+///
+/// Rows queued several times are sent once, parents first. Rows deleted since
+/// they were queued (retention, patient deletion) are skipped; the outbox
+/// still advances past them.
+fn next_batch(
+    conn: &Connection,
+    high_water_mark: i64,
+    batch_size: u32,
+    mut anonymizer: Option<&mut Anonymizer>,
+) -> rusqlite::Result<Option<Batch>> {
+    let mut stmt = conn.prepare(
+        "SELECT seq, table_name, row_id FROM sync_outbox
+         WHERE seq > ?1 ORDER BY seq LIMIT ?2",
+    )?;
+    let entries = stmt
+        .query_map(params![high_water_mark, batch_size], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let Some(to_seq) = entries.last().map(|(seq, _, _)| *seq) else {
+        return Ok(None);
+    };
+
+    let mut seen = HashSet::new();
+    let mut records = Vec::new();
+    for table in EXPORT_TABLES {
+        let Some(key) = primary_key(table) else {
+            continue;
+        };
+        let mut select = conn.prepare(&format!("SELECT * FROM {} WHERE {} = ?1", table, key))?;
+        let columns: Vec<String> = select
+            .column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+
+        for (_, _, row_id) in entries.iter().filter(|(_, t, _)| t == table) {
+            if !seen.insert((table, row_id.as_str())) {
+                continue;
+            }
+            let Some(mut row) = select
+                .query_row(params![row_id], |r| row_to_json(r, &columns))
+                .optional()?
+            else {
+                continue;
+            };
+
+            if let Some(anonymizer) = anonymizer.as_deref_mut() {
+                let session_id = row.get("session_id").and_then(Value::as_str);
+                if let Some(session_id) = session_id.filter(|id| !anonymizer.knows_session(id)) {
+                    let name: Option<String> = conn
+                        .query_row(
+                            "SELECT patient_name FROM sessions WHERE session_id = ?1",
+                            params![session_id],
+                            |r| r.get(0),
+                        )
+                        .optional()?
+                        .flatten();
+                    anonymizer.remember_session(session_id, name.as_deref().unwrap_or_default());
+                }
+                anonymizer.apply(table, &mut row);
+            }
+            records.push(json!({ "table": table, "row": row }));
+        }
+    }
+
+    Ok(Some(Batch { records, to_seq }))
+}
+
+async fn post_batch(
+    client: &reqwest::Client,
+    config: &SyncConfig,
+    endpoint: &str,
+    payload: &Value,
+) -> Result<(), String> {
+    let mut last_error = String::new();
+
+    for attempt in 0..config.max_retries {
+        let mut request = client.post(endpoint).json(payload);
+        if let Some(token) = config.api_token.as_deref().filter(|t| !t.is_empty()) {
+            request = request.bearer_auth(token);
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                last_error = format!(
+                    "HTTP {}: {}",
+                    status,
+                    body.chars().take(MAX_ERROR_LEN).collect::<String>()
+                );
+                // The endpoint rejected the batch itself; retrying won't help
+                if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    break;
+                }
+            }
+            Err(e) => last_error = format!("Upload failed: {}", e),
+        }
+
+        if attempt + 1 < config.max_retries {
+            // 指数退避：1s, 2s, 4s ... 最多 30s
+            let delay_ms = 1000 * (1u64 << attempt.min(5)).min(30);
+            println!(
+                "[Feedback] Sync attempt {} failed ({}), retrying in {}ms",
+                attempt + 1,
+                last_error,
+                delay_ms
+            );
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Serialises sync runs and wakes the worker early (config change, sync_now).
+#[derive(Default)]
+pub struct SyncWorker {
+    running: tokio::sync::Mutex<()>,
+    wake: tokio::sync::Notify,
+}
+
+/// Upload everything queued after the high-water mark, batch by batch.
+///
+/// The mark (and the outbox behind it) only advances after the endpoint
+/// acknowledges a batch, so a failure leaves the rest queued for the next run.
+async fn run_sync(app: &AppHandle, worker: &SyncWorker) -> Result<SyncReport, String> {
+    let _running = worker.running.lock().await;
+    let db = app.state::<DbConnection>();
+
+    let (config, source) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (load_config(&conn), workstation::local(&conn)?)
+    };
+    let endpoint = match (&config.endpoint, config.enabled) {
+        (Some(endpoint), true) => endpoint.clone(),
+        _ => return Err("Sync is not enabled".to_string()),
+    };
+    validate_config(&config)?;
+
+    let mut anonymizer = if config.anonymize {
+        Some(Anonymizer::new(&load_or_create_anonymization_key(app)?))
+    } else {
+        None
+    };
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut report = SyncReport {
+        batches: 0,
+        uploaded_rows: 0,
+        high_water_mark: 0,
+        remaining: 0,
+        error: None,
+        finished_at: 0,
+    };
+
+    loop {
+        let (batch, from_seq) = {
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            let mut state = load_state(&conn);
+            state.last_attempt_at = Some(current_timestamp());
+            settings::store(&conn, SYNC_STATE_SETTING, &state)?;
+            let batch = next_batch(
+                &conn,
+                state.high_water_mark,
+                config.batch_size,
+                anonymizer.as_mut(),
+            )
+            .map_err(|e| e.to_string())?;
+            (batch, state.high_water_mark)
+        };
+        let Some(batch) = batch else {
+            break;
+        };
+
+        let rows = batch.records.len() as i64;
+        let payload = json!({
+            "batchId": Uuid::new_v4().to_string(),
+            "workstation": source,
+            "sentAt": current_timestamp(),
+            "anonymized": anonymizer.is_some(),
+            "fromSeq": from_seq,
+            "toSeq": batch.to_seq,
+            "records": batch.records,
+        });
+        // Deleted rows can leave a batch empty; just move the mark past it
+        let result = if rows == 0 {
+            Ok(())
+        } else {
+            post_batch(&client, &config, &endpoint, &payload).await
+        };
+
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut state = load_state(&conn);
+        match result {
+            Ok(()) => {
+                state.high_water_mark = batch.to_seq;
+                state.uploaded_rows += rows;
+                settings::store(&conn, SYNC_STATE_SETTING, &state)?;
+                conn.execute(
+                    "DELETE FROM sync_outbox WHERE seq <= ?1",
+                    params![batch.to_seq],
+                )
+                .map_err(|e| e.to_string())?;
+                report.batches += 1;
+                report.uploaded_rows += rows;
+            }
+            Err(e) => {
+                state.last_error = Some(e.clone());
+                state.consecutive_failures += 1;
+                settings::store(&conn, SYNC_STATE_SETTING, &state)?;
+                report.error = Some(e);
+                break;
+            }
+        }
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut state = load_state(&conn);
+    if report.error.is_none() {
+        state.last_success_at = Some(current_timestamp());
+        state.last_error = None;
+        state.consecutive_failures = 0;
+        settings::store(&conn, SYNC_STATE_SETTING, &state)?;
+    }
+    report.high_water_mark = state.high_water_mark;
+    report.remaining = pending_rows(&conn, state.high_water_mark)
+        .map_err(|e| e.to_string())?
+        .1;
+    report.finished_at = current_timestamp();
+    Ok(report)
+}
+
+pub fn start_sync_worker(app: AppHandle) {
+    app.manage(SyncWorker::default());
+
+    tauri::async_runtime::spawn(async move {
+        let worker = app.state::<SyncWorker>();
+        loop {
+            let config = match app.state::<DbConnection>().0.lock() {
+                Ok(conn) => load_config(&conn),
+                Err(e) => {
+                    eprintln!("[Feedback] Failed to lock database: {}", e);
+                    SyncConfig::default()
+                }
+            };
+
+            if config.enabled {
+                match run_sync(&app, &worker).await {
+                    Ok(report) if report.error.is_some() => eprintln!(
+                        "[Feedback] Sync stopped after {} batches, {} rows still queued: {}",
+                        report.batches,
+                        report.remaining,
+                        report.error.unwrap_or_default()
+                    ),
+                    Ok(report) if report.uploaded_rows > 0 => println!(
+                        "[Feedback] Uploaded {} rows in {} batches",
+                        report.uploaded_rows, report.batches
+                    ),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("[Feedback] Sync failed: {}", e),
+                }
+            }
+
+            let interval = Duration::from_secs(u64::from(config.interval_minutes.max(1)) * 60);
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = worker.wake.notified() => {}
+            }
+        }
+    });
+}
+
+// Central Sync Commands
+
+#[command]
+pub async fn get_sync_config(app: AppHandle) -> Result<SyncConfig, String> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_config(&conn))
+}
+
+/// Save the sync configuration and wake the worker.
+///
+/// Switching sync on queues all existing local rows, since nothing was
+/// recorded in the outbox while it was off.
+#[command]
+pub async fn set_sync_config(app: AppHandle, config: SyncConfig) -> Result<(), String> {
+    validate_config(&config)?;
+
+    {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let was_enabled = load_config(&conn).enabled;
+        settings::store(&conn, SYNC_CONFIG_SETTING, &config)?;
+
+        if config.enabled && !was_enabled {
+            let state = load_state(&conn);
+            let queued =
+                enqueue_existing(&conn, state.high_water_mark).map_err(|e| e.to_string())?;
+            println!("[Feedback] Sync enabled, queued {} existing rows", queued);
+        }
+    }
+
+    if let Some(worker) = app.try_state::<SyncWorker>() {
+        worker.wake.notify_one();
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn get_sync_status(app: AppHandle) -> Result<SyncStatus, String> {
+    let running = app
+        .try_state::<SyncWorker>()
+        .is_some_and(|w| w.running.try_lock().is_err());
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let config = load_config(&conn);
+    let state = load_state(&conn);
+    let (pending, pending_total, oldest_pending_at) =
+        pending_rows(&conn, state.high_water_mark).map_err(|e| e.to_string())?;
+
+    Ok(SyncStatus {
+        enabled: config.enabled,
+        endpoint: config.endpoint,
+        running,
+        state,
+        pending: Value::Object(pending),
+        pending_total,
+        oldest_pending_at,
+    })
+}
+
+// Upload now instead of waiting for the next interval
+#[command]
+pub async fn sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    let worker = app
+        .try_state::<SyncWorker>()
+        .ok_or_else(|| "Sync worker is not running".to_string())?;
+    run_sync(&app, &worker).await
+}
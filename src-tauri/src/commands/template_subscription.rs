@@ -0,0 +1,379 @@
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{command, AppHandle, Manager};
+
+use super::feedback::{current_timestamp, DbConnection};
+use super::sync::check_endpoint;
+use crate::db::settings;
+use crate::templates::bundle::{
+    self, RefreshReport, SubscriptionConfig, SubscriptionState, SubscriptionStatus,
+};
+use crate::templates::history::SymptomRef;
+use crate::templates::repository::write_atomic;
+use crate::templates::{TemplateError, TemplateRepository};
+
+const SUBSCRIPTION_CONFIG_SETTING: &str = "template_subscription";
+const SUBSCRIPTION_STATE_SETTING: &str = "template_subscription_state";
+
+// Last verified bundle, so the base layer survives restarts and offline days
+const BUNDLE_CACHE_FILE: &str = "template_bundle.json";
+const SIGNATURE_CACHE_FILE: &str = "template_bundle.json.sig";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// Bundles are a few MB at most; refuse anything far larger
+const MAX_BUNDLE_BYTES: usize = 32 * 1024 * 1024;
+// Cap on error bodies kept in the status
+const MAX_ERROR_LEN: usize = 300;
+
+fn validate_config(config: &SubscriptionConfig) -> Result<(), String> {
+    if config.interval_minutes == 0 {
+        return Err("intervalMinutes must be at least 1".to_string());
+    }
+    if let Some(key) = config
+        .public_key
+        .as_deref()
+        .filter(|k| !k.trim().is_empty())
+    {
+        bundle::parse_public_key(key)?;
+    }
+    for url in [&config.url, &config.signature_url].into_iter().flatten() {
+        if !url.trim().is_empty() {
+            check_endpoint(url)?;
+        }
+    }
+
+    if config.enabled {
+        if config.url.as_deref().is_none_or(|u| u.trim().is_empty()) {
+            return Err("A bundle URL is required to enable the subscription".to_string());
+        }
+        if config
+            .public_key
+            .as_deref()
+            .is_none_or(|k| k.trim().is_empty())
+        {
+            return Err("A public key is required to enable the subscription".to_string());
+        }
+    }
+    Ok(())
+}
+
+fn load_config(conn: &Connection) -> SubscriptionConfig {
+    settings::load(conn, SUBSCRIPTION_CONFIG_SETTING).unwrap_or_default()
+}
+
+fn load_state(conn: &Connection) -> SubscriptionState {
+    settings::load(conn, SUBSCRIPTION_STATE_SETTING).unwrap_or_default()
+}
+
+fn cache_paths(app: &AppHandle) -> Result<(PathBuf, PathBuf), String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok((dir.join(BUNDLE_CACHE_FILE), dir.join(SIGNATURE_CACHE_FILE)))
+}
+
+fn repository(app: &AppHandle) -> Result<tauri::State<'_, TemplateRepository>, TemplateError> {
+    app.try_state::<TemplateRepository>()
+        .ok_or_else(|| TemplateError::Storage("Template repository is not available".to_string()))
+}
+
+// Re-verify the cached bundle with the configured key and layer it in
+fn install_cached(app: &AppHandle, config: &SubscriptionConfig) -> Result<bool, TemplateError> {
+    let (bundle_path, signature_path) = cache_paths(app)?;
+    let (Ok(bytes), Ok(signature)) = (
+        std::fs::read(&bundle_path),
+        std::fs::read_to_string(&signature_path),
+    ) else {
+        return Ok(false);
+    };
+    let public_key = bundle::parse_public_key(config.public_key.as_deref().unwrap_or_default())?;
+    let verified = bundle::verify(&bytes, &signature, &public_key)?;
+    println!(
+        "[Templates] Using cached template bundle {}",
+        verified.info.version
+    );
+    repository(app)?.set_base(verified)?;
+    Ok(true)
+}
+
+fn remove_cache(app: &AppHandle) -> Result<(), String> {
+    let (bundle_path, signature_path) = cache_paths(app)?;
+    for path in [bundle_path, signature_path] {
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    config: &SubscriptionConfig,
+    url: &str,
+) -> Result<Vec<u8>, String> {
+    let mut request = client.get(url);
+    if let Some(token) = config.api_token.as_deref().filter(|t| !t.is_empty()) {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "HTTP {} from {}: {}",
+            status,
+            url,
+            body.chars().take(MAX_ERROR_LEN).collect::<String>()
+        ));
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+    if bytes.len() > MAX_BUNDLE_BYTES {
+        return Err(format!("{} is larger than {} bytes", url, MAX_BUNDLE_BYTES));
+    }
+    Ok(bytes.to_vec())
+}
+
+/// Serialises bundle checks and wakes the worker early (config change, refresh).
+#[derive(Default)]
+pub struct SubscriptionWorker {
+    running: tokio::sync::Mutex<()>,
+    wake: tokio::sync::Notify,
+}
+
+/// Download the bundle and its signature, verify both and make the bundle
+/// the base layer.
+///
+/// A bundle that fails the signature or schema check is never applied; the
+/// previous one stays active and the error is kept in the state. Neither is a
+/// bundle published before the active one, so a replayed old bundle cannot
+/// roll the templates back, unless `force` is set.
+async fn refresh(
+    app: &AppHandle,
+    worker: &SubscriptionWorker,
+    force: bool,
+) -> Result<RefreshReport, TemplateError> {
+    let _running = worker.running.lock().await;
+    let config = {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        load_config(&conn)
+    };
+    let url = match (&config.url, config.enabled) {
+        (Some(url), true) => url.clone(),
+        _ => {
+            return Err("The template subscription is not enabled"
+                .to_string()
+                .into())
+        }
+    };
+    validate_config(&config)?;
+
+    let result = async {
+        let public_key =
+            bundle::parse_public_key(config.public_key.as_deref().unwrap_or_default())?;
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let signature_url = config
+            .signature_url
+            .clone()
+            .filter(|u| !u.trim().is_empty())
+            .unwrap_or_else(|| format!("{}.sig", url));
+
+        let bytes = fetch(&client, &config, &url).await?;
+        let signature = fetch(&client, &config, &signature_url).await?;
+        let signature =
+            String::from_utf8(signature).map_err(|_| "Bundle signature is not text".to_string())?;
+
+        let verified = bundle::verify(&bytes, &signature, &public_key)?;
+        let repository = repository(app)?;
+        if let Some(active) = repository.base_info() {
+            if active.sha256 == verified.info.sha256 {
+                return Ok::<bool, TemplateError>(false);
+            }
+            if !force && verified.info.is_older_than(&active) {
+                return Err(TemplateError::Storage(format!(
+                    "Template bundle {} is older than the active bundle {}; refresh with force to apply it",
+                    verified.info.version, active.version
+                )));
+            }
+        }
+
+        let (bundle_path, signature_path) = cache_paths(app)?;
+        write_atomic(&bundle_path, &bytes)?;
+        write_atomic(&signature_path, signature.as_bytes())?;
+        println!(
+            "[Templates] Applying template bundle {} ({} symptoms)",
+            verified.info.version, verified.info.symptom_count
+        );
+        repository.set_base(verified)?;
+        Ok(true)
+    }
+    .await;
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut state = load_state(&conn);
+    let checked_at = current_timestamp();
+    state.last_checked_at = Some(checked_at);
+    match &result {
+        Ok(_) => {
+            state.last_success_at = Some(checked_at);
+            state.last_error = None;
+            state.consecutive_failures = 0;
+        }
+        Err(e) => {
+            state.last_error = Some(e.to_string());
+            state.consecutive_failures += 1;
+        }
+    }
+    settings::store(&conn, SUBSCRIPTION_STATE_SETTING, &state)?;
+
+    Ok(RefreshReport {
+        changed: result?,
+        active: repository(app)?.base_info(),
+        checked_at,
+    })
+}
+
+pub fn start_template_subscription(app: AppHandle) {
+    app.manage(SubscriptionWorker::default());
+
+    // Layer the cached bundle in before the UI first loads templates
+    let config = match app.state::<DbConnection>().0.lock() {
+        Ok(conn) => load_config(&conn),
+        Err(e) => {
+            eprintln!("[Templates] Failed to lock database: {}", e);
+            SubscriptionConfig::default()
+        }
+    };
+    if config.enabled {
+        if let Err(e) = install_cached(&app, &config) {
+            eprintln!("[Templates] Ignoring cached template bundle: {}", e);
+        }
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let worker = app.state::<SubscriptionWorker>();
+        loop {
+            let config = match app.state::<DbConnection>().0.lock() {
+                Ok(conn) => load_config(&conn),
+                Err(e) => {
+                    eprintln!("[Templates] Failed to lock database: {}", e);
+                    SubscriptionConfig::default()
+                }
+            };
+
+            if config.enabled {
+                match refresh(&app, &worker, false).await {
+                    Ok(report) if report.changed => println!(
+                        "[Templates] Template bundle {} is now active",
+                        report.active.map(|a| a.version).unwrap_or_default()
+                    ),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("[Templates] Template bundle check failed: {}", e),
+                }
+            }
+
+            let interval = Duration::from_secs(u64::from(config.interval_minutes.max(1)) * 60);
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = worker.wake.notified() => {}
+            }
+        }
+    });
+}
+
+// Template Subscription Commands
+
+#[command]
+pub async fn get_template_subscription(
+    app: AppHandle,
+) -> Result<SubscriptionConfig, TemplateError> {
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_config(&conn))
+}
+
+/// Save the subscription and wake the worker to fetch the bundle.
+///
+/// Switching it off removes the base layer; the bundle's templates are kept
+/// as ordinary local templates.
+#[command]
+pub async fn set_template_subscription(
+    app: AppHandle,
+    config: SubscriptionConfig,
+) -> Result<(), TemplateError> {
+    validate_config(&config)?;
+
+    {
+        let db = app.state::<DbConnection>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        settings::store(&conn, SUBSCRIPTION_CONFIG_SETTING, &config)?;
+    }
+
+    if !config.enabled {
+        repository(&app)?.clear_base()?;
+        remove_cache(&app)?;
+        println!("[Templates] Template subscription disabled");
+    }
+    if let Some(worker) = app.try_state::<SubscriptionWorker>() {
+        worker.wake.notify_one();
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn get_template_subscription_status(
+    app: AppHandle,
+) -> Result<SubscriptionStatus, TemplateError> {
+    let running = app
+        .try_state::<SubscriptionWorker>()
+        .is_some_and(|w| w.running.try_lock().is_err());
+    let repository = repository(&app)?;
+    let active = repository.base_info();
+    // Without a bundle every local template is just a template, not an override
+    let overrides = match active {
+        Some(_) => repository
+            .load_local()?
+            .iter()
+            .map(|t| SymptomRef {
+                key: t.key.clone(),
+                name: t.name.clone(),
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let db = app.state::<DbConnection>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let config = load_config(&conn);
+    Ok(SubscriptionStatus {
+        enabled: config.enabled,
+        url: config.url,
+        running,
+        active,
+        state: load_state(&conn),
+        overrides,
+    })
+}
+
+// Check the server now instead of waiting for the next interval; `force`
+// applies the served bundle even when it is older than the active one
+#[command]
+pub async fn refresh_template_bundle(
+    app: AppHandle,
+    force: Option<bool>,
+) -> Result<RefreshReport, TemplateError> {
+    let worker = app
+        .try_state::<SubscriptionWorker>()
+        .ok_or_else(|| TemplateError::Storage("Subscription worker is not running".to_string()))?;
+    refresh(&app, &worker, force.unwrap_or(false)).await
+}
@@ -0,0 +1,323 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{command, AppHandle, Manager};
+
+use super::feedback::current_timestamp;
+
+use crate::templates::applicability::{self, PatientProfile};
+use crate::templates::history::{self, RevisionInfo, TemplateDiff};
+use crate::templates::merge::{self, ConflictResolution, ImportPreview, ImportResult};
+use crate::templates::questionnaire;
+use crate::templates::search::{SymptomMatch, SymptomSearchRequest};
+use crate::templates::sheet::{self, SheetFormat};
+use crate::templates::textgen::{self, GeneratedText, TextGenRequest};
+use crate::templates::{
+    schema, SymptomTemplate, TemplateError, TemplateRepository, ValidationIssue,
+};
+
+fn repository(app: &AppHandle) -> Result<tauri::State<'_, TemplateRepository>, TemplateError> {
+    app.try_state::<TemplateRepository>()
+        .ok_or_else(|| TemplateError::Storage("Template repository is not available".to_string()))
+}
+
+// Revisions record who saved; fall back to the OS account when the UI sends no name
+fn author_or_default(author: Option<String>) -> String {
+    author
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// Template Commands
+
+#[command]
+pub async fn load_templates(app: AppHandle) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    repository(&app)?.load()
+}
+
+// `content` is the JSON text of the full template list, as edited in SymptomManagement.
+// Returns the revision created, or null when the content did not change.
+#[command]
+pub async fn save_templates(
+    app: AppHandle,
+    content: String,
+    author: Option<String>,
+    note: Option<String>,
+) -> Result<Option<RevisionInfo>, TemplateError> {
+    let author = author_or_default(author);
+    let note = note.unwrap_or_default();
+    match repository(&app)?.save_str(&content, &author, note.trim()) {
+        Ok(revision) => {
+            println!("[Templates] Templates saved");
+            Ok(revision)
+        }
+        Err(e) => {
+            println!("[Templates] Rejected save: {}", e);
+            Err(e)
+        }
+    }
+}
+
+// Check templates without saving them; an empty list means they are valid
+#[command]
+pub async fn validate_templates(content: String) -> Result<Vec<ValidationIssue>, TemplateError> {
+    let templates = schema::parse(&content)?;
+    Ok(schema::validate(&templates))
+}
+
+#[command]
+pub async fn reset_templates(
+    app: AppHandle,
+    author: Option<String>,
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    repository(&app)?.reset(&author_or_default(author))
+}
+
+// Newest first
+#[command]
+pub async fn list_template_revisions(app: AppHandle) -> Result<Vec<RevisionInfo>, TemplateError> {
+    Ok(repository(&app)?.history().list()?)
+}
+
+// Compare two revisions; without `to`, compare `from` with the current templates
+#[command]
+pub async fn diff_template_revisions(
+    app: AppHandle,
+    from: String,
+    to: Option<String>,
+) -> Result<TemplateDiff, TemplateError> {
+    let repository = repository(&app)?;
+    let before = repository.history().load(&from)?;
+    let (to, after) = match to {
+        Some(id) => {
+            let after = repository.history().load(&id)?;
+            (id, after)
+        }
+        // Revisions hold the stored file, so compare against that rather than
+        // the layered view
+        None => ("current".to_string(), repository.load_local()?),
+    };
+    Ok(history::diff(&from, &before, &to, &after))
+}
+
+#[command]
+pub async fn restore_template_revision(
+    app: AppHandle,
+    revision_id: String,
+    author: Option<String>,
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    repository(&app)?.restore(&revision_id, &author_or_default(author))
+}
+
+// An exported template file must be valid on its own before it is merged.
+// Spreadsheets written by `export_templates_sheet` are read back by extension.
+// Returns the templates and the sha256 of the bytes they were parsed from.
+fn read_import(path: &Path) -> Result<(Vec<SymptomTemplate>, String), TemplateError> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let sha256 = hex::encode(Sha256::digest(&bytes));
+    let templates = match SheetFormat::from_path(path) {
+        Some(format) => sheet::read_bytes(path, &bytes, format, current_timestamp())?,
+        None => {
+            let content = std::str::from_utf8(&bytes)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            schema::parse(content)?
+        }
+    };
+    let issues = schema::validate(&templates);
+    if !issues.is_empty() {
+        return Err(TemplateError::Invalid(issues));
+    }
+    Ok((templates, sha256))
+}
+
+/// First step of an import: pick a file exported by `export_templates_with_dialog`
+/// or `export_templates_sheet` and report what merging it would add, update and conflict with.
+///
+/// Returns `None` when the dialog is cancelled. Nothing is written.
+#[command]
+pub async fn preview_template_import(
+    app: AppHandle,
+    input_path: Option<String>,
+) -> Result<Option<ImportPreview>, TemplateError> {
+    let path = match input_path {
+        Some(p) => PathBuf::from(p),
+        None => {
+            let handle = rfd::AsyncFileDialog::new()
+                .set_title("导入症状模板")
+                .add_filter("症状模板", &["json", "xlsx", "csv"])
+                .pick_file()
+                .await;
+            match handle {
+                Some(h) => h.path().to_path_buf(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let (incoming, sha256) = read_import(&path)?;
+    let local = repository(&app)?.load()?;
+    Ok(Some(merge::preview(
+        &path.to_string_lossy(),
+        &sha256,
+        &local,
+        &incoming,
+    )))
+}
+
+/// Second step: merge the previewed file, resolving each conflict by symptom
+/// key. Conflicts without a resolution keep the local copy.
+///
+/// `sha256` is the hash from the preview; a file changed since then is
+/// rejected, as the resolutions were chosen for the previewed contents.
+#[command]
+pub async fn apply_template_import(
+    app: AppHandle,
+    input_path: String,
+    sha256: String,
+    resolutions: HashMap<String, ConflictResolution>,
+    author: Option<String>,
+) -> Result<ImportResult, TemplateError> {
+    let path = PathBuf::from(&input_path);
+    let (incoming, actual) = read_import(&path)?;
+    if !actual.eq_ignore_ascii_case(&sha256) {
+        return Err(TemplateError::Storage(format!(
+            "{} changed after it was previewed; preview the import again",
+            path.display()
+        )));
+    }
+    let repository = repository(&app)?;
+    let local = repository.load()?;
+
+    let (merged, mut result) = merge::merge(&local, &incoming, &resolutions);
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or(input_path);
+    let note = format!(
+        "Imported {}: {} added, {} updated, {} conflicts taken",
+        file_name, result.added, result.updated, result.took_incoming
+    );
+    let revision = repository.save(&merged, &author_or_default(author), &note)?;
+    result.revision_id = revision.map(|r| r.id);
+
+    println!(
+        "[Templates] Imported {}: {} added, {} updated, {} kept local, {} took incoming",
+        file_name, result.added, result.updated, result.kept_local, result.took_incoming
+    );
+    Ok(result)
+}
+
+// Templates pruned to the symptoms, sections and fields that apply to the
+// patient; `patient` takes the HIS PatientInfo as sent to /api/consultation/start
+#[command]
+pub async fn load_applicable_templates(
+    app: AppHandle,
+    patient: PatientProfile,
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let templates = repository(&app)?.load()?;
+    Ok(applicability::applicable_templates(templates, &patient))
+}
+
+// Chief complaint and present illness for the answers of a consultation,
+// generated from the current templates
+#[command]
+pub async fn generate_record_text(
+    app: AppHandle,
+    request: TextGenRequest,
+) -> Result<GeneratedText, TemplateError> {
+    let templates = repository(&app)?.load()?;
+    Ok(textgen::generate(&templates, &request))
+}
+
+// Symptoms matching typed text, pinyin, initials or a chief complaint, best first
+#[command]
+pub async fn search_templates(
+    app: AppHandle,
+    request: SymptomSearchRequest,
+) -> Result<Vec<SymptomMatch>, TemplateError> {
+    Ok(repository(&app)?.search_index()?.search(&request))
+}
+
+/// Export `content` (the template list as edited) as a flattened spreadsheet
+/// for review outside the app. The format follows the extension picked in
+/// the dialog. Returns the path written, or `None` when cancelled.
+#[command]
+pub async fn export_templates_sheet(content: String) -> Result<Option<String>, TemplateError> {
+    let templates = schema::parse(&content)?;
+    let handle = rfd::AsyncFileDialog::new()
+        .set_title("导出症状模板表格")
+        .set_file_name("templates_exported.xlsx")
+        .add_filter("Excel", &["xlsx"])
+        .add_filter("CSV", &["csv"])
+        .save_file()
+        .await;
+    let Some(handle) = handle else {
+        return Ok(None);
+    };
+
+    let mut path = handle.path().to_path_buf();
+    let format = match SheetFormat::from_path(&path) {
+        Some(format) => format,
+        None => {
+            path.set_extension("xlsx");
+            SheetFormat::Xlsx
+        }
+    };
+    sheet::write(&templates, &path, format)?;
+    println!(
+        "[Templates] Exported {} templates to {}",
+        templates.len(),
+        path.display()
+    );
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+const QUESTIONNAIRE_TITLE: &str = "门诊问诊单";
+
+// The symptoms to print, in template order; all of them without `keys`
+fn questionnaire_templates(
+    content: &str,
+    keys: Option<Vec<String>>,
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let mut templates = schema::parse(content)?;
+    if let Some(keys) = keys {
+        templates.retain(|t| keys.contains(&t.key));
+    }
+    Ok(templates)
+}
+
+// Printable HTML questionnaire; the UI prints it, or saves it as PDF from the print dialog
+#[command]
+pub async fn render_template_questionnaire(
+    content: String,
+    keys: Option<Vec<String>>,
+) -> Result<String, TemplateError> {
+    let templates = questionnaire_templates(&content, keys)?;
+    Ok(questionnaire::render_html(&templates, QUESTIONNAIRE_TITLE))
+}
+
+#[command]
+pub async fn export_template_questionnaire(
+    content: String,
+    keys: Option<Vec<String>>,
+) -> Result<Option<String>, TemplateError> {
+    let templates = questionnaire_templates(&content, keys)?;
+    let handle = rfd::AsyncFileDialog::new()
+        .set_title("导出问诊单")
+        .set_file_name("questionnaire.html")
+        .add_filter("HTML", &["html"])
+        .save_file()
+        .await;
+    let Some(handle) = handle else {
+        return Ok(None);
+    };
+
+    let path = handle.path();
+    let html = questionnaire::render_html(&templates, QUESTIONNAIRE_TITLE);
+    std::fs::write(path, html).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
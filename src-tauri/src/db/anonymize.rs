@@ -0,0 +1,349 @@
+use hmac::{Hmac, Mac};
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const ID_CARD_MASK: &str = "[身份证号]";
+const PHONE_MASK: &str = "[手机号]";
+const NAME_MASK: &str = "[姓名]";
+
+// PatientInfo fields stored in sessions.metadata that identify the patient
+const METADATA_IDENTIFIERS: [&str; 3] = ["naPi", "mobilePhone", "idCard"];
+
+// Free-text columns that may carry patient identifiers, per table
+fn text_columns(table: &str) -> &'static [&'static str] {
+    match table {
+        "sessions" => &["metadata"],
+        "messages" => &["content"],
+        "feedbacks" => &["reason", "original_value", "modified_value"],
+        "recommendations" => &["content"],
+        "operation_logs" => &["details"],
+        "performance_metrics" => &["context"],
+        _ => &[],
+    }
+}
+
+/// Removes PHI from exported rows.
+///
+/// Patient ids (`id_pi`) are replaced by a keyed hash so the same patient maps
+/// to the same pseudonym across exports made with the same key. Names (`na_pi`)
+/// are dropped, and ID-card numbers (`id_card`), phone numbers (`mobile_phone`)
+/// and the session's patient name are scrubbed from free text.
+pub struct Anonymizer {
+    key: Vec<u8>,
+    // session_id -> patient name, filled while the sessions table is streamed
+    session_names: HashMap<String, String>,
+    digit_run: Regex,
+    spaced_mobile: Regex,
+    name_label: Regex,
+}
+
+impl Anonymizer {
+    pub fn new(key: &[u8]) -> Self {
+        Anonymizer {
+            key: key.to_vec(),
+            session_names: HashMap::new(),
+            digit_run: Regex::new(r"\+?[0-9]{6,}[Xx]?").unwrap(),
+            spaced_mobile: Regex::new(r"(?:\+?86[ -]?)?1[3-9][0-9][ -][0-9]{4}[ -][0-9]{4}")
+                .unwrap(),
+            // Plain names, or transliterated ones joined by "·" of any length
+            name_label: Regex::new(
+                r"(患者姓名|病人姓名|姓名)(\s*[:：]?\s*)(?:\p{Han}{1,8}(?:·\p{Han}{1,8})+|\p{Han}{2,5})",
+            )
+            .unwrap(),
+        }
+    }
+
+    pub fn pseudonymize(&self, patient_id: &str) -> String {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(patient_id.as_bytes());
+        let digest = hex::encode(mac.finalize().into_bytes());
+        format!("P-{}", &digest[..16])
+    }
+
+    fn mask_digits(run: &str) -> Option<&'static str> {
+        let digits = run.trim_start_matches('+');
+        let digits = match digits.strip_prefix("86") {
+            Some(rest) if rest.len() == 11 => rest,
+            _ => digits,
+        };
+        let is_id_card = match digits.len() {
+            18 => digits[..17].bytes().all(|b| b.is_ascii_digit()),
+            15 => digits.bytes().all(|b| b.is_ascii_digit()),
+            _ => false,
+        };
+        if is_id_card {
+            return Some(ID_CARD_MASK);
+        }
+        let is_mobile = digits.len() == 11
+            && digits.starts_with('1')
+            && matches!(digits.as_bytes()[1], b'3'..=b'9')
+            && digits.bytes().all(|b| b.is_ascii_digit());
+        is_mobile.then_some(PHONE_MASK)
+    }
+
+    pub fn scrub_text(&self, text: &str, patient_name: Option<&str>) -> String {
+        let text = self.digit_run.replace_all(text, |caps: &Captures| {
+            let run = &caps[0];
+            Self::mask_digits(run).unwrap_or(run).to_string()
+        });
+        let text = self.spaced_mobile.replace_all(&text, PHONE_MASK);
+        let text = self
+            .name_label
+            .replace_all(&text, format!("${{1}}${{2}}{}", NAME_MASK).as_str());
+
+        match patient_name.map(str::trim) {
+            // Single characters are too ambiguous to scrub from Chinese text
+            Some(name) if name.chars().count() >= 2 => text.replace(name, NAME_MASK),
+            _ => text.into_owned(),
+        }
+    }
+
+    // Consultation sessions carry the HIS PatientInfo under metadata.patient
+    fn anonymize_metadata(&self, row: &mut Map<String, Value>) {
+        let Some(Value::String(raw)) = row.get("metadata") else {
+            return;
+        };
+        let Ok(mut metadata) = serde_json::from_str::<Value>(raw) else {
+            return;
+        };
+        let Some(patient) = metadata.get_mut("patient").and_then(Value::as_object_mut) else {
+            return;
+        };
+
+        for key in METADATA_IDENTIFIERS {
+            patient.remove(key);
+        }
+        if let Some(id) = patient.get("idPi").and_then(Value::as_str) {
+            let pseudonym = self.pseudonymize(id);
+            patient.insert("idPi".to_string(), Value::String(pseudonym));
+        }
+        row.insert("metadata".to_string(), Value::String(metadata.to_string()));
+    }
+
+    // Incremental uploads can carry child rows without their session, so the
+    // caller registers the session's patient name before applying
+    pub fn knows_session(&self, session_id: &str) -> bool {
+        self.session_names.contains_key(session_id)
+    }
+
+    pub fn remember_session(&mut self, session_id: &str, patient_name: &str) {
+        self.session_names
+            .insert(session_id.to_string(), patient_name.to_string());
+    }
+
+    pub fn apply(&mut self, table: &str, row: &mut Map<String, Value>) {
+        let session_id = row
+            .get("session_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        if table == "sessions" {
+            if let (Some(id), Some(name)) = (
+                session_id.as_ref(),
+                row.get("patient_name").and_then(Value::as_str),
+            ) {
+                self.session_names.insert(id.clone(), name.to_string());
+            }
+            if let Some(patient_id) = row.get("patient_id").and_then(Value::as_str) {
+                let pseudonym = self.pseudonymize(patient_id);
+                row.insert("patient_id".to_string(), Value::String(pseudonym));
+            }
+            row.insert("patient_name".to_string(), Value::Null);
+            self.anonymize_metadata(row);
+        }
+
+        if table == "messages" {
+            // Attached images are screenshots of the HIS and cannot be scrubbed
+            row.insert("images".to_string(), Value::Null);
+        }
+
+        let patient_name = session_id
+            .as_ref()
+            .and_then(|id| self.session_names.get(id))
+            .map(String::as_str);
+        for column in text_columns(table) {
+            if let Some(Value::String(text)) = row.get(*column) {
+                let scrubbed = self.scrub_text(text, patient_name);
+                row.insert(column.to_string(), Value::String(scrubbed));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn row(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn masks_id_card_numbers() {
+        let anonymizer = Anonymizer::new(b"key");
+        assert_eq!(
+            anonymizer.scrub_text("身份证110101199003071234，", None),
+            "身份证[身份证号]，"
+        );
+        assert_eq!(
+            anonymizer.scrub_text("身份证 11010119900307123X", None),
+            "身份证 [身份证号]"
+        );
+        assert_eq!(
+            anonymizer.scrub_text("身份证 11010119900307123x", None),
+            "身份证 [身份证号]"
+        );
+        assert_eq!(
+            anonymizer.scrub_text("旧证号110101900307123", None),
+            "旧证号[身份证号]"
+        );
+    }
+
+    #[test]
+    fn masks_mobile_numbers() {
+        let anonymizer = Anonymizer::new(b"key");
+        for text in [
+            "电话13812345678",
+            "电话+8613812345678",
+            "电话8613812345678",
+            "电话138 1234 5678",
+            "电话138-1234-5678",
+            "电话+86 138 1234 5678",
+        ] {
+            assert_eq!(
+                anonymizer.scrub_text(text, None),
+                "电话[手机号]",
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_other_numbers() {
+        let anonymizer = Anonymizer::new(b"key");
+        for text in [
+            "体温38.5℃",
+            "白细胞12000",
+            "编号12345678901",
+            "门诊号20260301",
+        ] {
+            assert_eq!(anonymizer.scrub_text(text, None), text);
+        }
+    }
+
+    #[test]
+    fn masks_labelled_names() {
+        let anonymizer = Anonymizer::new(b"key");
+        assert_eq!(
+            anonymizer.scrub_text("姓名：张三，男，45岁", None),
+            "姓名：[姓名]，男，45岁"
+        );
+        assert_eq!(
+            anonymizer.scrub_text("患者姓名 李四 主诉发热", None),
+            "患者姓名 [姓名] 主诉发热"
+        );
+        assert_eq!(
+            anonymizer.scrub_text("病人姓名:阿依古丽·买买提", None),
+            "病人姓名:[姓名]"
+        );
+    }
+
+    #[test]
+    fn masks_the_session_patient_name() {
+        let anonymizer = Anonymizer::new(b"key");
+        assert_eq!(
+            anonymizer.scrub_text("王小明诉咳嗽3天，王小明既往体健", Some("王小明")),
+            "[姓名]诉咳嗽3天，[姓名]既往体健"
+        );
+        // Single characters would hit unrelated text
+        assert_eq!(
+            anonymizer.scrub_text("王先生咳嗽", Some("王")),
+            "王先生咳嗽"
+        );
+    }
+
+    #[test]
+    fn scrubs_child_rows_with_the_session_name() {
+        let mut anonymizer = Anonymizer::new(b"key");
+        let mut session = row(json!({
+            "session_id": "s1",
+            "patient_id": "HIS001",
+            "patient_name": "赵六",
+            "metadata": null,
+        }));
+        anonymizer.apply("sessions", &mut session);
+        assert_eq!(session["patient_name"], Value::Null);
+        assert_eq!(
+            session["patient_id"],
+            json!(anonymizer.pseudonymize("HIS001"))
+        );
+
+        let mut message = row(json!({
+            "session_id": "s1",
+            "content": "赵六，电话13812345678",
+            "images": "[\"data:image/png;base64,AAAA\"]",
+        }));
+        anonymizer.apply("messages", &mut message);
+        assert_eq!(message["content"], json!("[姓名]，电话[手机号]"));
+        assert_eq!(message["images"], Value::Null);
+
+        // Rows of a session the anonymizer has not seen still lose numbers
+        let mut other = row(json!({ "session_id": "s2", "content": "赵六 13812345678" }));
+        anonymizer.apply("messages", &mut other);
+        assert_eq!(other["content"], json!("赵六 [手机号]"));
+    }
+
+    #[test]
+    fn strips_identifiers_from_session_metadata() {
+        let mut anonymizer = Anonymizer::new(b"key");
+        let metadata = json!({
+            "source": "http",
+            "patient": {
+                "idPi": "HIS001",
+                "naPi": "赵六",
+                "mobilePhone": "13812345678",
+                "idCard": "110101199003071234",
+                "ageText": "45岁",
+            },
+        });
+        let mut session = row(json!({
+            "session_id": "s1",
+            "patient_id": "HIS001",
+            "patient_name": "赵六",
+            "metadata": metadata.to_string(),
+        }));
+        anonymizer.apply("sessions", &mut session);
+
+        let metadata: Value = serde_json::from_str(session["metadata"].as_str().unwrap()).unwrap();
+        let patient = metadata["patient"].as_object().unwrap();
+        for key in METADATA_IDENTIFIERS {
+            assert!(!patient.contains_key(key), "{}", key);
+        }
+        assert_eq!(patient["idPi"], json!(anonymizer.pseudonymize("HIS001")));
+        assert_eq!(patient["ageText"], json!("45岁"));
+        assert!(!session["metadata"].as_str().unwrap().contains("赵六"));
+    }
+
+    #[test]
+    fn pseudonyms_depend_only_on_key_and_id() {
+        let first = Anonymizer::new(b"department key");
+        let second = Anonymizer::new(b"department key");
+        let other = Anonymizer::new(b"another key");
+
+        let pseudonym = first.pseudonymize("HIS001");
+        assert!(pseudonym.starts_with("P-"));
+        assert_eq!(pseudonym.len(), 18);
+        assert_eq!(pseudonym, first.pseudonymize("HIS001"));
+        assert_eq!(pseudonym, second.pseudonymize("HIS001"));
+        assert_ne!(pseudonym, first.pseudonymize("HIS002"));
+        assert_ne!(pseudonym, other.pseudonymize("HIS001"));
+    }
+}
@@ -0,0 +1,338 @@
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+
+// Overrides the key file, e.g. for managed deployments that inject secrets
+pub const KEY_ENV_VAR: &str = "FEEDBACK_DB_KEY";
+pub const KEY_FILE_NAME: &str = "feedback.key";
+
+/// Key material for the SQLCipher database.
+///
+/// Generated keys are 32 random bytes used as a raw key; a key from
+/// `FEEDBACK_DB_KEY` is treated as a passphrase.
+#[derive(Clone)]
+pub enum DbKey {
+    Raw(String),
+    Passphrase(String),
+}
+
+impl DbKey {
+    pub fn generate() -> Self {
+        let bytes: [u8; 32] = rand::random();
+        DbKey::Raw(hex::encode(bytes))
+    }
+
+    // Value for PRAGMA key / PRAGMA rekey
+    #[cfg_attr(not(feature = "sqlcipher"), allow(dead_code))]
+    fn pragma_value(&self) -> String {
+        match self {
+            DbKey::Raw(hex) => format!("\"x'{}'\"", hex),
+            DbKey::Passphrase(p) => format!("'{}'", p.replace('\'', "''")),
+        }
+    }
+}
+
+// Managed in app state so commands know how the open database was keyed
+pub struct EncryptionState {
+    pub key_source: Option<KeySource>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeySource {
+    Environment,
+    KeyFile,
+}
+
+impl KeySource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KeySource::Environment => "environment",
+            KeySource::KeyFile => "keyFile",
+        }
+    }
+}
+
+// The key lives in the config dir, not next to feedback.db in the data dir
+pub fn key_file_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(KEY_FILE_NAME)
+}
+
+// A rotation writes the new key here before re-keying the database
+pub fn pending_key_file_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(format!("{}.new", KEY_FILE_NAME))
+}
+
+pub fn load_or_create_key(config_dir: &Path) -> Result<(DbKey, KeySource), String> {
+    if let Ok(passphrase) = std::env::var(KEY_ENV_VAR) {
+        if !passphrase.is_empty() {
+            return Ok((DbKey::Passphrase(passphrase), KeySource::Environment));
+        }
+    }
+
+    let path = key_file_path(config_dir);
+    if let Some(key) = read_key_file(&path)? {
+        return Ok((key, KeySource::KeyFile));
+    }
+
+    std::fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+    let key = DbKey::generate();
+    write_key_file(&path, &key)?;
+    println!("[Feedback] Created database key at {:?}", path);
+    Ok((key, KeySource::KeyFile))
+}
+
+fn read_key_file(path: &Path) -> Result<Option<DbKey>, String> {
+    let Ok(existing) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let hex = existing.trim();
+    if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(Some(DbKey::Raw(hex.to_string())));
+    }
+    Err(format!("Key file {:?} is corrupt", path))
+}
+
+pub fn write_key_file(path: &Path, key: &DbKey) -> Result<(), String> {
+    let DbKey::Raw(hex) = key else {
+        return Err("Only generated keys can be stored in the key file".to_string());
+    };
+    std::fs::write(path, hex).map_err(|e| e.to_string())?;
+    restrict_permissions(path)
+}
+
+// Make a key file readable by the current user only
+pub fn restrict_permissions(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|e| e.to_string())?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+// Plaintext SQLite files start with this header; SQLCipher files look random
+fn is_plaintext_database(path: &Path) -> bool {
+    use std::io::Read;
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .map(|_| &header == b"SQLite format 3\0")
+        .unwrap_or(false)
+}
+
+#[cfg(feature = "sqlcipher")]
+fn apply_key(conn: &Connection, key: &DbKey) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!("PRAGMA key = {};", key.pragma_value()))?;
+    // Fails with "file is not a database" when the key is wrong
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))
+}
+
+// Copy a plaintext database into a new encrypted file and swap it into place
+#[cfg(feature = "sqlcipher")]
+fn encrypt_plaintext_database(path: &Path, key: &DbKey) -> Result<(), String> {
+    let mut encrypted_path = path.as_os_str().to_owned();
+    encrypted_path.push(".encrypting");
+    let encrypted_path = PathBuf::from(encrypted_path);
+    let _ = std::fs::remove_file(&encrypted_path);
+
+    {
+        let plain = Connection::open(path).map_err(|e| e.to_string())?;
+        plain
+            .execute_batch(&format!(
+                "ATTACH DATABASE '{}' AS encrypted KEY {};
+             SELECT sqlcipher_export('encrypted');
+             DETACH DATABASE encrypted;",
+                encrypted_path.to_string_lossy().replace('\'', "''"),
+                key.pragma_value()
+            ))
+            .map_err(|e| format!("Failed to encrypt database: {}", e))?;
+    }
+
+    // Make sure the copy opens with the key before the original goes away
+    {
+        let check = Connection::open(&encrypted_path).map_err(|e| e.to_string())?;
+        apply_key(&check, key).map_err(|e| format!("Encrypted copy is unreadable: {}", e))?;
+    }
+
+    std::fs::rename(&encrypted_path, path).map_err(|e| e.to_string())?;
+    for suffix in ["-journal", "-wal", "-shm"] {
+        let mut stale = path.as_os_str().to_owned();
+        stale.push(suffix);
+        let _ = std::fs::remove_file(PathBuf::from(stale));
+    }
+    Ok(())
+}
+
+/// Open the feedback database. With a key (SQLCipher builds) a plaintext
+/// database is encrypted in place first.
+pub fn open(path: &Path, key: Option<&DbKey>) -> Result<Connection, String> {
+    match key {
+        #[cfg(feature = "sqlcipher")]
+        Some(key) => {
+            if is_plaintext_database(path) {
+                println!("[Feedback] Encrypting existing plaintext database...");
+                encrypt_plaintext_database(path, key)?;
+                println!("[Feedback] Database encrypted");
+            }
+            let conn = Connection::open(path).map_err(|e| e.to_string())?;
+            apply_key(&conn, key).map_err(|e| format!("Failed to unlock database: {}", e))?;
+            Ok(conn)
+        }
+        _ => {
+            if path.exists() && !is_plaintext_database(path) {
+                eprintln!(
+                    "[Feedback] Database looks encrypted but no key is in use; \
+                     was it created by a SQLCipher build?"
+                );
+            }
+            Connection::open(path).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Open the feedback database with the key from the key file, finishing a
+/// rotation that was interrupted after `PRAGMA rekey` but before the new key
+/// replaced the key file.
+#[cfg(feature = "sqlcipher")]
+pub fn open_with_key_file(
+    path: &Path,
+    config_dir: &Path,
+    key: &DbKey,
+) -> Result<Connection, String> {
+    let pending_path = pending_key_file_path(config_dir);
+    match open(path, Some(key)) {
+        Ok(conn) => {
+            // Left over from a rotation that failed before the re-key
+            if pending_path.exists() {
+                eprintln!(
+                    "[Feedback] Discarding unused pending key {:?}",
+                    pending_path
+                );
+                let _ = std::fs::remove_file(&pending_path);
+            }
+            Ok(conn)
+        }
+        Err(e) => {
+            let Some(pending) = read_key_file(&pending_path).ok().flatten() else {
+                return Err(e);
+            };
+            let conn = open(path, Some(&pending)).map_err(|_| e)?;
+            std::fs::rename(&pending_path, key_file_path(config_dir)).map_err(|e| {
+                format!(
+                    "Database opened with the pending key but it could not replace the key file: {}",
+                    e
+                )
+            })?;
+            println!("[Feedback] Finished an interrupted key rotation");
+            Ok(conn)
+        }
+    }
+}
+
+/// Open a read-only connection to the feedback database, for long reads
+/// that should not hold the shared connection.
+pub fn open_read_only(path: &Path, key: Option<&DbKey>) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    match key {
+        #[cfg(feature = "sqlcipher")]
+        Some(key) => {
+            apply_key(&conn, key).map_err(|e| format!("Failed to unlock database: {}", e))?
+        }
+        _ => {}
+    }
+    Ok(conn)
+}
+
+#[cfg(feature = "sqlcipher")]
+pub fn rekey(conn: &Connection, new_key: &DbKey) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!("PRAGMA rekey = {};", new_key.pragma_value()))
+}
+
+pub fn is_supported() -> bool {
+    cfg!(feature = "sqlcipher")
+}
+
+#[cfg(all(test, feature = "sqlcipher"))]
+mod tests {
+    use super::*;
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("feedback-key-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn create_database(path: &Path, key: &DbKey) {
+        let conn = open(path, Some(key)).unwrap();
+        conn.execute_batch("CREATE TABLE t (v TEXT); INSERT INTO t VALUES ('kept');")
+            .unwrap();
+    }
+
+    fn stored_key(config_dir: &Path) -> String {
+        std::fs::read_to_string(key_file_path(config_dir)).unwrap()
+    }
+
+    #[test]
+    fn open_promotes_pending_key_after_crash_between_rekey_and_rename() {
+        let dir = scratch_dir();
+        let db_path = dir.join("feedback.db");
+        let (old_key, _) = load_or_create_key(&dir).unwrap();
+        create_database(&db_path, &old_key);
+
+        // rekey_database up to the crash: new key written, database re-keyed,
+        // key file never replaced
+        let new_key = DbKey::generate();
+        write_key_file(&pending_key_file_path(&dir), &new_key).unwrap();
+        {
+            let conn = open(&db_path, Some(&old_key)).unwrap();
+            rekey(&conn, &new_key).unwrap();
+        }
+        assert!(open(&db_path, Some(&old_key)).is_err());
+
+        let (startup_key, _) = load_or_create_key(&dir).unwrap();
+        let conn = open_with_key_file(&db_path, &dir, &startup_key).unwrap();
+        let v: String = conn.query_row("SELECT v FROM t", [], |r| r.get(0)).unwrap();
+        assert_eq!(v, "kept");
+
+        let DbKey::Raw(new_hex) = &new_key else {
+            unreachable!()
+        };
+        assert_eq!(&stored_key(&dir), new_hex);
+        assert!(!pending_key_file_path(&dir).exists());
+
+        drop(conn);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn open_discards_pending_key_when_rekey_never_ran() {
+        let dir = scratch_dir();
+        let db_path = dir.join("feedback.db");
+        let (old_key, _) = load_or_create_key(&dir).unwrap();
+        create_database(&db_path, &old_key);
+        let old_hex = stored_key(&dir);
+
+        write_key_file(&pending_key_file_path(&dir), &DbKey::generate()).unwrap();
+
+        open_with_key_file(&db_path, &dir, &old_key).unwrap();
+        assert_eq!(stored_key(&dir), old_hex);
+        assert!(!pending_key_file_path(&dir).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn open_fails_with_wrong_key_and_no_pending_key() {
+        let dir = scratch_dir();
+        let db_path = dir.join("feedback.db");
+        create_database(&db_path, &DbKey::generate());
+
+        let (unrelated, _) = load_or_create_key(&dir).unwrap();
+        assert!(open_with_key_file(&db_path, &dir, &unrelated).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::fmt;
+
+/// Errors returned by the feedback commands.
+///
+/// Validation failures are reported before anything reaches SQLite, so the
+/// frontend gets the offending field and the accepted values instead of a raw
+/// `CHECK constraint failed` string.
+#[derive(Debug)]
+pub enum FeedbackError {
+    InvalidValue {
+        field: &'static str,
+        value: String,
+        allowed: &'static [&'static str],
+    },
+    OutOfRange {
+        field: &'static str,
+        value: String,
+        expected: &'static str,
+    },
+    SessionNotFound {
+        session_id: String,
+    },
+    Database(String),
+}
+
+impl FeedbackError {
+    fn kind(&self) -> &'static str {
+        match self {
+            FeedbackError::InvalidValue { .. } => "invalidValue",
+            FeedbackError::OutOfRange { .. } => "outOfRange",
+            FeedbackError::SessionNotFound { .. } => "sessionNotFound",
+            FeedbackError::Database(_) => "database",
+        }
+    }
+}
+
+impl fmt::Display for FeedbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedbackError::InvalidValue {
+                field,
+                value,
+                allowed,
+            } => write!(
+                f,
+                "Invalid {} '{}': expected one of {}",
+                field,
+                value,
+                allowed.join(", ")
+            ),
+            FeedbackError::OutOfRange {
+                field,
+                value,
+                expected,
+            } => write!(f, "Invalid {} {}: expected {}", field, value, expected),
+            FeedbackError::SessionNotFound { session_id } => {
+                write!(f, "Session '{}' does not exist", session_id)
+            }
+            FeedbackError::Database(message) => write!(f, "Database error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FeedbackError {}
+
+// Serialized as an object so the frontend can branch on `kind` and still show `message`
+impl Serialize for FeedbackError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = json!({
+            "kind": self.kind(),
+            "message": self.to_string(),
+        });
+        match self {
+            FeedbackError::InvalidValue {
+                field,
+                value: v,
+                allowed,
+            } => {
+                value["field"] = json!(field);
+                value["value"] = json!(v);
+                value["allowed"] = json!(allowed);
+            }
+            FeedbackError::OutOfRange {
+                field, value: v, ..
+            } => {
+                value["field"] = json!(field);
+                value["value"] = json!(v);
+            }
+            FeedbackError::SessionNotFound { session_id } => {
+                value["sessionId"] = json!(session_id);
+            }
+            FeedbackError::Database(_) => {}
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl From<rusqlite::Error> for FeedbackError {
+    fn from(e: rusqlite::Error) -> Self {
+        FeedbackError::Database(e.to_string())
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for FeedbackError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        FeedbackError::Database(e.to_string())
+    }
+}
+
+impl From<FeedbackError> for String {
+    fn from(e: FeedbackError) -> Self {
+        e.to_string()
+    }
+}
@@ -1,13 +1,84 @@
+pub mod anonymize;
+pub mod encryption;
+pub mod error;
 pub mod models;
+pub mod settings;
+pub mod workstation;
 
+use rusqlite::Connection;
 use tauri_plugin_sql::{Migration, MigrationKind};
 
+// (version, description, sql); applied in order and tracked in PRAGMA user_version.
+// Versions 1 and 2 are idempotent because databases created before version
+// tracking have them applied with user_version still at 0.
+const MIGRATIONS: [(i64, &str, &str); 7] = [
+    (
+        1,
+        "Initial feedback schema",
+        include_str!("../../migrations/001_initial_schema.sql"),
+    ),
+    (
+        2,
+        "Backend settings table",
+        include_str!("../../migrations/002_app_settings.sql"),
+    ),
+    (
+        3,
+        "Model column on recommendations",
+        include_str!("../../migrations/003_recommendation_model.sql"),
+    ),
+    (
+        4,
+        "Millisecond timestamps and message sequence",
+        include_str!("../../migrations/004_millisecond_timestamps.sql"),
+    ),
+    (
+        5,
+        "Import sources",
+        include_str!("../../migrations/005_import_sources.sql"),
+    ),
+    (
+        6,
+        "Sync outbox",
+        include_str!("../../migrations/006_sync_outbox.sql"),
+    ),
+    (
+        7,
+        "Template usage",
+        include_str!("../../migrations/007_template_usage.sql"),
+    ),
+];
+
 #[allow(dead_code)]
 pub fn get_migrations() -> Vec<Migration> {
-    vec![Migration {
-        version: 1,
-        description: "Initial feedback schema",
-        sql: include_str!("../../migrations/001_initial_schema.sql"),
-        kind: MigrationKind::Up,
-    }]
+    MIGRATIONS
+        .iter()
+        .map(|(version, description, sql)| Migration {
+            version: *version,
+            description,
+            sql,
+            kind: MigrationKind::Up,
+        })
+        .collect()
+}
+
+// Apply the feedback schema to a connection (used for the live database and for snapshots)
+pub fn run_migrations(conn: &Connection) -> rusqlite::Result<()> {
+    let current: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (version, description, sql) in MIGRATIONS {
+        if version <= current {
+            continue;
+        }
+        println!("[Feedback] Applying migration {}: {}", version, description);
+        conn.execute_batch(&format!(
+            "BEGIN;\n{}\nPRAGMA user_version = {};\nCOMMIT;",
+            sql, version
+        ))
+        .inspect_err(|_| {
+            let _ = conn.execute_batch("ROLLBACK");
+        })?;
+    }
+
+    Ok(())
 }
@@ -1,17 +1,133 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use serde::{Deserialize, Serialize};
 
+use super::error::FeedbackError;
+
+// Enumerations mirroring the CHECK constraints in the schema. `parse` validates
+// incoming strings before they reach SQLite; ToSql/FromSql store the same text.
+macro_rules! db_enum {
+    ($name:ident, $field:literal, { $($variant:ident => $value:literal),+ $(,)? }) => {
+        #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $(#[serde(rename = $value)] $variant),+
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            pub const ALL: &'static [&'static str] = &[$($value),+];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $value),+
+                }
+            }
+
+            pub fn parse(value: &str) -> Result<Self, FeedbackError> {
+                match value {
+                    $($value => Ok($name::$variant),)+
+                    _ => Err(FeedbackError::InvalidValue {
+                        field: $field,
+                        value: value.to_string(),
+                        allowed: Self::ALL,
+                    }),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::from(self.as_str()))
+            }
+        }
+
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                $name::parse(value.as_str()?).map_err(|e| FromSqlError::Other(Box::new(e)))
+            }
+        }
+    };
+}
+
+db_enum!(SessionType, "session_type", {
+    Chat => "chat",
+    Consultation => "consultation",
+    Voice => "voice",
+    Reception => "reception",
+});
+
+db_enum!(SessionStatus, "status", {
+    Active => "active",
+    Completed => "completed",
+    Cancelled => "cancelled",
+    Error => "error",
+});
+
+db_enum!(MessageRole, "role", {
+    System => "system",
+    User => "user",
+    Assistant => "assistant",
+});
+
+db_enum!(TargetType, "target_type", {
+    Message => "message",
+    Diagnosis => "diagnosis",
+    Medication => "medication",
+    Examination => "examination",
+    Record => "record",
+});
+
+db_enum!(FeedbackType, "feedback_type", {
+    Positive => "positive",
+    Negative => "negative",
+    Adopted => "adopted",
+    Rejected => "rejected",
+    Modified => "modified",
+});
+
+db_enum!(RecommendationType, "rec_type", {
+    Diagnosis => "diagnosis",
+    Medication => "medication",
+    Examination => "examination",
+});
+
+db_enum!(OperationType, "operation_type", {
+    ViewChange => "view_change",
+    ButtonClick => "button_click",
+    FormSubmit => "form_submit",
+    ApiCall => "api_call",
+    Error => "error",
+});
+
+db_enum!(MetricType, "metric_type", {
+    LlmLatency => "llm_latency",
+    ApiLatency => "api_latency",
+    UiRender => "ui_render",
+    MemoryUsage => "memory_usage",
+});
+
+db_enum!(UsageEvent, "event", {
+    Shown => "shown",
+    Answered => "answered",
+});
+
 // Session Types
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[allow(dead_code)]
+#[serde(rename_all = "camelCase")]
 pub struct Session {
     pub session_id: String,
     pub patient_id: Option<String>,
     pub patient_name: Option<String>,
-    pub session_type: String,
+    pub session_type: SessionType,
     pub start_time: i64,
     pub end_time: Option<i64>,
-    pub status: String,
-    pub metadata: Option<String>,
+    pub status: SessionStatus,
+    pub metadata: Option<serde_json::Value>,
     pub created_at: i64,
 }
 
@@ -21,13 +137,15 @@ pub struct Session {
 pub struct Message {
     pub message_id: String,
     pub session_id: String,
-    pub role: String,
+    pub role: MessageRole,
     pub content: String,
     pub images: Option<String>,
     pub token_count: Option<i32>,
     pub llm_model: Option<String>,
     pub latency_ms: Option<i32>,
     pub created_at: i64,
+    // 1-based position within the session
+    pub seq: i64,
 }
 
 // Feedback Types
@@ -36,9 +154,9 @@ pub struct Message {
 pub struct Feedback {
     pub feedback_id: String,
     pub session_id: String,
-    pub target_type: String,
+    pub target_type: TargetType,
     pub target_id: String,
-    pub feedback_type: String,
+    pub feedback_type: FeedbackType,
     pub rating: Option<i32>,
     pub reason: Option<String>,
     pub original_value: Option<String>,
@@ -52,13 +170,14 @@ pub struct Feedback {
 pub struct Recommendation {
     pub recommendation_id: String,
     pub session_id: String,
-    pub rec_type: String,
+    pub rec_type: RecommendationType,
     pub content: String,
     pub matched: bool,
     pub match_confidence: Option<f64>,
     pub prompt_tokens: Option<i32>,
     pub completion_tokens: Option<i32>,
     pub latency_ms: Option<i32>,
+    pub llm_model: Option<String>,
     pub created_at: i64,
 }
 
@@ -68,7 +187,7 @@ pub struct Recommendation {
 pub struct OperationLog {
     pub log_id: String,
     pub session_id: Option<String>,
-    pub operation_type: String,
+    pub operation_type: OperationType,
     pub operation_name: String,
     pub details: Option<String>,
     pub success: bool,
@@ -82,7 +201,7 @@ pub struct OperationLog {
 pub struct PerformanceMetric {
     pub metric_id: String,
     pub session_id: Option<String>,
-    pub metric_type: String,
+    pub metric_type: MetricType,
     pub metric_value: f64,
     pub unit: String,
     pub context: Option<String>,
@@ -131,3 +250,595 @@ pub struct PerformanceStatistics {
     pub total_token_count: i32,
     pub metrics_by_type: serde_json::Value,
 }
+
+// Export Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub format: String,
+    pub row_counts: serde_json::Value,
+    pub bytes_written: u64,
+    pub exported_at: i64,
+    pub anonymized: bool,
+}
+
+// Retention Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    // Retention in days per table; None keeps rows forever
+    pub sessions_days: Option<u32>,
+    pub messages_days: Option<u32>,
+    pub message_images_days: Option<u32>,
+    pub feedbacks_days: Option<u32>,
+    pub recommendations_days: Option<u32>,
+    pub operation_logs_days: Option<u32>,
+    pub performance_metrics_days: Option<u32>,
+    pub template_usage_days: Option<u32>,
+    pub purge_interval_hours: u32,
+    pub vacuum_after_purge: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            sessions_days: None,
+            messages_days: None,
+            message_images_days: None,
+            feedbacks_days: None,
+            recommendations_days: None,
+            operation_logs_days: None,
+            performance_metrics_days: None,
+            template_usage_days: None,
+            purge_interval_hours: 24,
+            vacuum_after_purge: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub deleted_rows: serde_json::Value,
+    pub images_cleared: i64,
+    pub vacuumed: bool,
+    pub purged_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseSizeReport {
+    pub file_size_bytes: u64,
+    pub page_size: i64,
+    pub page_count: i64,
+    pub freelist_count: i64,
+    pub row_counts: serde_json::Value,
+}
+
+// Session Lifecycle Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionLifecyclePolicy {
+    // Active sessions with no activity for this long are cancelled
+    pub idle_timeout_minutes: u32,
+    pub sweep_interval_minutes: u32,
+}
+
+impl Default for SessionLifecyclePolicy {
+    fn default() -> Self {
+        SessionLifecyclePolicy {
+            idle_timeout_minutes: 30,
+            sweep_interval_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSweepReport {
+    pub idle_cancelled: usize,
+    pub interrupted_cancelled: usize,
+    pub swept_at: i64,
+}
+
+// Encryption Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub supported: bool,
+    pub encrypted: bool,
+    pub key_source: Option<String>,
+    pub key_path: Option<String>,
+}
+
+// Recommendation Analytics Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendationTypeStatistics {
+    pub rec_type: String,
+    pub total: i64,
+    pub matched_count: i64,
+    pub with_outcome: i64,
+    pub adopted_count: i64,
+    pub rejected_count: i64,
+    pub modified_count: i64,
+    // adopted / recommendations with an adopted/rejected/modified outcome
+    pub acceptance_rate: Option<f64>,
+    pub total_prompt_tokens: i64,
+    pub total_completion_tokens: i64,
+    pub avg_prompt_tokens: Option<f64>,
+    pub avg_completion_tokens: Option<f64>,
+    pub avg_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfidenceBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: i64,
+    pub avg_confidence: Option<f64>,
+    pub match_rate: Option<f64>,
+    pub acceptance_rate: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendationContentCount {
+    pub rec_type: String,
+    pub content: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecommendationStatistics {
+    pub total_recommendations: i64,
+    pub by_type: Vec<RecommendationTypeStatistics>,
+    pub calibration: Vec<ConfidenceBucket>,
+    pub top_rejected: Vec<RecommendationContentCount>,
+    pub top_modified: Vec<RecommendationContentCount>,
+}
+
+// Modification Analytics Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModificationDiff {
+    pub feedback_id: String,
+    pub target_type: String,
+    pub original_length: usize,
+    pub modified_length: usize,
+    pub edit_distance: usize,
+    // edit_distance / max(original_length, modified_length)
+    pub normalized_distance: f64,
+    pub inserted_chars: usize,
+    pub deleted_chars: usize,
+    pub inserted_segments: Vec<String>,
+    pub deleted_segments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FragmentCount {
+    pub text: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModificationTargetSummary {
+    pub target_type: String,
+    pub count: i64,
+    pub unchanged_count: i64,
+    pub avg_edit_distance: f64,
+    pub avg_normalized_distance: f64,
+    pub total_inserted_chars: i64,
+    pub total_deleted_chars: i64,
+    pub top_insertions: Vec<FragmentCount>,
+    pub top_deletions: Vec<FragmentCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModificationAnalysis {
+    pub total_modified: i64,
+    pub by_target_type: Vec<ModificationTargetSummary>,
+    pub items: Vec<ModificationDiff>,
+}
+
+// Operation Analytics Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationStatistics {
+    pub operation_type: OperationType,
+    pub operation_name: String,
+    pub total: i64,
+    // success = 0 or operation_type = 'error'
+    pub error_count: i64,
+    pub error_rate: Option<f64>,
+    pub avg_duration_ms: Option<f64>,
+    pub max_duration_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClickPathStep {
+    pub operation_type: OperationType,
+    pub operation_name: String,
+    pub success: bool,
+    pub duration_ms: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionClickPath {
+    pub session_id: String,
+    pub steps: Vec<ClickPathStep>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FunnelDefinition {
+    pub name: String,
+    // operation_name of each step, in the order they must happen
+    pub steps: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FunnelStepResult {
+    pub operation_name: String,
+    pub sessions: i64,
+    pub conversion_from_previous: Option<f64>,
+    pub conversion_from_start: Option<f64>,
+    // Time between reaching the previous step and this one
+    pub avg_ms_from_previous: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FunnelReport {
+    pub name: String,
+    pub total_sessions: i64,
+    pub steps: Vec<FunnelStepResult>,
+}
+
+// Template Usage Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldUsageStatistics {
+    pub field_key: String,
+    // From the current templates; None for fields since removed
+    pub label: Option<String>,
+    pub shown: i64,
+    pub answered: i64,
+    pub skip_rate: Option<f64>,
+    // From the field appearing in the form to its first answer
+    pub avg_answer_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomUsageStatistics {
+    pub symptom_key: String,
+    pub name: Option<String>,
+    // Sessions the symptom was shown in, and those with at least one answer
+    pub sessions: i64,
+    pub answered_sessions: i64,
+    // Share of the sessions in scope that used the symptom
+    pub usage_rate: Option<f64>,
+    pub skip_rate: Option<f64>,
+    // From the symptom appearing in the form to the last of its fields answered
+    pub avg_answer_ms: Option<f64>,
+    pub fields: Vec<FieldUsageStatistics>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateUsageStatistics {
+    // Sessions in scope that showed any template
+    pub total_sessions: i64,
+    // Most used first
+    pub symptoms: Vec<SymptomUsageStatistics>,
+    // Keys of current template symptoms never shown in scope
+    pub unused_symptoms: Vec<String>,
+}
+
+// Batch Write Types
+fn default_true() -> bool {
+    true
+}
+
+/// One row for the batch writer. `kind` selects the table; `id` and
+/// `createdAt` may be set by the caller (e.g. when a write is buffered and the
+/// id is needed before it reaches the database).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum WriteItem {
+    Message {
+        id: Option<String>,
+        session_id: String,
+        role: String,
+        content: String,
+        images: Option<String>,
+        token_count: Option<i32>,
+        llm_model: Option<String>,
+        latency_ms: Option<i32>,
+        created_at: Option<i64>,
+    },
+    Feedback {
+        id: Option<String>,
+        session_id: String,
+        target_type: String,
+        target_id: String,
+        feedback_type: String,
+        rating: Option<i32>,
+        reason: Option<String>,
+        original_value: Option<String>,
+        modified_value: Option<String>,
+        created_at: Option<i64>,
+    },
+    Recommendation {
+        id: Option<String>,
+        session_id: String,
+        rec_type: String,
+        content: String,
+        #[serde(default)]
+        matched: bool,
+        match_confidence: Option<f64>,
+        prompt_tokens: Option<i32>,
+        completion_tokens: Option<i32>,
+        latency_ms: Option<i32>,
+        llm_model: Option<String>,
+        created_at: Option<i64>,
+    },
+    Log {
+        id: Option<String>,
+        session_id: Option<String>,
+        operation_type: String,
+        operation_name: String,
+        details: Option<String>,
+        #[serde(default = "default_true")]
+        success: bool,
+        duration_ms: Option<i32>,
+        created_at: Option<i64>,
+    },
+    Metric {
+        id: Option<String>,
+        session_id: Option<String>,
+        metric_type: String,
+        metric_value: f64,
+        unit: String,
+        context: Option<String>,
+        created_at: Option<i64>,
+    },
+    // A template symptom, or one of its fields, shown or answered in the form.
+    // Repeated events for the same item only keep the first time.
+    TemplateUsage {
+        id: Option<String>,
+        session_id: String,
+        symptom_key: String,
+        field_key: Option<String>,
+        event: String,
+        created_at: Option<i64>,
+    },
+}
+
+impl WriteItem {
+    pub fn created_at_mut(&mut self) -> &mut Option<i64> {
+        match self {
+            WriteItem::Message { created_at, .. }
+            | WriteItem::Feedback { created_at, .. }
+            | WriteItem::Recommendation { created_at, .. }
+            | WriteItem::Log { created_at, .. }
+            | WriteItem::Metric { created_at, .. }
+            | WriteItem::TemplateUsage { created_at, .. } => created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteItemResult {
+    pub index: usize,
+    pub id: Option<String>,
+    // Serialized FeedbackError when the item was rejected
+    pub error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchWriteReport {
+    pub written: usize,
+    pub failed: usize,
+    pub results: Vec<WriteItemResult>,
+    // Buffered writes discarded since the last flush because the buffer was full
+    pub dropped: usize,
+}
+
+// Import Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkstationInfo {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub import_id: String,
+    pub workstation_id: String,
+    pub workstation_name: Option<String>,
+    pub path: String,
+    pub format: String,
+    // Per table: rows added, and rows skipped because the primary key already
+    // exists or the parent session is missing
+    pub inserted_rows: serde_json::Value,
+    pub skipped_rows: serde_json::Value,
+    pub imported_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkstationSummary {
+    pub workstation_id: String,
+    pub name: Option<String>,
+    pub local: bool,
+    pub session_count: i64,
+    pub import_count: i64,
+    pub last_imported_at: Option<i64>,
+}
+
+// Central Sync Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncConfig {
+    pub enabled: bool,
+    // HTTPS collection endpoint; plain http is accepted only for localhost
+    pub endpoint: Option<String>,
+    // Sent as a bearer token when set
+    pub api_token: Option<String>,
+    pub interval_minutes: u32,
+    pub batch_size: u32,
+    // Attempts per batch before the cycle gives up and waits for the next one
+    pub max_retries: u32,
+    pub anonymize: bool,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            enabled: false,
+            endpoint: None,
+            api_token: None,
+            interval_minutes: 15,
+            batch_size: 500,
+            max_retries: 3,
+            anonymize: true,
+        }
+    }
+}
+
+// Progress persisted between runs
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncState {
+    // Highest sync_outbox seq acknowledged by the endpoint
+    pub high_water_mark: i64,
+    pub last_attempt_at: Option<i64>,
+    pub last_success_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub uploaded_rows: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub enabled: bool,
+    pub endpoint: Option<String>,
+    pub running: bool,
+    pub state: SyncState,
+    // Rows waiting for upload, per table
+    pub pending: serde_json::Value,
+    pub pending_total: i64,
+    pub oldest_pending_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub batches: u32,
+    pub uploaded_rows: i64,
+    pub high_water_mark: i64,
+    pub remaining: i64,
+    pub error: Option<String>,
+    pub finished_at: i64,
+}
+
+// LLM Cost Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    // Exact model name, or a prefix ending in '*' (e.g. "deepseek-*")
+    pub model: String,
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CostSettings {
+    pub currency: String,
+    pub prices: Vec<ModelPrice>,
+    pub daily_budget: Option<f64>,
+    pub monthly_budget: Option<f64>,
+    // Percentages of a budget at which a warning is emitted, once per period
+    pub alert_thresholds: Vec<u32>,
+}
+
+impl Default for CostSettings {
+    fn default() -> Self {
+        CostSettings {
+            currency: "CNY".to_string(),
+            prices: Vec::new(),
+            daily_budget: None,
+            monthly_budget: None,
+            alert_thresholds: vec![80, 100],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CostBreakdown {
+    pub key: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost: f64,
+    // Tokens from models missing in the price table, not included in cost
+    pub unpriced_tokens: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub daily_budget: Option<f64>,
+    pub daily_spent: f64,
+    pub monthly_budget: Option<f64>,
+    pub monthly_spent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CostReport {
+    pub currency: String,
+    pub total: CostBreakdown,
+    pub by_day: Vec<CostBreakdown>,
+    pub by_model: Vec<CostBreakdown>,
+    pub by_session_type: Vec<CostBreakdown>,
+    pub by_doctor: Vec<CostBreakdown>,
+    pub unpriced_models: Vec<String>,
+    pub budget: BudgetStatus,
+}
+
+// Payload of the "llm-budget-warning" event
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetWarning {
+    // "daily" or "monthly"
+    pub period: String,
+    // Local date ("2026-10-18") or month ("2026-10")
+    pub period_key: String,
+    pub threshold_percent: u32,
+    pub budget: f64,
+    pub spent: f64,
+    pub currency: String,
+}
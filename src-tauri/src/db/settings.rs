@@ -0,0 +1,34 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// Read a JSON setting, falling back to the type's default when unset or unreadable
+pub fn load<T: DeserializeOwned + Default>(conn: &Connection, key: &str) -> Result<T, String> {
+    let raw: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match raw {
+        Some(value) => serde_json::from_str(&value).or_else(|e| {
+            eprintln!("[Feedback] Ignoring invalid setting '{}': {}", key, e);
+            Ok(T::default())
+        }),
+        None => Ok(T::default()),
+    }
+}
+
+pub fn store<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, CAST(unixepoch('subsec') * 1000 AS INTEGER))
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, json],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use super::models::WorkstationInfo;
+use super::settings;
+
+const WORKSTATION_SETTING: &str = "workstation";
+
+fn host_name() -> String {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "workstation".to_string())
+}
+
+/// Identity of this workstation, created on first use.
+///
+/// Exports carry it so that imports on another machine can record where the
+/// rows came from. The id is random rather than the host name, which may be
+/// shared or change.
+pub fn local(conn: &Connection) -> Result<WorkstationInfo, String> {
+    let stored: Option<WorkstationInfo> = settings::load(conn, WORKSTATION_SETTING)?;
+    if let Some(info) = stored {
+        return Ok(info);
+    }
+
+    let info = WorkstationInfo {
+        id: Uuid::new_v4().to_string(),
+        name: host_name(),
+    };
+    settings::store(conn, WORKSTATION_SETTING, &info)?;
+    println!("[Feedback] Workstation id: {} ({})", info.id, info.name);
+    Ok(info)
+}
+
+// Stored setting only, for reading the identity out of a database snapshot
+pub fn stored(conn: &Connection) -> Option<WorkstationInfo> {
+    settings::load(conn, WORKSTATION_SETTING).ok().flatten()
+}
@@ -3,6 +3,12 @@ use actix_cors::Cors;
 use serde::{Deserialize, Serialize};
 use tauri::{Emitter, Manager};
 
+use crate::commands::feedback::{close_session, insert_session, DbConnection};
+use crate::db::models::{SessionStatus, SessionType};
+use crate::templates::applicability::{self, PatientProfile};
+use crate::templates::search::SymptomSearchRequest;
+use crate::templates::textgen::{self, TextGenRequest};
+use crate::templates::TemplateRepository;
 use crate::SharedAppState;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -16,6 +22,10 @@ pub struct PatientInfo {
     pub sd_sex_text: String,    // 对应 sdSexText
     #[serde(alias = "age")]
     pub age_text: String,       // 对应 ageText
+    // 编码形式的性别和年龄 (可选)，用于症状模板适用人群过滤
+    pub sd_sex: Option<String>,  // "1" 男 / "2" 女
+    pub age_num: Option<f64>,
+    pub age_unit: Option<String>, // Y/M/D
     
     // 保留原有字段，但允许为空或通过别名映射
     pub department: Option<String>,
@@ -26,6 +36,9 @@ pub struct PatientInfo {
     pub id_card: Option<String>,
     #[serde(alias = "allergyHistory")]
     pub allergy_history: Option<String>,
+    // 接诊医生，用于按医生统计 LLM 费用
+    #[serde(alias = "doctor")]
+    pub doctor_name: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -67,6 +80,76 @@ pub struct PatientRiskData {
     pub risks: Vec<RiskItem>,
 }
 
+// 为 HIS 推送的接诊创建反馈会话，完整的 PatientInfo 存入 sessions.metadata
+fn open_consultation_session(
+    app_handle: &tauri::AppHandle,
+    state: &SharedAppState,
+    patient: &PatientInfo,
+) -> Option<String> {
+    let db = app_handle.try_state::<DbConnection>()?;
+    let conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("[Feedback] Failed to lock database: {}", e);
+            return None;
+        }
+    };
+
+    // 上一次接诊未收到 stop/complete，视为取消
+    if let Some(previous) = state.consultation_session.lock().unwrap().take() {
+        if let Err(e) = close_session(&conn, &previous, SessionStatus::Cancelled) {
+            eprintln!("[Feedback] Failed to close session {}: {}", previous, e);
+        }
+    }
+
+    let metadata = serde_json::json!({
+        "source": "http",
+        "patient": patient,
+        "doctor": patient.doctor_name,
+    });
+    match insert_session(
+        &conn,
+        SessionType::Consultation,
+        Some(&patient.id_pi),
+        Some(&patient.na_pi),
+        Some(&metadata),
+    ) {
+        Ok(session_id) => {
+            *state.consultation_session.lock().unwrap() = Some(session_id.clone());
+            println!("[Feedback] Consultation session created: {}", session_id);
+            Some(session_id)
+        }
+        Err(e) => {
+            eprintln!("[Feedback] Failed to create consultation session: {}", e);
+            None
+        }
+    }
+}
+
+// 结束当前 HIS 接诊会话（stop 或 complete_consultation），返回会话 ID
+pub(crate) fn close_consultation_session(
+    app_handle: &tauri::AppHandle,
+    state: &SharedAppState,
+    status: SessionStatus,
+) -> Option<String> {
+    let session_id = state.consultation_session.lock().unwrap().take()?;
+    let db = app_handle.try_state::<DbConnection>()?;
+    let result = db
+        .0
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| close_session(&conn, &session_id, status).map_err(|e| e.to_string()));
+    match result {
+        Ok(true) => println!("[Feedback] Consultation session {} closed ({})", session_id, status),
+        Ok(false) => eprintln!(
+            "[Feedback] Consultation session {} was already closed, {} not recorded",
+            session_id, status
+        ),
+        Err(e) => eprintln!("[Feedback] Failed to close session {}: {}", session_id, e),
+    }
+    Some(session_id)
+}
+
 async fn start_consultation(
     data: web::Json<PatientInfo>,
     app_handle: web::Data<tauri::AppHandle>,
@@ -83,10 +166,13 @@ async fn start_consultation(
         let mut result = state.last_result.lock().unwrap();
         *result = None;
     }
+    let session_id = open_consultation_session(&app_handle, &state, &patient);
 
-    // 2. Emit event to Frontend
+    // 2. Emit event to Frontend (with the feedback session so the UI reuses it)
+    let mut payload = serde_json::to_value(&patient).unwrap_or_default();
+    payload["sessionId"] = serde_json::json!(session_id);
     if let Some(window) = app_handle.get_webview_window("main") {
-        if let Err(e) = window.emit("start-consultation", &patient) {
+        if let Err(e) = window.emit("start-consultation", &payload) {
             eprintln!("Failed to emit event: {}", e);
         } else {
             println!("Event 'start-consultation' emitted successfully to main window");
@@ -103,7 +189,8 @@ async fn start_consultation(
     // 3. Return response
     HttpResponse::Ok().json(serde_json::json!({
         "status": "success",
-        "consultationId": patient.id_pi 
+        "consultationId": patient.id_pi,
+        "sessionId": session_id
     }))
 }
 
@@ -140,10 +227,12 @@ async fn stop_consultation(
         let mut current = state.current_consultation.lock().unwrap();
         *current = None;
     }
+    // Stopped before a result was confirmed
+    let session_id = close_consultation_session(&app_handle, &state, SessionStatus::Cancelled);
 
     // 2. Emit event to Frontend
     if let Some(window) = app_handle.get_webview_window("main") {
-        if let Err(e) = window.emit("stop-consultation", ()) {
+        if let Err(e) = window.emit("stop-consultation", serde_json::json!({ "sessionId": session_id })) {
             eprintln!("Failed to emit event: {}", e);
         } else {
             println!("Event 'stop-consultation' emitted successfully to main window");
@@ -210,6 +299,81 @@ async fn show_patient_risks(
     }))
 }
 
+// 按患者性别、年龄返回适用的症状模板（不适用的症状、分组和字段已移除）
+async fn applicable_templates(
+    data: web::Json<PatientProfile>,
+    app_handle: web::Data<tauri::AppHandle>,
+) -> impl Responder {
+    let patient = data.into_inner();
+    let templates = match app_handle.try_state::<TemplateRepository>() {
+        Some(repository) => repository.load(),
+        None => {
+            return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "error": "Template repository is not available"
+            }));
+        }
+    };
+    match templates {
+        Ok(templates) => HttpResponse::Ok().json(applicability::applicable_templates(templates, &patient)),
+        Err(e) => {
+            eprintln!("[Templates] Failed to load templates: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() }))
+        }
+    }
+}
+
+// 按名称、拼音或主诉原文搜索症状，供 HIS 预选症状
+async fn search_templates(
+    data: web::Json<SymptomSearchRequest>,
+    app_handle: web::Data<tauri::AppHandle>,
+) -> impl Responder {
+    let request = data.into_inner();
+    let index = match app_handle.try_state::<TemplateRepository>() {
+        Some(repository) => repository.search_index(),
+        None => {
+            return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "error": "Template repository is not available"
+            }));
+        }
+    };
+    match index {
+        Ok(index) => HttpResponse::Ok().json(index.search(&request)),
+        Err(e) => {
+            eprintln!("[Templates] Failed to load templates: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() }))
+        }
+    }
+}
+
+// 按当前症状模板为 HIS 生成主诉和现病史文本
+async fn generate_record_text(
+    data: web::Json<TextGenRequest>,
+    app_handle: web::Data<tauri::AppHandle>,
+) -> impl Responder {
+    let request = data.into_inner();
+    let templates = match app_handle.try_state::<TemplateRepository>() {
+        Some(repository) => repository.load(),
+        None => {
+            return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "error": "Template repository is not available"
+            }));
+        }
+    };
+    match templates {
+        Ok(templates) => {
+            let generated = textgen::generate(&templates, &request);
+            if !generated.unknown_symptoms.is_empty() {
+                println!("[Templates] Unknown symptoms in text request: {:?}", generated.unknown_symptoms);
+            }
+            HttpResponse::Ok().json(generated)
+        }
+        Err(e) => {
+            eprintln!("[Templates] Failed to load templates: {}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({ "error": e.to_string() }))
+        }
+    }
+}
+
 pub fn run_server(app_handle: tauri::AppHandle, state: SharedAppState) {
     std::thread::spawn(move || {
         let sys = actix_web::rt::System::new();
@@ -231,6 +395,9 @@ pub fn run_server(app_handle: tauri::AppHandle, state: SharedAppState) {
                     .route("/api/consultation/stop", web::post().to(stop_consultation))
                     .route("/api/consultation/result", web::get().to(get_result))
                     .route("/api/patient/risks", web::post().to(show_patient_risks))
+                    .route("/api/templates/applicable", web::post().to(applicable_templates))
+                    .route("/api/templates/generate-text", web::post().to(generate_record_text))
+                    .route("/api/templates/search", web::post().to(search_templates))
             })
             .bind(("127.0.0.1", 8081))
             .expect("Failed to bind port 8081")
@@ -1,5 +1,5 @@
 use std::sync::{Arc, Mutex};
-use tauri::{Emitter, Manager};
+use tauri::{Emitter, Manager, RunEvent, WindowEvent};
 
 mod http_server;
 use http_server::{ConsultationResult, PatientInfo};
@@ -9,10 +9,13 @@ use aliyun_speech::transcribe_realtime_aliyun;
 
 mod commands;
 mod db;
+mod templates;
 
 pub struct AppState {
     pub current_consultation: Mutex<Option<PatientInfo>>,
     pub last_result: Mutex<Option<ConsultationResult>>,
+    // Feedback session created for the consultation pushed via /api/consultation/start
+    pub consultation_session: Mutex<Option<String>>,
 }
 
 pub type SharedAppState = Arc<AppState>;
@@ -41,72 +44,16 @@ async fn set_window_position(window: tauri::Window, x: i32, y: i32) -> Result<()
 
 #[tauri::command]
 async fn complete_consultation(
+    app: tauri::AppHandle,
     state: tauri::State<'_, SharedAppState>,
     result: ConsultationResult,
 ) -> Result<(), String> {
-    let mut last_result = state.last_result.lock().map_err(|e| e.to_string())?;
-    *last_result = Some(result);
-    println!("Consultation completed, result saved.");
-    Ok(())
-}
-
-#[tauri::command]
-async fn save_templates(content: String) -> Result<(), String> {
-    use std::io::Write;
-    use std::path::Path;
-
-    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
-    println!("Current working directory: {:?}", cwd);
-
-    // Try multiple possible paths for development
-    let paths = vec![
-        "src/assets/templates.json",       // Run from project root
-        "../src/assets/templates.json",    // Run from src-tauri
-        "../../src/assets/templates.json", // Run from src-tauri/target/debug/...
-    ];
-
-    let mut target_path = std::path::PathBuf::new();
-    let mut found = false;
-
-    // First check if any exists (to overwrite)
-    for p in &paths {
-        if Path::new(p).exists() {
-            target_path = Path::new(p).to_path_buf();
-            found = true;
-            break;
-        }
-    }
-
-    // If not found, try to find a valid directory to create it in
-    if !found {
-        println!("templates.json not found in common paths. Checking parent directories...");
-        for p in &paths {
-            if let Some(parent) = Path::new(p).parent() {
-                if parent.exists() {
-                    target_path = Path::new(p).to_path_buf();
-                    found = true;
-                    break;
-                }
-            }
-        }
+    {
+        let mut last_result = state.last_result.lock().map_err(|e| e.to_string())?;
+        *last_result = Some(result);
     }
-
-    if !found {
-        return Err(format!(
-            "Could not find src/assets/templates.json path. CWD: {:?}",
-            cwd
-        ));
-    }
-
-    let path_str = target_path.to_string_lossy().to_string();
-    println!("Saving templates to: {}", path_str);
-
-    let mut file = std::fs::File::create(&target_path)
-        .map_err(|e| format!("Failed to create file at {}: {}", path_str, e))?;
-    file.write_all(content.as_bytes())
-        .map_err(|e| e.to_string())?;
-
-    println!("Templates saved successfully.");
+    http_server::close_consultation_session(&app, &state, db::models::SessionStatus::Completed);
+    println!("Consultation completed, result saved.");
     Ok(())
 }
 
@@ -153,6 +100,7 @@ pub fn run() {
     let state = Arc::new(AppState {
         current_consultation: Mutex::new(None),
         last_result: Mutex::new(None),
+        consultation_session: Mutex::new(None),
     });
 
     tauri::Builder::default()
@@ -176,7 +124,6 @@ pub fn run() {
             set_window_position,
             complete_consultation,
             transcribe_realtime_aliyun,
-            save_templates,
             check_mouse_hover,
             export_templates_with_dialog,
             // Feedback system commands
@@ -190,13 +137,79 @@ pub fn run() {
             commands::feedback::get_session_statistics,
             commands::feedback::get_feedback_statistics,
             commands::feedback::get_performance_statistics,
-            commands::feedback::export_data
+            commands::export::export_data,
+            commands::retention::get_retention_policy,
+            commands::retention::set_retention_policy,
+            commands::retention::purge_expired_data,
+            commands::retention::delete_patient_data,
+            commands::retention::get_database_size,
+            commands::retention::vacuum_database,
+            commands::encryption::get_encryption_status,
+            commands::encryption::rekey_database,
+            commands::analytics::get_recommendation_statistics,
+            commands::analytics::get_modification_analysis,
+            commands::analytics::get_operation_statistics,
+            commands::analytics::get_session_click_paths,
+            commands::analytics::get_operation_funnels,
+            commands::analytics::set_operation_funnels,
+            commands::analytics::get_funnel_statistics,
+            commands::analytics::get_template_usage_statistics,
+            commands::batch::save_batch,
+            commands::batch::enqueue_writes,
+            commands::batch::flush_writes,
+            commands::lifecycle::get_session_lifecycle_policy,
+            commands::lifecycle::set_session_lifecycle_policy,
+            commands::lifecycle::sweep_idle_sessions,
+            commands::lifecycle::resume_interrupted_session,
+            commands::import::import_data,
+            commands::import::list_workstations,
+            commands::sync::get_sync_config,
+            commands::sync::set_sync_config,
+            commands::sync::get_sync_status,
+            commands::sync::sync_now,
+            commands::cost::get_cost_settings,
+            commands::cost::set_cost_settings,
+            commands::cost::get_cost_report,
+            commands::templates::load_templates,
+            commands::templates::save_templates,
+            commands::templates::validate_templates,
+            commands::templates::reset_templates,
+            commands::templates::list_template_revisions,
+            commands::templates::diff_template_revisions,
+            commands::templates::restore_template_revision,
+            commands::templates::preview_template_import,
+            commands::templates::apply_template_import,
+            commands::templates::export_templates_sheet,
+            commands::templates::render_template_questionnaire,
+            commands::templates::export_template_questionnaire,
+            commands::templates::load_applicable_templates,
+            commands::templates::generate_record_text,
+            commands::templates::search_templates,
+            commands::template_subscription::get_template_subscription,
+            commands::template_subscription::set_template_subscription,
+            commands::template_subscription::get_template_subscription_status,
+            commands::template_subscription::refresh_template_bundle
         ])
         .setup(move |app| {
+            // The template subscription below layers onto the repository
+            if let Err(e) = templates::repository::init(app.handle()) {
+                eprintln!("[Templates] Failed to initialize template repository: {}", e);
+            }
+
             // Initialize feedback database
             println!("[Feedback] Initializing feedback database...");
             match commands::feedback::init_database(app.handle()) {
-                Ok(_) => println!("[Feedback] Database initialized successfully"),
+                Ok(_) => {
+                    println!("[Feedback] Database initialized successfully");
+                    commands::retention::start_purge_worker(app.handle().clone());
+                    commands::batch::start_write_buffer(app.handle().clone());
+                    commands::lifecycle::sweep_on_startup(app.handle());
+                    commands::lifecycle::start_session_sweeper(app.handle().clone());
+                    commands::sync::start_sync_worker(app.handle().clone());
+                    commands::template_subscription::start_template_subscription(
+                        app.handle().clone(),
+                    );
+                }
                 Err(e) => {
                     eprintln!("[Feedback] Failed to initialize feedback database: {}", e);
                     eprintln!("[Feedback] Error details: {:?}", e);
@@ -265,6 +278,17 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(|window, event| {
+            // Buffered feedback writes must reach the database before the window goes
+            if let WindowEvent::CloseRequested { .. } = event {
+                commands::batch::flush_pending(window.app_handle());
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                commands::batch::flush_pending(app);
+            }
+        });
 }
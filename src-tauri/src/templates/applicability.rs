@@ -0,0 +1,436 @@
+use serde::{Deserialize, Serialize};
+
+use super::schema::{AgeUnit, ApplicablePopulation, SymptomTemplate};
+
+/// The patient attributes templates can be restricted by.
+///
+/// Accepts the HIS patient record as is: the coded `sdSex` ("1" male /
+/// "2" female) and `ageNum` + `ageUnit` (Y/M/D) when present, otherwise the
+/// display texts `sdSexText` ("女性") and `ageText` ("19岁", "2岁3个月").
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PatientProfile {
+    pub sd_sex: Option<String>,
+    #[serde(alias = "gender")]
+    pub sd_sex_text: Option<String>,
+    pub age_num: Option<f64>,
+    pub age_unit: Option<String>,
+    #[serde(alias = "age")]
+    pub age_text: Option<String>,
+}
+
+impl PatientProfile {
+    /// HIS sex code, "1" or "2"; None when unknown.
+    pub fn sex(&self) -> Option<&'static str> {
+        [&self.sd_sex, &self.sd_sex_text]
+            .into_iter()
+            .flatten()
+            .find_map(|s| parse_sex(s))
+    }
+
+    /// Age in days (a year is 365, a month 30); None when unknown.
+    pub fn age_days(&self) -> Option<f64> {
+        if let Some(num) = self.age_num.filter(|n| *n >= 0.0) {
+            let unit = match self.age_unit.as_deref().map(str::trim) {
+                Some("M") | Some("m") => AgeUnit::Months,
+                Some("D") | Some("d") => AgeUnit::Days,
+                _ => AgeUnit::Years,
+            };
+            return Some(unit.to_days(num));
+        }
+        self.age_text.as_deref().and_then(parse_age_days)
+    }
+}
+
+/// Map a sex code or text to the HIS code: "1" male, "2" female.
+pub fn parse_sex(text: &str) -> Option<&'static str> {
+    match text.trim().to_lowercase().as_str() {
+        "1" | "男" | "男性" | "m" | "male" => Some("1"),
+        "2" | "女" | "女性" | "f" | "female" => Some("2"),
+        _ => None,
+    }
+}
+
+// Days per age unit as written in HIS age texts
+fn unit_days(unit: &str) -> Option<f64> {
+    match unit {
+        "岁" | "年" | "y" | "Y" => Some(365.0),
+        "月" | "个月" | "m" | "M" => Some(30.0),
+        "周" | "w" | "W" => Some(7.0),
+        "天" | "日" | "d" | "D" => Some(1.0),
+        // Newborns are aged in hours or minutes
+        "小时" | "时" | "分" | "分钟" | "h" | "H" => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Parse an age text such as "19岁", "3个月", "2岁3个月" or "15天" into days.
+///
+/// A number without a unit counts as years. Returns None when the text has no
+/// number or an unknown unit.
+pub fn parse_age_days(text: &str) -> Option<f64> {
+    let mut days = 0.0;
+    let mut found = false;
+    let mut chars = text.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let number: String =
+            std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_digit() || *c == '.')).collect();
+        if number.is_empty() {
+            return None;
+        }
+        let value: f64 = number.parse().ok()?;
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let unit: String =
+            std::iter::from_fn(|| chars.next_if(|c| !c.is_ascii_digit() && !c.is_whitespace()))
+                .collect();
+        let factor = if unit.is_empty() {
+            365.0
+        } else {
+            unit_days(&unit)?
+        };
+        days += value * factor;
+        found = true;
+    }
+    found.then_some(days)
+}
+
+/// Whether a symptom, section or field restricted to `population` applies to
+/// the patient.
+///
+/// Only what is known about the patient is checked: without a sex the gender
+/// restriction is ignored, without an age the age range is.
+pub fn is_applicable(population: Option<&ApplicablePopulation>, patient: &PatientProfile) -> bool {
+    let Some(population) = population else {
+        return true;
+    };
+
+    if let Some(sex) = patient.sex() {
+        if !population.genders.is_empty() && !population.genders.iter().any(|g| g == sex) {
+            return false;
+        }
+    }
+
+    if let (Some(range), Some(age)) = (&population.age_range, patient.age_days()) {
+        let unit = range.unit.unwrap_or(AgeUnit::Years);
+        let min = range.min.map_or(0.0, |min| unit.to_days(min));
+        let max = range.max.map_or(f64::INFINITY, |max| unit.to_days(max));
+        if age < min || age > max {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether `applicable_templates` keeps anything of `symptom` for the patient.
+pub fn symptom_applies(symptom: &SymptomTemplate, patient: &PatientProfile) -> bool {
+    is_applicable(Some(&symptom.applicable_population), patient)
+        && (symptom.config.sections.is_empty()
+            || symptom.config.sections.iter().any(|section| {
+                is_applicable(section.applicable_population.as_ref(), patient)
+                    && section
+                        .fields
+                        .iter()
+                        .any(|field| is_applicable(field.applicable_population.as_ref(), patient))
+            }))
+}
+
+/// The templates as they apply to the patient: symptoms, sections and fields
+/// that do not apply are removed, as are sections left without fields.
+///
+/// A symptom whose sections were all removed goes too; many templates only
+/// restrict their single section (e.g. breast symptoms to women).
+pub fn applicable_templates(
+    templates: Vec<SymptomTemplate>,
+    patient: &PatientProfile,
+) -> Vec<SymptomTemplate> {
+    templates
+        .into_iter()
+        .filter(|symptom| is_applicable(Some(&symptom.applicable_population), patient))
+        .filter_map(|mut symptom| {
+            let had_sections = !symptom.config.sections.is_empty();
+            symptom.config.sections.retain_mut(|section| {
+                if !is_applicable(section.applicable_population.as_ref(), patient) {
+                    return false;
+                }
+                section
+                    .fields
+                    .retain(|field| is_applicable(field.applicable_population.as_ref(), patient));
+                !section.fields.is_empty()
+            });
+            (!had_sections || !symptom.config.sections.is_empty()).then_some(symptom)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::templates::schema::AgeRange;
+
+    fn patient(sex: Option<&str>, age: Option<&str>) -> PatientProfile {
+        PatientProfile {
+            sd_sex: sex.map(str::to_string),
+            age_text: age.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn population(
+        genders: &[&str],
+        range: Option<(Option<f64>, Option<f64>, AgeUnit)>,
+    ) -> ApplicablePopulation {
+        ApplicablePopulation {
+            genders: genders.iter().map(|g| g.to_string()).collect(),
+            age_range: range.map(|(min, max, unit)| AgeRange {
+                min,
+                max,
+                unit: Some(unit),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn field(key: &str, population: Option<Value>) -> Value {
+        let mut field = json!({
+            "id": key,
+            "key": key,
+            "label": key,
+            "type": "radio",
+            "props": { "options": ["是", "否"] },
+            "storageKey": key,
+        });
+        if let Some(population) = population {
+            field["applicablePopulation"] = population;
+        }
+        field
+    }
+
+    fn symptom(key: &str, population: Value, sections: Vec<Value>) -> SymptomTemplate {
+        serde_json::from_value(json!({
+            "id": key,
+            "key": key,
+            "name": key,
+            "applicablePopulation": population,
+            "config": { "sections": sections },
+        }))
+        .unwrap()
+    }
+
+    fn section(id: &str, population: Option<Value>, fields: Vec<Value>) -> Value {
+        let mut section = json!({ "id": id, "title": id, "fields": fields });
+        if let Some(population) = population {
+            section["applicablePopulation"] = population;
+        }
+        section
+    }
+
+    #[test]
+    fn parses_age_texts() {
+        assert_eq!(parse_age_days("19岁"), Some(19.0 * 365.0));
+        assert_eq!(parse_age_days("2岁3个月"), Some(2.0 * 365.0 + 3.0 * 30.0));
+        assert_eq!(parse_age_days("15天"), Some(15.0));
+        assert_eq!(parse_age_days("3周"), Some(21.0));
+        assert_eq!(parse_age_days("12小时"), Some(0.0));
+        assert_eq!(parse_age_days("5"), Some(5.0 * 365.0));
+        assert_eq!(parse_age_days(" 2 岁 "), Some(2.0 * 365.0));
+    }
+
+    #[test]
+    fn rejects_unparseable_age_texts() {
+        assert_eq!(parse_age_days(""), None);
+        assert_eq!(parse_age_days("岁"), None);
+        assert_eq!(parse_age_days("abc"), None);
+        assert_eq!(parse_age_days("3光年"), None);
+    }
+
+    #[test]
+    fn parses_sex() {
+        for male in ["男", "男性", "1", "M", "m", "male"] {
+            assert_eq!(parse_sex(male), Some("1"), "{}", male);
+        }
+        for female in ["女", "女性", "2", "F", "f", "Female"] {
+            assert_eq!(parse_sex(female), Some("2"), "{}", female);
+        }
+        for unknown in ["", "0", "9", "未知", "x"] {
+            assert_eq!(parse_sex(unknown), None, "{}", unknown);
+        }
+    }
+
+    #[test]
+    fn sex_falls_back_to_text() {
+        let profile = PatientProfile {
+            sd_sex: Some("9".to_string()),
+            sd_sex_text: Some("女性".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(profile.sex(), Some("2"));
+        assert_eq!(PatientProfile::default().sex(), None);
+    }
+
+    #[test]
+    fn age_prefers_coded_age_over_text() {
+        let profile = PatientProfile {
+            age_num: Some(2.0),
+            age_unit: Some("M".to_string()),
+            age_text: Some("19岁".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(profile.age_days(), Some(60.0));
+
+        let profile = PatientProfile {
+            age_num: Some(3.0),
+            age_unit: None,
+            ..Default::default()
+        };
+        assert_eq!(profile.age_days(), Some(3.0 * 365.0));
+
+        // A negative coded age is ignored in favour of the text
+        let profile = PatientProfile {
+            age_num: Some(-1.0),
+            age_text: Some("15天".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(profile.age_days(), Some(15.0));
+        assert_eq!(PatientProfile::default().age_days(), None);
+    }
+
+    #[test]
+    fn age_range_bounds_are_inclusive() {
+        let adults = population(&[], Some((Some(18.0), Some(60.0), AgeUnit::Years)));
+        assert!(!is_applicable(Some(&adults), &patient(None, Some("17岁"))));
+        assert!(is_applicable(Some(&adults), &patient(None, Some("18岁"))));
+        assert!(is_applicable(Some(&adults), &patient(None, Some("60岁"))));
+        assert!(!is_applicable(Some(&adults), &patient(None, Some("61岁"))));
+
+        let infants = population(&[], Some((Some(1.0), Some(12.0), AgeUnit::Months)));
+        assert!(!is_applicable(Some(&infants), &patient(None, Some("29天"))));
+        assert!(is_applicable(Some(&infants), &patient(None, Some("30天"))));
+        assert!(is_applicable(
+            Some(&infants),
+            &patient(None, Some("12个月"))
+        ));
+        assert!(!is_applicable(Some(&infants), &patient(None, Some("1岁"))));
+
+        let newborns = population(&[], Some((None, Some(28.0), AgeUnit::Days)));
+        assert!(is_applicable(
+            Some(&newborns),
+            &patient(None, Some("12小时"))
+        ));
+        assert!(is_applicable(Some(&newborns), &patient(None, Some("28天"))));
+        assert!(!is_applicable(
+            Some(&newborns),
+            &patient(None, Some("29天"))
+        ));
+    }
+
+    #[test]
+    fn unknown_attributes_are_not_checked() {
+        let girls = population(&["2"], Some((None, Some(14.0), AgeUnit::Years)));
+        assert!(is_applicable(Some(&girls), &patient(None, None)));
+        assert!(is_applicable(Some(&girls), &patient(None, Some("10岁"))));
+        assert!(is_applicable(Some(&girls), &patient(Some("2"), None)));
+        assert!(!is_applicable(Some(&girls), &patient(Some("1"), None)));
+        assert!(!is_applicable(Some(&girls), &patient(None, Some("30岁"))));
+        assert!(is_applicable(None, &patient(Some("1"), Some("30岁"))));
+    }
+
+    #[test]
+    fn drops_symptoms_for_the_other_sex() {
+        let templates = vec![
+            symptom(
+                "fever",
+                json!({}),
+                vec![section("s1", None, vec![field("a", None)])],
+            ),
+            symptom(
+                "menstrualDisorder",
+                json!({ "genders": ["2"] }),
+                vec![section("s1", None, vec![field("a", None)])],
+            ),
+        ];
+        let keys = |templates: Vec<SymptomTemplate>| -> Vec<String> {
+            templates.into_iter().map(|t| t.key).collect()
+        };
+
+        assert_eq!(
+            keys(applicable_templates(
+                templates.clone(),
+                &patient(Some("1"), None)
+            )),
+            vec!["fever"]
+        );
+        assert_eq!(
+            keys(applicable_templates(templates, &patient(Some("2"), None))),
+            vec!["fever", "menstrualDisorder"]
+        );
+    }
+
+    #[test]
+    fn drops_sections_and_fields_that_do_not_apply() {
+        let female = json!({ "genders": ["2"] });
+        let templates = vec![
+            symptom(
+                "abdominalPain",
+                json!({}),
+                vec![
+                    section(
+                        "common",
+                        None,
+                        vec![
+                            field("site", None),
+                            field("pregnancy", Some(female.clone())),
+                        ],
+                    ),
+                    section(
+                        "gynecology",
+                        Some(female.clone()),
+                        vec![field("menses", None)],
+                    ),
+                    section(
+                        "obstetric",
+                        None,
+                        vec![field("gestation", Some(female.clone()))],
+                    ),
+                ],
+            ),
+            // Restricted only through its single section
+            symptom(
+                "breastLump",
+                json!({}),
+                vec![section("s1", Some(female), vec![field("size", None)])],
+            ),
+        ];
+
+        let applicable = applicable_templates(templates, &patient(Some("1"), None));
+        assert_eq!(applicable.len(), 1);
+        let sections = &applicable[0].config.sections;
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].id, "common");
+        let fields: Vec<&str> = sections[0].fields.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(fields, vec!["site"]);
+    }
+
+    #[test]
+    fn symptom_applies_matches_applicable_templates() {
+        let female = json!({ "genders": ["2"] });
+        let breast = symptom(
+            "breastLump",
+            json!({}),
+            vec![section("s1", Some(female), vec![field("size", None)])],
+        );
+        assert!(!symptom_applies(&breast, &patient(Some("1"), None)));
+        assert!(symptom_applies(&breast, &patient(Some("2"), None)));
+        assert!(symptom_applies(&breast, &patient(None, None)));
+
+        let empty = symptom("empty", json!({}), Vec::new());
+        assert!(symptom_applies(&empty, &patient(Some("1"), None)));
+        assert_eq!(
+            applicable_templates(vec![empty], &patient(Some("1"), None)).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::error::{TemplateError, ValidationIssue};
+use super::schema::{self, SymptomTemplate};
+
+/// A department's curated template set, as published on the template server.
+///
+/// The bundle is a JSON document signed with minisign (the same format
+/// `tauri signer` produces for app updates); the signature covers the exact
+/// bytes served, so nothing is re-serialised before verification.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleDocument {
+    version: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    published_at: Option<i64>,
+    templates: Value,
+}
+
+// What the status reports about the base layer in use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleInfo {
+    pub version: String,
+    pub name: Option<String>,
+    pub published_at: Option<i64>,
+    pub sha256: String,
+    pub symptom_count: usize,
+    // Trusted comment of the signature, e.g. "timestamp:1760745600 file:bundle.json"
+    pub signed_comment: String,
+}
+
+impl BundleInfo {
+    /// Signing time from the trusted comment, in ms; None when it has none.
+    pub fn signed_at(&self) -> Option<i64> {
+        self.signed_comment
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix("timestamp:"))
+            .and_then(|seconds| seconds.parse::<i64>().ok())
+            .map(|seconds| seconds * 1000)
+    }
+
+    /// Whether this bundle was published before `other`.
+    ///
+    /// Compares `publishedAt` when both bundles have it, otherwise the signing
+    /// times; bundles with neither are never considered older.
+    pub fn is_older_than(&self, other: &BundleInfo) -> bool {
+        match (self.published_at, other.published_at) {
+            (Some(published), Some(other_published)) => published < other_published,
+            _ => match (self.signed_at(), other.signed_at()) {
+                (Some(signed), Some(other_signed)) => signed < other_signed,
+                _ => false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TemplateBundle {
+    pub info: BundleInfo,
+    pub templates: Vec<SymptomTemplate>,
+}
+
+fn invalid(message: String) -> TemplateError {
+    TemplateError::Parse(ValidationIssue {
+        path: "$".to_string(),
+        message,
+    })
+}
+
+// Accept either the minisign text itself or the base64 of it, which is how
+// `tauri signer` prints keys and writes `.sig` files
+fn minisign_text(input: &str) -> Option<String> {
+    let input = input.trim();
+    if input.starts_with("untrusted comment:") {
+        return Some(input.to_string());
+    }
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(input)
+        .ok()?;
+    String::from_utf8(decoded)
+        .ok()
+        .filter(|text| text.starts_with("untrusted comment:"))
+}
+
+/// Parse a public key given as a bare minisign key (`RW...`), a `minisign.pub`
+/// file, or the base64 form used in `tauri.conf.json`.
+pub fn parse_public_key(key: &str) -> Result<PublicKey, String> {
+    let key = key.trim();
+    let parsed = match minisign_text(key) {
+        Some(text) => PublicKey::decode(&text),
+        None => PublicKey::from_base64(key),
+    };
+    parsed.map_err(|e| format!("Invalid public key: {}", e))
+}
+
+/// Check the signature of `bytes` and decode them as a template bundle.
+///
+/// The templates must also pass the same validation as a local save; a bundle
+/// that fails either check is rejected as a whole.
+pub fn verify(
+    bytes: &[u8],
+    signature: &str,
+    public_key: &PublicKey,
+) -> Result<TemplateBundle, TemplateError> {
+    let signature_text = minisign_text(signature)
+        .ok_or_else(|| TemplateError::Storage("Bundle signature is not minisign".to_string()))?;
+    let signature = Signature::decode(&signature_text)
+        .map_err(|e| TemplateError::Storage(format!("Invalid bundle signature: {}", e)))?;
+    public_key
+        .verify(bytes, &signature, false)
+        .map_err(|e| TemplateError::Storage(format!("Bundle signature check failed: {}", e)))?;
+
+    let document: BundleDocument = serde_json::from_slice(bytes)
+        .map_err(|e| invalid(format!("Bundle is not a template bundle: {}", e)))?;
+    if document.version.trim().is_empty() {
+        return Err(invalid("Bundle has no version".to_string()));
+    }
+    let templates = schema::from_value(document.templates).map_err(|e| match e {
+        // Point at the templates inside the bundle document
+        TemplateError::Parse(issue) => TemplateError::Parse(ValidationIssue {
+            path: issue.path.replacen('$', "$.templates", 1),
+            message: issue.message,
+        }),
+        other => other,
+    })?;
+    let issues = schema::validate(&templates);
+    if !issues.is_empty() {
+        return Err(TemplateError::Invalid(
+            issues
+                .into_iter()
+                .map(|issue| ValidationIssue {
+                    path: issue.path.replacen('$', "$.templates", 1),
+                    message: issue.message,
+                })
+                .collect(),
+        ));
+    }
+
+    Ok(TemplateBundle {
+        info: BundleInfo {
+            version: document.version,
+            name: document.name,
+            published_at: document.published_at,
+            sha256: hex::encode(Sha256::digest(bytes)),
+            symptom_count: templates.len(),
+            signed_comment: signature.trusted_comment().to_string(),
+        },
+        templates,
+    })
+}
+
+// Template Subscription Types
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SubscriptionConfig {
+    pub enabled: bool,
+    // Bundle URL on the department server; https, or http for localhost
+    pub url: Option<String>,
+    // Defaults to `<url>.sig`
+    pub signature_url: Option<String>,
+    // Minisign public key the bundle must be signed with
+    pub public_key: Option<String>,
+    // Sent as a bearer token when set
+    pub api_token: Option<String>,
+    pub interval_minutes: u32,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        SubscriptionConfig {
+            enabled: false,
+            url: None,
+            signature_url: None,
+            public_key: None,
+            api_token: None,
+            interval_minutes: 60,
+        }
+    }
+}
+
+// Progress persisted between checks
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SubscriptionState {
+    pub last_checked_at: Option<i64>,
+    pub last_success_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionStatus {
+    pub enabled: bool,
+    pub url: Option<String>,
+    pub running: bool,
+    // Bundle currently layered under the local templates
+    pub active: Option<BundleInfo>,
+    pub state: SubscriptionState,
+    // Local symptoms that override or add to the bundle
+    pub overrides: Vec<super::history::SymptomRef>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshReport {
+    // False when the server still serves the active bundle
+    pub changed: bool,
+    pub active: Option<BundleInfo>,
+    pub checked_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+    use openssl::hash::{hash, MessageDigest};
+    use openssl::pkey::{PKey, Private};
+
+    fn info(published_at: Option<i64>, signed_comment: &str) -> BundleInfo {
+        BundleInfo {
+            version: "v".to_string(),
+            name: None,
+            published_at,
+            sha256: String::new(),
+            symptom_count: 0,
+            signed_comment: signed_comment.to_string(),
+        }
+    }
+
+    #[test]
+    fn reads_signing_time_from_trusted_comment() {
+        let signed = info(None, "timestamp:1760745600\tfile:bundle.json");
+        assert_eq!(signed.signed_at(), Some(1_760_745_600_000));
+        assert_eq!(info(None, "file:bundle.json").signed_at(), None);
+        assert_eq!(info(None, "timestamp:soon").signed_at(), None);
+    }
+
+    #[test]
+    fn compares_publication_before_signing_time() {
+        // Re-signing an old bundle does not make it newer
+        let old = info(Some(1_000), "timestamp:300");
+        let new = info(Some(2_000), "timestamp:100");
+        assert!(old.is_older_than(&new));
+        assert!(!new.is_older_than(&old));
+
+        // Without publishedAt the signing times decide
+        let old = info(None, "timestamp:100");
+        let new = info(None, "timestamp:200");
+        assert!(old.is_older_than(&new));
+        assert!(!new.is_older_than(&old));
+
+        assert!(!info(None, "").is_older_than(&new));
+        assert!(!info(Some(1), "").is_older_than(&info(None, "")));
+    }
+
+    // A minisign key pair made for the test, as `tauri signer generate` would
+    struct Signer {
+        key: PKey<Private>,
+        key_id: [u8; 8],
+    }
+
+    impl Signer {
+        fn generate() -> Self {
+            Signer {
+                key: PKey::generate_ed25519().unwrap(),
+                key_id: rand::random(),
+            }
+        }
+
+        fn ed25519(&self, message: &[u8]) -> Vec<u8> {
+            let mut signer = openssl::sign::Signer::new_without_digest(&self.key).unwrap();
+            signer.sign_oneshot_to_vec(message).unwrap()
+        }
+
+        // Base64 of the `minisign.pub` text
+        fn public_key(&self) -> String {
+            let mut bin = b"Ed".to_vec();
+            bin.extend_from_slice(&self.key_id);
+            bin.extend_from_slice(&self.key.raw_public_key().unwrap());
+            let text = format!(
+                "untrusted comment: minisign public key\n{}\n",
+                STANDARD.encode(bin)
+            );
+            STANDARD.encode(text)
+        }
+
+        // Base64 of the `.sig` text; legacy signatures cover the bytes
+        // themselves instead of their BLAKE2b-512 hash
+        fn sign(&self, bytes: &[u8], legacy: bool) -> String {
+            let (algorithm, signature) = if legacy {
+                (b"Ed", self.ed25519(bytes))
+            } else {
+                let digest = MessageDigest::from_name("BLAKE2b512").unwrap();
+                (b"ED", self.ed25519(&hash(digest, bytes).unwrap()))
+            };
+            let trusted = "timestamp:1760745600\tfile:bundle.json";
+            let mut bin = algorithm.to_vec();
+            bin.extend_from_slice(&self.key_id);
+            bin.extend_from_slice(&signature);
+            let global = self.ed25519(&[signature.as_slice(), trusted.as_bytes()].concat());
+            let text = format!(
+                "untrusted comment: signature from tauri secret key\n{}\ntrusted comment: {}\n{}\n",
+                STANDARD.encode(bin),
+                trusted,
+                STANDARD.encode(global)
+            );
+            STANDARD.encode(text)
+        }
+    }
+
+    fn bundle_bytes() -> Vec<u8> {
+        let templates = include_str!("../../../src/assets/templates.json");
+        format!(r#"{{"version":"2026.10","templates":{}}}"#, templates).into_bytes()
+    }
+
+    fn rejected(result: Result<TemplateBundle, TemplateError>) -> String {
+        match result {
+            Err(TemplateError::Storage(message)) => message,
+            Err(other) => panic!("expected a signature error, got {:?}", other),
+            Ok(_) => panic!("expected the bundle to be rejected"),
+        }
+    }
+
+    #[test]
+    fn accepts_a_correctly_signed_bundle() {
+        let signer = Signer::generate();
+        let bytes = bundle_bytes();
+        let key = parse_public_key(&signer.public_key()).unwrap();
+
+        let bundle = verify(&bytes, &signer.sign(&bytes, false), &key).unwrap();
+        assert_eq!(bundle.info.version, "2026.10");
+        assert_eq!(bundle.info.symptom_count, bundle.templates.len());
+        assert!(!bundle.templates.is_empty());
+        assert_eq!(bundle.info.sha256, hex::encode(Sha256::digest(&bytes)));
+        assert_eq!(bundle.info.signed_at(), Some(1_760_745_600_000));
+    }
+
+    #[test]
+    fn rejects_tampered_bytes() {
+        let signer = Signer::generate();
+        let bytes = bundle_bytes();
+        let signature = signer.sign(&bytes, false);
+        let key = parse_public_key(&signer.public_key()).unwrap();
+
+        let tampered = String::from_utf8(bytes)
+            .unwrap()
+            .replacen("2026.10", "2026.11", 1)
+            .into_bytes();
+        let message = rejected(verify(&tampered, &signature, &key));
+        assert!(
+            message.starts_with("Bundle signature check failed"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn rejects_a_signature_from_another_key() {
+        let signer = Signer::generate();
+        let bytes = bundle_bytes();
+        let signature = signer.sign(&bytes, false);
+
+        // A different key pair under the same key id gets past the id check
+        let other = Signer {
+            key: PKey::generate_ed25519().unwrap(),
+            key_id: signer.key_id,
+        };
+        let key = parse_public_key(&other.public_key()).unwrap();
+        let message = rejected(verify(&bytes, &signature, &key));
+        assert!(
+            message.starts_with("Bundle signature check failed"),
+            "{}",
+            message
+        );
+
+        let key = parse_public_key(&Signer::generate().public_key()).unwrap();
+        let message = rejected(verify(&bytes, &signature, &key));
+        assert!(
+            message.starts_with("Bundle signature check failed"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn rejects_a_legacy_signature() {
+        let signer = Signer::generate();
+        let bytes = bundle_bytes();
+        let key = parse_public_key(&signer.public_key()).unwrap();
+
+        let message = rejected(verify(&bytes, &signer.sign(&bytes, true), &key));
+        assert!(
+            message.starts_with("Bundle signature check failed"),
+            "{}",
+            message
+        );
+
+        let message = rejected(verify(&bytes, "not a signature", &key));
+        assert_eq!(message, "Bundle signature is not minisign");
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::fmt;
+
+// One problem found in the templates, located by a JSON path such as
+// `$[3].config.sections[0].fields[2].storageKey`
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Errors returned by the template commands.
+///
+/// Like `FeedbackError` it serializes as `{ kind, message, ... }`; `invalid`
+/// carries every issue found so the editor can mark all of them at once.
+#[derive(Debug)]
+pub enum TemplateError {
+    // JSON that does not match the template structure
+    Parse(ValidationIssue),
+    Invalid(Vec<ValidationIssue>),
+    Storage(String),
+}
+
+impl TemplateError {
+    fn kind(&self) -> &'static str {
+        match self {
+            TemplateError::Parse(_) => "parse",
+            TemplateError::Invalid(_) => "invalid",
+            TemplateError::Storage(_) => "storage",
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Parse(issue) => write!(f, "Malformed templates at {}", issue),
+            TemplateError::Invalid(issues) => {
+                write!(f, "{} template problem(s)", issues.len())?;
+                if let Some(first) = issues.first() {
+                    write!(f, ", first: {}", first)?;
+                }
+                Ok(())
+            }
+            TemplateError::Storage(message) => write!(f, "Template storage error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl Serialize for TemplateError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = json!({
+            "kind": self.kind(),
+            "message": self.to_string(),
+        });
+        match self {
+            TemplateError::Parse(issue) => value["issues"] = json!([issue]),
+            TemplateError::Invalid(issues) => value["issues"] = json!(issues),
+            TemplateError::Storage(_) => {}
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl From<String> for TemplateError {
+    fn from(message: String) -> Self {
+        TemplateError::Storage(message)
+    }
+}
+
+impl From<TemplateError> for String {
+    fn from(e: TemplateError) -> Self {
+        e.to_string()
+    }
+}
@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::error::TemplateError;
+use super::repository::write_atomic;
+use super::schema::{Field, SymptomTemplate};
+use crate::commands::feedback::current_timestamp;
+
+const INDEX_FILE: &str = "index.json";
+// Oldest revisions beyond this are deleted on the next save
+const MAX_REVISIONS: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionInfo {
+    pub id: String,
+    pub created_at: i64,
+    pub author: String,
+    #[serde(default)]
+    pub note: String,
+    pub symptom_count: usize,
+    // SHA-256 of the stored JSON, used to skip saves that change nothing
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomRef {
+    pub key: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldRef {
+    pub key: String,
+    pub label: String,
+}
+
+// A property that differs, e.g. `props.options` or `applicablePopulation`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyChange {
+    pub property: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub key: String,
+    pub label: String,
+    pub changes: Vec<PropertyChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomChange {
+    pub key: String,
+    pub name: String,
+    // Symptom-level properties other than the fields
+    pub changes: Vec<PropertyChange>,
+    pub fields_added: Vec<FieldRef>,
+    pub fields_removed: Vec<FieldRef>,
+    pub fields_modified: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateDiff {
+    pub from: String,
+    pub to: String,
+    pub added: Vec<SymptomRef>,
+    pub removed: Vec<SymptomRef>,
+    pub modified: Vec<SymptomChange>,
+}
+
+/// Snapshots of the template list taken on every save.
+///
+/// Each revision is a compact copy of the full list in `<id>.json`; the
+/// metadata lives in `index.json`, newest first, so listing never has to open
+/// the snapshots themselves.
+pub struct TemplateHistory {
+    dir: PathBuf,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+impl TemplateHistory {
+    pub fn new(dir: PathBuf) -> Result<Self, String> {
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        Ok(TemplateHistory { dir })
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join(INDEX_FILE)
+    }
+
+    fn revision_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    pub fn list(&self) -> Result<Vec<RevisionInfo>, String> {
+        let path = self.index_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Revision index {} is corrupt: {}", path.display(), e))
+    }
+
+    fn write_index(&self, revisions: &[RevisionInfo]) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(revisions).map_err(|e| e.to_string())?;
+        write_atomic(&self.index_path(), content.as_bytes())
+    }
+
+    /// Store `templates` as a new revision; returns `None` when they are
+    /// identical to the latest one. The caller holds the repository's write
+    /// lock, so the index is never updated concurrently.
+    pub fn record(
+        &self,
+        templates: &[SymptomTemplate],
+        author: &str,
+        note: &str,
+    ) -> Result<Option<RevisionInfo>, String> {
+        let content = serde_json::to_string(templates).map_err(|e| e.to_string())?;
+        let sha256 = sha256_hex(content.as_bytes());
+
+        let mut revisions = self.list()?;
+        if revisions
+            .first()
+            .is_some_and(|latest| latest.sha256 == sha256)
+        {
+            return Ok(None);
+        }
+
+        let created_at = current_timestamp();
+        let info = RevisionInfo {
+            id: format!("{}-{}", created_at, &sha256[..8]),
+            created_at,
+            author: author.to_string(),
+            note: note.to_string(),
+            symptom_count: templates.len(),
+            sha256,
+        };
+        write_atomic(&self.revision_path(&info.id), content.as_bytes())?;
+        revisions.insert(0, info.clone());
+
+        let expired = revisions.split_off(revisions.len().min(MAX_REVISIONS));
+        self.write_index(&revisions)?;
+        // Only delete snapshots once the index no longer points at them
+        for revision in expired {
+            if let Err(e) = std::fs::remove_file(self.revision_path(&revision.id)) {
+                eprintln!(
+                    "[Templates] Failed to delete revision {}: {}",
+                    revision.id, e
+                );
+            }
+        }
+
+        Ok(Some(info))
+    }
+
+    pub fn info(&self, id: &str) -> Result<RevisionInfo, TemplateError> {
+        self.list()?
+            .into_iter()
+            .find(|r| r.id == id)
+            .ok_or_else(|| TemplateError::Storage(format!("Revision {} not found", id)))
+    }
+
+    pub fn load(&self, id: &str) -> Result<Vec<SymptomTemplate>, TemplateError> {
+        self.info(id)?;
+        let path = self.revision_path(id);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        super::schema::parse(&content)
+    }
+}
+
+pub fn revisions_dir(templates_dir: &Path) -> PathBuf {
+    templates_dir.join("template_revisions")
+}
+
+// Compare the JSON form of two values key by key; `prefix` names the parent
+fn property_changes(
+    before: &Value,
+    after: &Value,
+    prefix: &str,
+    skip: &[&str],
+) -> Vec<PropertyChange> {
+    let empty = serde_json::Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+
+    keys.into_iter()
+        .filter(|key| !skip.contains(&key.as_str()))
+        .filter_map(|key| {
+            let old = before.get(key).unwrap_or(&Value::Null);
+            let new = after.get(key).unwrap_or(&Value::Null);
+            (old != new).then(|| PropertyChange {
+                property: format!("{}{}", prefix, key),
+                before: old.clone(),
+                after: new.clone(),
+            })
+        })
+        .collect()
+}
+
+fn field_changes(before: &Field, after: &Field) -> Vec<PropertyChange> {
+    let old = serde_json::to_value(before).unwrap_or_default();
+    let new = serde_json::to_value(after).unwrap_or_default();
+    // Report option edits as `props.options` rather than one opaque `props`
+    let mut changes = property_changes(&old, &new, "", &["props"]);
+    changes.extend(property_changes(
+        &old["props"],
+        &new["props"],
+        "props.",
+        &[],
+    ));
+    changes
+}
+
+fn fields_by_key(template: &SymptomTemplate) -> BTreeMap<&str, &Field> {
+    template
+        .config
+        .sections
+        .iter()
+        .flat_map(|section| &section.fields)
+        .map(|field| (field.key.as_str(), field))
+        .collect()
+}
+
+pub fn symptom_change(before: &SymptomTemplate, after: &SymptomTemplate) -> Option<SymptomChange> {
+    let old = serde_json::to_value(before).unwrap_or_default();
+    let new = serde_json::to_value(after).unwrap_or_default();
+    // updatedAt moves on every edit and says nothing on its own
+    let mut changes = property_changes(&old, &new, "", &["config", "updatedAt"]);
+    changes.extend(property_changes(
+        &old["config"],
+        &new["config"],
+        "config.",
+        &["sections"],
+    ));
+
+    let old_fields = fields_by_key(before);
+    let new_fields = fields_by_key(after);
+    let field_ref = |field: &Field| FieldRef {
+        key: field.key.clone(),
+        label: field.label.clone(),
+    };
+
+    let fields_added: Vec<FieldRef> = new_fields
+        .iter()
+        .filter(|(key, _)| !old_fields.contains_key(*key))
+        .map(|(_, field)| field_ref(field))
+        .collect();
+    let fields_removed: Vec<FieldRef> = old_fields
+        .iter()
+        .filter(|(key, _)| !new_fields.contains_key(*key))
+        .map(|(_, field)| field_ref(field))
+        .collect();
+    let fields_modified: Vec<FieldChange> = new_fields
+        .iter()
+        .filter_map(|(key, field)| {
+            let changes = field_changes(old_fields.get(key)?, field);
+            (!changes.is_empty()).then(|| FieldChange {
+                key: field.key.clone(),
+                label: field.label.clone(),
+                changes,
+            })
+        })
+        .collect();
+
+    // Section ids or titles can change without any field changing
+    let (old_layout, new_layout) = (layout_value(before), layout_value(after));
+    if old_layout != new_layout {
+        changes.push(PropertyChange {
+            property: "config.sections".to_string(),
+            before: old_layout,
+            after: new_layout,
+        });
+    }
+
+    if changes.is_empty()
+        && fields_added.is_empty()
+        && fields_removed.is_empty()
+        && fields_modified.is_empty()
+    {
+        return None;
+    }
+    Some(SymptomChange {
+        key: after.key.clone(),
+        name: after.name.clone(),
+        changes,
+        fields_added,
+        fields_removed,
+        fields_modified,
+    })
+}
+
+// Sections with their field keys, in order
+fn layout_value(template: &SymptomTemplate) -> Value {
+    template
+        .config
+        .sections
+        .iter()
+        .map(|s| {
+            serde_json::json!({
+                "id": s.id,
+                "title": s.title,
+                "fields": s.fields.iter().map(|f| &f.key).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+/// Symptom- and field-level differences between two template lists.
+///
+/// Symptoms are matched by `key` and fields by `key` within their symptom, so
+/// renaming a key shows up as a removal plus an addition.
+pub fn diff(
+    from: &str,
+    before: &[SymptomTemplate],
+    to: &str,
+    after: &[SymptomTemplate],
+) -> TemplateDiff {
+    let old: HashMap<&str, &SymptomTemplate> = before.iter().map(|t| (t.key.as_str(), t)).collect();
+    let new: HashMap<&str, &SymptomTemplate> = after.iter().map(|t| (t.key.as_str(), t)).collect();
+    let symptom_ref = |t: &SymptomTemplate| SymptomRef {
+        key: t.key.clone(),
+        name: t.name.clone(),
+    };
+
+    TemplateDiff {
+        from: from.to_string(),
+        to: to.to_string(),
+        added: after
+            .iter()
+            .filter(|t| !old.contains_key(t.key.as_str()))
+            .map(symptom_ref)
+            .collect(),
+        removed: before
+            .iter()
+            .filter(|t| !new.contains_key(t.key.as_str()))
+            .map(symptom_ref)
+            .collect(),
+        modified: after
+            .iter()
+            .filter_map(|t| symptom_change(old.get(t.key.as_str())?, t))
+            .collect(),
+    }
+}
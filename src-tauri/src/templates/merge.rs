@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::history::{symptom_change, SymptomChange, SymptomRef};
+use super::schema::SymptomTemplate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+    KeepLocal,
+    TakeIncoming,
+}
+
+// A symptom present on both sides where the incoming copy is not newer
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportConflict {
+    pub key: String,
+    pub name: String,
+    pub local_updated_at: i64,
+    pub incoming_updated_at: i64,
+    // What taking the incoming copy would change
+    pub changes: Option<SymptomChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    pub path: String,
+    // Hash of the previewed file; the apply step refuses a file that changed since
+    pub sha256: String,
+    // Keys not in the local set
+    pub added: Vec<SymptomRef>,
+    // Incoming copy is newer than the local one and will replace it
+    pub updated: Vec<SymptomRef>,
+    pub conflicts: Vec<ImportConflict>,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub added: usize,
+    pub updated: usize,
+    pub kept_local: usize,
+    pub took_incoming: usize,
+    pub unchanged: usize,
+    pub revision_id: Option<String>,
+}
+
+pub fn same_content(a: &SymptomTemplate, b: &SymptomTemplate) -> bool {
+    // Ids and timestamps differ between workstations even for identical templates
+    let normalized = |t: &SymptomTemplate| {
+        let mut value = serde_json::to_value(t).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            for key in ["id", "createdAt", "updatedAt"] {
+                object.remove(key);
+            }
+        }
+        value
+    };
+    normalized(a) == normalized(b)
+}
+
+enum Outcome<'a> {
+    Added(&'a SymptomTemplate),
+    Updated(&'a SymptomTemplate),
+    Conflict(&'a SymptomTemplate),
+    Unchanged,
+}
+
+fn classify<'a>(
+    local: &HashMap<&str, &SymptomTemplate>,
+    incoming: &'a SymptomTemplate,
+) -> Outcome<'a> {
+    match local.get(incoming.key.as_str()) {
+        None => Outcome::Added(incoming),
+        Some(existing) if same_content(existing, incoming) => Outcome::Unchanged,
+        Some(existing) if incoming.updated_at > existing.updated_at => Outcome::Updated(incoming),
+        Some(_) => Outcome::Conflict(incoming),
+    }
+}
+
+fn symptom_ref(t: &SymptomTemplate) -> SymptomRef {
+    SymptomRef {
+        key: t.key.clone(),
+        name: t.name.clone(),
+    }
+}
+
+/// Compare an exported template list against the local one by symptom `key`.
+///
+/// An incoming symptom with a later `updatedAt` is an update; one that differs
+/// but is not newer means the local copy was edited too, so it is reported as
+/// a conflict for the caller to resolve.
+pub fn preview(
+    path: &str,
+    sha256: &str,
+    local: &[SymptomTemplate],
+    incoming: &[SymptomTemplate],
+) -> ImportPreview {
+    let by_key: HashMap<&str, &SymptomTemplate> =
+        local.iter().map(|t| (t.key.as_str(), t)).collect();
+    let mut preview = ImportPreview {
+        path: path.to_string(),
+        sha256: sha256.to_string(),
+        added: Vec::new(),
+        updated: Vec::new(),
+        conflicts: Vec::new(),
+        unchanged: 0,
+    };
+
+    for template in incoming {
+        match classify(&by_key, template) {
+            Outcome::Added(t) => preview.added.push(symptom_ref(t)),
+            Outcome::Updated(t) => preview.updated.push(symptom_ref(t)),
+            Outcome::Conflict(t) => {
+                let existing = by_key[t.key.as_str()];
+                preview.conflicts.push(ImportConflict {
+                    key: t.key.clone(),
+                    name: t.name.clone(),
+                    local_updated_at: existing.updated_at,
+                    incoming_updated_at: t.updated_at,
+                    changes: symptom_change(existing, t),
+                });
+            }
+            Outcome::Unchanged => preview.unchanged += 1,
+        }
+    }
+    preview
+}
+
+/// Merge `incoming` into `local` and return the new list with a summary.
+///
+/// Replaced symptoms keep their local position and id; added ones go to the
+/// end, with a fresh id if theirs is already taken. Conflicts missing from
+/// `resolutions` keep the local copy.
+pub fn merge(
+    local: &[SymptomTemplate],
+    incoming: &[SymptomTemplate],
+    resolutions: &HashMap<String, ConflictResolution>,
+) -> (Vec<SymptomTemplate>, ImportResult) {
+    let by_key: HashMap<&str, &SymptomTemplate> =
+        local.iter().map(|t| (t.key.as_str(), t)).collect();
+    let mut result = ImportResult {
+        added: 0,
+        updated: 0,
+        kept_local: 0,
+        took_incoming: 0,
+        unchanged: 0,
+        revision_id: None,
+    };
+
+    let mut replacements: HashMap<&str, &SymptomTemplate> = HashMap::new();
+    let mut added = Vec::new();
+    for template in incoming {
+        match classify(&by_key, template) {
+            Outcome::Added(t) => {
+                added.push(t.clone());
+                result.added += 1;
+            }
+            Outcome::Updated(t) => {
+                replacements.insert(t.key.as_str(), t);
+                result.updated += 1;
+            }
+            Outcome::Conflict(t) => match resolutions.get(&t.key) {
+                Some(ConflictResolution::TakeIncoming) => {
+                    replacements.insert(t.key.as_str(), t);
+                    result.took_incoming += 1;
+                }
+                _ => result.kept_local += 1,
+            },
+            Outcome::Unchanged => result.unchanged += 1,
+        }
+    }
+
+    let mut merged: Vec<SymptomTemplate> = local
+        .iter()
+        .map(|existing| match replacements.get(existing.key.as_str()) {
+            Some(replacement) => SymptomTemplate {
+                id: existing.id.clone(),
+                created_at: existing.created_at,
+                ..(*replacement).clone()
+            },
+            None => existing.clone(),
+        })
+        .collect();
+
+    let mut ids: HashSet<String> = merged.iter().map(|t| t.id.clone()).collect();
+    for mut template in added {
+        if !ids.insert(template.id.clone()) {
+            template.id = Uuid::new_v4().to_string();
+            ids.insert(template.id.clone());
+        }
+        merged.push(template);
+    }
+
+    (merged, result)
+}
@@ -0,0 +1,15 @@
+pub mod applicability;
+pub mod bundle;
+pub mod error;
+pub mod history;
+pub mod merge;
+pub mod questionnaire;
+pub mod repository;
+pub mod schema;
+pub mod search;
+pub mod sheet;
+pub mod textgen;
+
+pub use error::{TemplateError, ValidationIssue};
+pub use repository::TemplateRepository;
+pub use schema::SymptomTemplate;
@@ -0,0 +1,172 @@
+use super::schema::{AgeUnit, ApplicablePopulation, Field, FieldType, SymptomTemplate};
+
+// Paper version of the templates: one block per symptom with boxes to tick
+// and lines to fill in. Self-contained HTML, printed (or saved as PDF) from
+// the webview or any browser.
+
+const STYLE: &str = r#"
+@page { size: A4; margin: 15mm; }
+body { font-family: "Microsoft YaHei", "PingFang SC", "Noto Sans CJK SC", sans-serif; font-size: 12pt; color: #111; margin: 0; }
+h1 { font-size: 18pt; text-align: center; margin: 0 0 8pt; }
+.patient { display: flex; gap: 16pt; justify-content: space-between; border-bottom: 1px solid #111; padding-bottom: 6pt; margin-bottom: 10pt; }
+.blank { display: inline-block; min-width: 60pt; border-bottom: 1px solid #111; }
+.blank.wide { min-width: 240pt; }
+.symptom { break-inside: avoid; page-break-inside: avoid; margin-bottom: 12pt; }
+.symptom h2 { font-size: 14pt; margin: 0 0 4pt; border-left: 4pt solid #111; padding-left: 6pt; }
+.symptom h3 { font-size: 12pt; margin: 6pt 0 2pt; }
+.note { font-size: 10pt; color: #555; font-weight: normal; }
+.field { margin: 4pt 0; line-height: 1.7; }
+.label { font-weight: bold; margin-right: 6pt; }
+.required { color: #b91c1c; }
+.choice { display: inline-block; margin-right: 12pt; white-space: nowrap; }
+"#;
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn age_text(value: f64, unit: AgeUnit) -> String {
+    let unit = match unit {
+        AgeUnit::Years => "岁",
+        AgeUnit::Months => "个月",
+        AgeUnit::Days => "天",
+    };
+    format!("{}{}", value, unit)
+}
+
+// "（仅女性，12岁至60岁）"; empty when the population is unrestricted
+fn population_note(population: Option<&ApplicablePopulation>) -> String {
+    let Some(population) = population else {
+        return String::new();
+    };
+    let mut parts = Vec::new();
+
+    let genders: Vec<&str> = population
+        .genders
+        .iter()
+        .filter_map(|g| match g.as_str() {
+            "1" => Some("男性"),
+            "2" => Some("女性"),
+            _ => None,
+        })
+        .collect();
+    if genders.len() == 1 {
+        parts.push(format!("仅{}", genders[0]));
+    }
+
+    if let Some(range) = &population.age_range {
+        let unit = range.unit.unwrap_or(AgeUnit::Years);
+        match (range.min, range.max) {
+            (Some(min), Some(max)) => {
+                parts.push(format!("{}至{}", age_text(min, unit), age_text(max, unit)))
+            }
+            (Some(min), None) => parts.push(format!("{}及以上", age_text(min, unit))),
+            (None, Some(max)) => parts.push(format!("{}及以下", age_text(max, unit))),
+            (None, None) => {}
+        }
+    }
+
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " <span class=\"note\">（{}）</span>",
+            escape(&parts.join("，"))
+        )
+    }
+}
+
+fn choices(options: &[String], mark: &str) -> String {
+    options
+        .iter()
+        .map(|o| format!("<span class=\"choice\">{} {}</span>", mark, escape(o)))
+        .collect()
+}
+
+fn render_field(field: &Field, html: &mut String) {
+    let required = if field.required == Some(true) {
+        "<span class=\"required\">*</span>"
+    } else {
+        ""
+    };
+    html.push_str(&format!(
+        "<div class=\"field\"><span class=\"label\">{}{}</span>",
+        escape(&field.label),
+        required
+    ));
+
+    let unit = field
+        .props
+        .unit
+        .as_deref()
+        .map(|u| format!(" {}", escape(u)))
+        .unwrap_or_default();
+    let answer = match field.field_type {
+        FieldType::Radio => choices(field.choices(), "○"),
+        FieldType::Checkbox => format!(
+            "{} <span class=\"note\">（可多选）</span>",
+            choices(field.choices(), "□")
+        ),
+        FieldType::InputRadio => format!(
+            "<span class=\"blank\"></span> {}",
+            choices(field.choices(), "○")
+        ),
+        FieldType::Number => format!("<span class=\"blank\"></span>{}", unit),
+        FieldType::Input => "<span class=\"blank wide\"></span>".to_string(),
+    };
+    html.push_str(&answer);
+    html.push_str(&population_note(field.applicable_population.as_ref()));
+    html.push_str("</div>\n");
+}
+
+/// Render the templates as a printable questionnaire.
+pub fn render_html(templates: &[SymptomTemplate], title: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(title)));
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str(&format!("<h1>{}</h1>\n", escape(title)));
+    html.push_str(
+        "<div class=\"patient\">\
+         <span>姓名 <span class=\"blank\"></span></span>\
+         <span>性别 <span class=\"blank\"></span></span>\
+         <span>年龄 <span class=\"blank\"></span></span>\
+         <span>日期 <span class=\"blank\"></span></span>\
+         </div>\n",
+    );
+
+    for symptom in templates {
+        html.push_str("<section class=\"symptom\">\n");
+        html.push_str(&format!(
+            "<h2>{}{}</h2>\n",
+            escape(&symptom.name),
+            population_note(Some(&symptom.applicable_population))
+        ));
+        let multiple_sections = symptom.config.sections.len() > 1;
+        for section in &symptom.config.sections {
+            let note = population_note(section.applicable_population.as_ref());
+            // The title of a single section (usually 症状属性问诊) adds nothing on paper
+            if (multiple_sections && !section.title.is_empty()) || !note.is_empty() {
+                html.push_str(&format!("<h3>{}{}</h3>\n", escape(&section.title), note));
+            }
+            for field in &section.fields {
+                render_field(field, &mut html);
+            }
+        }
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
@@ -0,0 +1,342 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use tauri::{AppHandle, Manager};
+
+use super::bundle::{BundleInfo, TemplateBundle};
+use super::error::TemplateError;
+use super::history::{self, RevisionInfo, TemplateHistory};
+use super::merge::same_content;
+use super::schema::{self, SymptomTemplate};
+use super::search::SearchIndex;
+
+// Symptom templates shipped with the app; seeds the user's copy on first run
+const BUNDLED_TEMPLATES: &str = include_str!("../../../src/assets/templates.json");
+const TEMPLATES_FILE: &str = "templates.json";
+
+/// The user's symptom templates, kept in the app data dir.
+///
+/// The file is created from the bundled `templates.json` the first time it is
+/// needed and every save replaces it atomically, so a crash mid-write never
+/// leaves the consultation UI with a truncated file. Every save is also kept
+/// as a revision in `history`.
+///
+/// With a department bundle subscribed the file only holds the doctor's
+/// overrides and additions, layered over the bundle by symptom `key`; the
+/// bundle itself is read-only.
+pub struct TemplateRepository {
+    path: PathBuf,
+    history: TemplateHistory,
+    base: RwLock<Option<TemplateBundle>>,
+    // Serialises writes, and their revisions, from concurrent commands. Held
+    // across each read-compute-write and always taken before `base`.
+    write_lock: Mutex<()>,
+    // Built on the first search, dropped whenever the templates change
+    index: RwLock<Option<Arc<SearchIndex>>>,
+}
+
+// Write to a sibling temp file, flush it to disk, then rename over the target
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(format!("Failed to write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+pub fn bundled_templates() -> Result<Vec<SymptomTemplate>, TemplateError> {
+    schema::parse(BUNDLED_TEMPLATES)
+}
+
+// Base symptoms in bundle order, each replaced by the local copy with the same
+// key, followed by symptoms that only exist locally
+fn layer(base: &[SymptomTemplate], local: &[SymptomTemplate]) -> Vec<SymptomTemplate> {
+    let overrides: HashMap<&str, &SymptomTemplate> =
+        local.iter().map(|t| (t.key.as_str(), t)).collect();
+    let base_keys: HashSet<&str> = base.iter().map(|t| t.key.as_str()).collect();
+
+    base.iter()
+        .map(|t| (*overrides.get(t.key.as_str()).unwrap_or(&t)).clone())
+        .chain(
+            local
+                .iter()
+                .filter(|t| !base_keys.contains(t.key.as_str()))
+                .cloned(),
+        )
+        .collect()
+}
+
+// The part of `templates` the local file has to keep under `base`. Copies that
+// match the base, or that were seeded from the app's templates and never
+// edited, are dropped so the bundle shows through. Seeded copies of symptoms
+// the bundle does not cover stay; nothing else would provide them.
+fn overrides(
+    base: &[SymptomTemplate],
+    templates: &[SymptomTemplate],
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let seed = bundled_templates()?;
+    let seed: HashMap<&str, &SymptomTemplate> = seed.iter().map(|t| (t.key.as_str(), t)).collect();
+    let base: HashMap<&str, &SymptomTemplate> = base.iter().map(|t| (t.key.as_str(), t)).collect();
+
+    let unchanged = |t: &SymptomTemplate, from: &HashMap<&str, &SymptomTemplate>| {
+        from.get(t.key.as_str())
+            .is_some_and(|original| same_content(original, t))
+    };
+    let shows_through = |t: &SymptomTemplate| {
+        unchanged(t, &base) || (base.contains_key(t.key.as_str()) && unchanged(t, &seed))
+    };
+    Ok(templates
+        .iter()
+        .filter(|t| !shows_through(t))
+        .cloned()
+        .collect())
+}
+
+impl TemplateRepository {
+    pub fn new(dir: &Path) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let repository = TemplateRepository {
+            path: dir.join(TEMPLATES_FILE),
+            history: TemplateHistory::new(history::revisions_dir(dir))?,
+            base: RwLock::new(None),
+            write_lock: Mutex::new(()),
+            index: RwLock::new(None),
+        };
+        if !repository.path.exists() {
+            repository.write_file(&bundled_templates().map_err(String::from)?)?;
+            println!(
+                "[Templates] Seeded {:?} from bundled templates",
+                repository.path
+            );
+        }
+        Ok(repository)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn history(&self) -> &TemplateHistory {
+        &self.history
+    }
+
+    // The file as stored: the full list, or only overrides when subscribed
+    pub fn load_local(&self) -> Result<Vec<SymptomTemplate>, TemplateError> {
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        schema::parse(&content)
+    }
+
+    // The templates in effect, with the subscribed bundle layered underneath
+    pub fn load(&self) -> Result<Vec<SymptomTemplate>, TemplateError> {
+        let local = self.load_local()?;
+        let base = self.base.read().map_err(|e| e.to_string())?;
+        Ok(match base.as_ref() {
+            Some(bundle) => layer(&bundle.templates, &local),
+            None => local,
+        })
+    }
+
+    pub fn base_info(&self) -> Option<BundleInfo> {
+        let base = self.base.read().ok()?;
+        base.as_ref().map(|bundle| bundle.info.clone())
+    }
+
+    /// Put `bundle` under the local templates.
+    ///
+    /// The local file is reduced to the doctor's own overrides right away, so
+    /// unedited copies do not hide later bundle updates.
+    pub fn set_base(&self, bundle: TemplateBundle) -> Result<(), TemplateError> {
+        let writes = self.lock_writes()?;
+        let local = self.load_local()?;
+        let reduced = overrides(&bundle.templates, &local)?;
+        let note = format!("Layered over template bundle {}", bundle.info.version);
+        *self.base.write().map_err(|e| e.to_string())? = Some(bundle);
+        self.write(&writes, &reduced, "system", &note)?;
+        Ok(())
+    }
+
+    /// Drop the bundle, writing the layered result back as the full local
+    /// list so no template disappears with the subscription.
+    pub fn clear_base(&self) -> Result<(), TemplateError> {
+        let writes = self.lock_writes()?;
+        let Some(bundle) = self.base.write().map_err(|e| e.to_string())?.take() else {
+            return Ok(());
+        };
+        let flattened = layer(&bundle.templates, &self.load_local()?);
+        let note = format!("Unsubscribed from template bundle {}", bundle.info.version);
+        self.write(&writes, &flattened, "system", &note)?;
+        Ok(())
+    }
+
+    // Search index over the templates in effect
+    pub fn search_index(&self) -> Result<Arc<SearchIndex>, TemplateError> {
+        if let Some(index) = self.index.read().map_err(|e| e.to_string())?.as_ref() {
+            return Ok(index.clone());
+        }
+        let index = Arc::new(SearchIndex::build(&self.load()?));
+        *self.index.write().map_err(|e| e.to_string())? = Some(index.clone());
+        Ok(index)
+    }
+
+    fn write_file(&self, templates: &[SymptomTemplate]) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(templates).map_err(|e| e.to_string())?;
+        write_atomic(&self.path, content.as_bytes())
+    }
+
+    fn lock_writes(&self) -> Result<MutexGuard<'_, ()>, TemplateError> {
+        Ok(self.write_lock.lock().map_err(|e| e.to_string())?)
+    }
+
+    // Replace the file and record the new content as a revision; the caller
+    // holds the write lock from before it read what `templates` was built from
+    fn write(
+        &self,
+        _writes: &MutexGuard<'_, ()>,
+        templates: &[SymptomTemplate],
+        author: &str,
+        note: &str,
+    ) -> Result<Option<RevisionInfo>, TemplateError> {
+        // Saves made before history existed have no revision; keep what is on
+        // disk as the baseline so the first tracked save can be undone
+        if self.history.list()?.is_empty() {
+            if let Ok(current) = self.load_local() {
+                self.history
+                    .record(&current, "system", "Before first tracked save")?;
+            }
+        }
+
+        *self.index.write().map_err(|e| e.to_string())? = None;
+        self.write_file(templates)?;
+        let revision = self.history.record(templates, author, note)?;
+        if let Some(revision) = &revision {
+            println!(
+                "[Templates] Recorded revision {} by {}",
+                revision.id, revision.author
+            );
+        }
+        Ok(revision)
+    }
+
+    /// Validate and store the full list. Nothing reaches disk unless every
+    /// template passes; returns the new revision, or `None` if nothing changed.
+    pub fn save(
+        &self,
+        templates: &[SymptomTemplate],
+        author: &str,
+        note: &str,
+    ) -> Result<Option<RevisionInfo>, TemplateError> {
+        let issues = schema::validate(templates);
+        if !issues.is_empty() {
+            return Err(TemplateError::Invalid(issues));
+        }
+        let writes = self.lock_writes()?;
+        let base = self.base.read().map_err(|e| e.to_string())?;
+        match base.as_ref() {
+            // Bundle symptoms left out of `templates` come back: the base is read-only
+            Some(bundle) => {
+                let reduced = overrides(&bundle.templates, templates)?;
+                self.write(&writes, &reduced, author, note)
+            }
+            None => self.write(&writes, templates, author, note),
+        }
+    }
+
+    pub fn save_str(
+        &self,
+        content: &str,
+        author: &str,
+        note: &str,
+    ) -> Result<Option<RevisionInfo>, TemplateError> {
+        self.save(&schema::parse(content)?, author, note)
+    }
+
+    // Discard the user's edits and go back to the bundled templates, or to the
+    // subscribed bundle plus the bundled symptoms it does not cover
+    pub fn reset(&self, author: &str) -> Result<Vec<SymptomTemplate>, TemplateError> {
+        let templates = bundled_templates()?;
+        self.save(&templates, author, "Reset to bundled templates")?;
+        println!("[Templates] Reset {:?} to bundled templates", self.path);
+        self.load()
+    }
+
+    // Make an earlier revision current again; this adds a new revision, so
+    // the restore itself can be undone
+    pub fn restore(
+        &self,
+        revision_id: &str,
+        author: &str,
+    ) -> Result<Vec<SymptomTemplate>, TemplateError> {
+        let info = self.history.info(revision_id)?;
+        let templates = self.history.load(revision_id)?;
+        let note = format!(
+            "Restored revision {} ({})",
+            info.id,
+            if info.note.is_empty() {
+                &info.author
+            } else {
+                &info.note
+            }
+        );
+        // A revision holds what the file held; layer it like the file itself
+        let templates = match self.base.read().map_err(|e| e.to_string())?.as_ref() {
+            Some(bundle) => layer(&bundle.templates, &templates),
+            None => templates,
+        };
+        self.save(&templates, author, &note)?;
+        println!("[Templates] Restored revision {}", revision_id);
+        self.load()
+    }
+}
+
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let repository = TemplateRepository::new(&app_data_dir)?;
+    println!("[Templates] Using {:?}", repository.path());
+    app.manage(repository);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(templates: &[SymptomTemplate]) -> Vec<&str> {
+        templates.iter().map(|t| t.key.as_str()).collect()
+    }
+
+    #[test]
+    fn overrides_keep_seeded_symptoms_the_bundle_does_not_cover() {
+        let seed = bundled_templates().unwrap();
+        let base = vec![seed[0].clone()];
+
+        let kept = overrides(&base, &seed).unwrap();
+        assert_eq!(keys(&kept), keys(&seed[1..]));
+
+        // Layering the kept copies back over the bundle loses nothing
+        assert_eq!(keys(&layer(&base, &kept)), keys(&seed));
+    }
+
+    #[test]
+    fn overrides_keep_edited_copies() {
+        let seed = bundled_templates().unwrap();
+        let base = seed.clone();
+        let mut edited = seed.clone();
+        edited[1].name = format!("{}（修改）", edited[1].name);
+
+        let kept = overrides(&base, &edited).unwrap();
+        assert_eq!(keys(&kept), vec![seed[1].key.as_str()]);
+    }
+}
@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::error::{TemplateError, ValidationIssue};
+
+// Every struct keeps keys it does not know in `extra`, so a save through the
+// typed model never drops data written by a newer editor.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomTemplate {
+    pub id: String,
+    pub key: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub is_common_symptom: bool,
+    #[serde(default)]
+    pub system_category: Vec<String>,
+    #[serde(default)]
+    pub body_parts: Vec<String>,
+    pub config: TemplateConfig,
+    #[serde(default)]
+    pub applicable_population: ApplicablePopulation,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub sections: Vec<Section>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Section {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    pub fields: Vec<Field>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applicable_population: Option<ApplicablePopulation>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Radio,
+    Checkbox,
+    // Free input plus a unit picked from `radioOptions`, e.g. "3" + "天"
+    InputRadio,
+    Number,
+    // Free text
+    Input,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Field {
+    pub id: String,
+    pub key: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub props: FieldProps,
+    pub storage_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub applicable_population: Option<ApplicablePopulation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_gen_config: Option<TextGenConfig>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldProps {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radio_options: Option<Vec<String>>,
+    // Groups of options; options from different groups cannot be checked together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutual_exclusions: Option<Vec<Vec<String>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Field {
+    // The choices offered by the field: `radioOptions` for input_radio, `options` otherwise
+    pub fn choices(&self) -> &[String] {
+        let choices = match self.field_type {
+            FieldType::InputRadio => &self.props.radio_options,
+            _ => &self.props.options,
+        };
+        choices.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgeUnit {
+    #[serde(rename = "Y")]
+    Years,
+    #[serde(rename = "M")]
+    Months,
+    #[serde(rename = "D")]
+    Days,
+}
+
+impl AgeUnit {
+    // Same approximation as the frontend: a year is 365 days, a month 30
+    pub fn to_days(self, value: f64) -> f64 {
+        match self {
+            AgeUnit::Years => value * 365.0,
+            AgeUnit::Months => value * 30.0,
+            AgeUnit::Days => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgeRange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    // Defaults to years
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<AgeUnit>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplicablePopulation {
+    // HIS sex codes: "1" male, "2" female; empty means everyone
+    #[serde(default)]
+    pub genders: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_range: Option<AgeRange>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextTarget {
+    ChiefComplaint,
+    HistoryOfPresentIllness,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptionConfig {
+    // Values left out of the generated text
+    #[serde(default)]
+    pub ignore_values: Vec<String>,
+    // Replacement wording per value
+    #[serde(default)]
+    pub value_map: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextGenConfig {
+    pub targets: Vec<TextTarget>,
+    // `{value}` is replaced by the answer, `{label}` by the field label
+    pub template: String,
+    // Joins multiple values; "、" when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub option_config: Option<OptionConfig>,
+}
+
+const GENDER_CODES: [&str; 2] = ["1", "2"];
+// Oldest age accepted in a range, in days
+const MAX_AGE_DAYS: f64 = 150.0 * 365.0;
+
+/// Parse the JSON text of a template list.
+///
+/// Structural errors point at the smallest part that fails to decode, e.g.
+/// an unknown field type is reported at `$[12].config.sections[0].fields[3]`.
+pub fn parse(content: &str) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let value: Value = serde_json::from_str(content).map_err(|e| {
+        TemplateError::Parse(ValidationIssue {
+            path: "$".to_string(),
+            message: e.to_string(),
+        })
+    })?;
+    from_value(value)
+}
+
+pub fn from_value(value: Value) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let Value::Array(items) = value else {
+        return Err(TemplateError::Parse(ValidationIssue {
+            path: "$".to_string(),
+            message: "templates must be a JSON array".to_string(),
+        }));
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            SymptomTemplate::deserialize(&item).map_err(|e| {
+                let (path, message) =
+                    locate_error(&item).unwrap_or_else(|| (String::new(), e.to_string()));
+                TemplateError::Parse(ValidationIssue {
+                    path: format!("$[{}]{}", i, path),
+                    message,
+                })
+            })
+        })
+        .collect()
+}
+
+// serde_json reports no path when decoding from a Value, so retry the
+// sections and fields one by one to find the part that failed
+fn locate_error(item: &Value) -> Option<(String, String)> {
+    let sections = item.pointer("/config/sections")?.as_array()?;
+    for (s, section) in sections.iter().enumerate() {
+        for (f, field) in section.get("fields")?.as_array()?.iter().enumerate() {
+            if let Err(e) = Field::deserialize(field) {
+                let path = format!(".config.sections[{}].fields[{}]", s, f);
+                return Some((path, e.to_string()));
+            }
+        }
+        if let Err(e) = Section::deserialize(section) {
+            return Some((format!(".config.sections[{}]", s), e.to_string()));
+        }
+    }
+    None
+}
+
+struct Validator {
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    fn issue(&mut self, path: &str, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn required(&mut self, path: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.issue(path, "must not be empty");
+        }
+    }
+
+    // Record `value` under `scope`, reporting a duplicate with the first path it was seen at
+    fn unique<'a>(&mut self, seen: &mut HashMap<&'a str, String>, value: &'a str, path: String) {
+        if value.is_empty() {
+            return;
+        }
+        match seen.get(value) {
+            Some(first) => {
+                let message = format!("duplicate '{}', already used at {}", value, first);
+                self.issue(&path, message);
+            }
+            None => {
+                seen.insert(value, path);
+            }
+        }
+    }
+
+    fn population(&mut self, path: &str, population: &ApplicablePopulation) {
+        for (i, gender) in population.genders.iter().enumerate() {
+            if !GENDER_CODES.contains(&gender.as_str()) {
+                self.issue(
+                    &format!("{}.genders[{}]", path, i),
+                    format!("unknown gender code '{}', expected \"1\" or \"2\"", gender),
+                );
+            }
+        }
+
+        let Some(range) = &population.age_range else {
+            return;
+        };
+        let path = format!("{}.ageRange", path);
+        let unit = range.unit.unwrap_or(AgeUnit::Years);
+        for (name, value) in [("min", range.min), ("max", range.max)] {
+            match value {
+                Some(v) if !v.is_finite() || v < 0.0 => {
+                    self.issue(&format!("{}.{}", path, name), "must be zero or more")
+                }
+                Some(v) if unit.to_days(v) > MAX_AGE_DAYS => {
+                    self.issue(&format!("{}.{}", path, name), "exceeds 150 years")
+                }
+                _ => {}
+            }
+        }
+        match (range.min, range.max) {
+            (Some(min), Some(max)) if min > max => {
+                self.issue(&path, format!("min {} is greater than max {}", min, max))
+            }
+            (None, None) => self.issue(&path, "needs min, max or both"),
+            _ => {}
+        }
+    }
+
+    fn text_gen(&mut self, path: &str, config: &TextGenConfig) {
+        if config.targets.is_empty() {
+            self.issue(
+                &format!("{}.targets", path),
+                "must name at least one target",
+            );
+        }
+        if !config.template.contains("{value}") {
+            self.issue(&format!("{}.template", path), "must contain {value}");
+        }
+    }
+
+    fn field(&mut self, path: &str, field: &Field) {
+        self.required(&format!("{}.id", path), &field.id);
+        self.required(&format!("{}.key", path), &field.key);
+        self.required(&format!("{}.storageKey", path), &field.storage_key);
+
+        let choices_key = match field.field_type {
+            FieldType::InputRadio => "radioOptions",
+            _ => "options",
+        };
+        let choices = field.choices();
+        let free_form = matches!(field.field_type, FieldType::Number | FieldType::Input);
+        if !free_form && choices.is_empty() {
+            self.issue(
+                &format!("{}.props.{}", path, choices_key),
+                "needs at least one option",
+            );
+        }
+        let mut seen = HashMap::new();
+        for (i, choice) in choices.iter().enumerate() {
+            let choice_path = format!("{}.props.{}[{}]", path, choices_key, i);
+            self.required(&choice_path, choice);
+            self.unique(&mut seen, choice, choice_path);
+        }
+
+        if let Some(groups) = &field.props.mutual_exclusions {
+            let groups_path = format!("{}.props.mutualExclusions", path);
+            if field.field_type != FieldType::Checkbox && groups.iter().any(|g| !g.is_empty()) {
+                self.issue(
+                    &groups_path,
+                    "only checkbox fields can have mutual exclusions",
+                );
+            }
+            let options: HashSet<&str> = choices.iter().map(String::as_str).collect();
+            for (g, group) in groups.iter().enumerate() {
+                // An option may sit in several groups, but only once per group
+                let mut grouped = HashMap::new();
+                for (i, option) in group.iter().enumerate() {
+                    let option_path = format!("{}[{}][{}]", groups_path, g, i);
+                    if !options.contains(option.as_str()) {
+                        self.issue(
+                            &option_path,
+                            format!("'{}' is not one of the field's options", option),
+                        );
+                    }
+                    self.unique(&mut grouped, option, option_path);
+                }
+            }
+        }
+
+        if let Some(population) = &field.applicable_population {
+            self.population(&format!("{}.applicablePopulation", path), population);
+        }
+        if let Some(config) = &field.text_gen_config {
+            self.text_gen(&format!("{}.textGenConfig", path), config);
+        }
+    }
+
+    fn template(&mut self, path: &str, template: &SymptomTemplate) {
+        self.required(&format!("{}.name", path), &template.name);
+        self.population(
+            &format!("{}.applicablePopulation", path),
+            &template.applicable_population,
+        );
+
+        let sections_path = format!("{}.config.sections", path);
+        if template.config.sections.is_empty() {
+            self.issue(&sections_path, "needs at least one section");
+        }
+
+        // Answers are stored per symptom under storageKey, so keys only need
+        // to be unique within one template
+        let mut section_ids = HashMap::new();
+        let mut field_ids = HashMap::new();
+        let mut field_keys = HashMap::new();
+        let mut storage_keys = HashMap::new();
+        for (s, section) in template.config.sections.iter().enumerate() {
+            let section_path = format!("{}[{}]", sections_path, s);
+            self.required(&format!("{}.id", section_path), &section.id);
+            self.unique(
+                &mut section_ids,
+                &section.id,
+                format!("{}.id", section_path),
+            );
+            if let Some(population) = &section.applicable_population {
+                self.population(
+                    &format!("{}.applicablePopulation", section_path),
+                    population,
+                );
+            }
+
+            for (f, field) in section.fields.iter().enumerate() {
+                let field_path = format!("{}.fields[{}]", section_path, f);
+                self.field(&field_path, field);
+                self.unique(&mut field_ids, &field.id, format!("{}.id", field_path));
+                self.unique(&mut field_keys, &field.key, format!("{}.key", field_path));
+                self.unique(
+                    &mut storage_keys,
+                    &field.storage_key,
+                    format!("{}.storageKey", field_path),
+                );
+            }
+        }
+    }
+}
+
+/// Check the templates for problems serde cannot catch; empty when valid.
+///
+/// Symptom ids and keys must be unique across the list, field keys and
+/// storage keys within their symptom. Mutual exclusion groups may only name
+/// the field's own options, and age ranges must be ordered and plausible.
+pub fn validate(templates: &[SymptomTemplate]) -> Vec<ValidationIssue> {
+    let mut validator = Validator { issues: Vec::new() };
+    let mut ids = HashMap::new();
+    let mut keys = HashMap::new();
+
+    for (i, template) in templates.iter().enumerate() {
+        let path = format!("$[{}]", i);
+        validator.required(&format!("{}.id", path), &template.id);
+        validator.required(&format!("{}.key", path), &template.key);
+        validator.unique(&mut ids, &template.id, format!("{}.id", path));
+        validator.unique(&mut keys, &template.key, format!("{}.key", path));
+        validator.template(&path, template);
+    }
+
+    validator.issues
+}
@@ -0,0 +1,290 @@
+use pinyin::ToPinyin;
+use serde::{Deserialize, Serialize};
+
+use super::applicability::{symptom_applies, PatientProfile};
+use super::schema::SymptomTemplate;
+
+const DEFAULT_LIMIT: usize = 20;
+
+// Chinese labels of the systemCategory and bodyParts codes, as the UI shows them
+const SYSTEM_CATEGORY_LABELS: [(&str, &str); 9] = [
+    ("respiratory", "呼吸系统"),
+    ("circulatory", "循环系统"),
+    ("endocrine", "内分泌系统"),
+    ("digestive", "消化系统"),
+    ("urinary", "泌尿系统"),
+    ("reproductive", "生殖系统"),
+    ("nervous", "神经系统"),
+    ("motor", "运动系统"),
+    ("other", "其他"),
+];
+const BODY_PART_LABELS: [(&str, &str); 9] = [
+    ("head", "头部"),
+    ("neck", "颈部"),
+    ("chest", "胸部"),
+    ("abdomen", "腹部"),
+    ("back", "背部"),
+    ("buttock", "臀部"),
+    ("genital", "生殖器"),
+    ("upper_limb", "上肢"),
+    ("lower_limb", "下肢"),
+];
+
+// Separators in typed queries and HIS chief complaints ("发热，咳嗽3天")
+const QUERY_SEPARATORS: &str = "，。、；：！？（）【】“”‘’《》";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchField {
+    Name,
+    Key,
+    SystemCategory,
+    BodyPart,
+    Option,
+}
+
+impl MatchField {
+    fn weight(self) -> f64 {
+        match self {
+            MatchField::Name => 100.0,
+            MatchField::Key => 80.0,
+            MatchField::BodyPart => 50.0,
+            MatchField::SystemCategory => 40.0,
+            MatchField::Option => 20.0,
+        }
+    }
+}
+
+// One searchable text of a symptom with its romanizations, all lowercase
+struct Term {
+    field: MatchField,
+    label: String,
+    text: String,
+    pinyin: String,
+    initials: String,
+}
+
+struct Entry {
+    symptom: SymptomTemplate,
+    terms: Vec<Term>,
+}
+
+// A code and, when known, its label
+fn coded_terms(field: MatchField, code: &str, labels: &[(&str, &str)]) -> Vec<Term> {
+    let mut terms = vec![Term::new(field, code)];
+    terms.extend(
+        labels
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, label)| Term::new(field, label)),
+    );
+    terms
+}
+
+/// Full pinyin and initials of `text`: "发热" gives ("fare", "fr"). Letters
+/// and digits are kept as they are, so "B超" gives ("bchao", "bc").
+pub fn romanize(text: &str) -> (String, String) {
+    let mut full = String::new();
+    let mut initials = String::new();
+    for (c, pinyin) in text.chars().zip(text.to_pinyin()) {
+        match pinyin {
+            Some(pinyin) => {
+                full.push_str(pinyin.plain());
+                initials.push_str(pinyin.first_letter());
+            }
+            None if c.is_alphanumeric() => {
+                full.extend(c.to_lowercase());
+                initials.extend(c.to_lowercase());
+            }
+            None => {}
+        }
+    }
+    (full, initials)
+}
+
+impl Term {
+    fn new(field: MatchField, label: &str) -> Self {
+        let (pinyin, initials) = romanize(label);
+        Term {
+            field,
+            label: label.to_string(),
+            text: label.to_lowercase(),
+            pinyin,
+            initials,
+        }
+    }
+
+    // How well `token` matches, from 0 (not at all) to 1 (exactly)
+    fn quality(&self, token: &str) -> f64 {
+        if self.text == token {
+            return 1.0;
+        }
+        // A chief complaint names the symptom among other words: "发热3天伴咳嗽"
+        if self.field == MatchField::Name
+            && self.text.chars().count() >= 2
+            && token.contains(&self.text)
+        {
+            return 0.9;
+        }
+        if self.text.starts_with(token) {
+            return 0.8;
+        }
+        if self.text.contains(token) {
+            return 0.6;
+        }
+
+        if !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return 0.0;
+        }
+        if self.pinyin == token || self.initials == token {
+            0.75
+        } else if self.pinyin.starts_with(token) {
+            0.7
+        } else if self.initials.starts_with(token) {
+            0.65
+        } else if self.pinyin.contains(token) {
+            0.5
+        } else if token.len() >= 2 && self.initials.contains(token) {
+            0.45
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomSearchRequest {
+    // Typed text, pinyin or initials, or a whole chief complaint
+    pub query: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    // Leave out symptoms that do not apply to the patient
+    #[serde(default)]
+    pub patient: Option<PatientProfile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomMatch {
+    pub key: String,
+    pub name: String,
+    pub system_category: Vec<String>,
+    pub score: f64,
+    // The text that contributed most to the score
+    pub matched_field: MatchField,
+    pub matched_text: String,
+}
+
+/// Symptom lookup over name, key, system category, body parts and option
+/// labels, in Chinese, full pinyin or pinyin initials.
+///
+/// The query is split on whitespace and punctuation; each part adds the score
+/// of its best match in a symptom, weighted by where it matched (name before
+/// key before body part, system category and option labels).
+pub struct SearchIndex {
+    entries: Vec<Entry>,
+}
+
+impl SearchIndex {
+    pub fn build(templates: &[SymptomTemplate]) -> Self {
+        let entries = templates
+            .iter()
+            .map(|symptom| {
+                let mut terms = vec![
+                    Term::new(MatchField::Name, &symptom.name),
+                    Term::new(MatchField::Key, &symptom.key),
+                ];
+                for category in &symptom.system_category {
+                    terms.extend(coded_terms(
+                        MatchField::SystemCategory,
+                        category,
+                        &SYSTEM_CATEGORY_LABELS,
+                    ));
+                }
+                for part in &symptom.body_parts {
+                    terms.extend(coded_terms(MatchField::BodyPart, part, &BODY_PART_LABELS));
+                }
+
+                let mut options: Vec<&String> = symptom
+                    .config
+                    .sections
+                    .iter()
+                    .flat_map(|section| &section.fields)
+                    .flat_map(|field| field.choices())
+                    .collect();
+                options.sort();
+                options.dedup();
+                terms.extend(
+                    options
+                        .into_iter()
+                        .map(|o| Term::new(MatchField::Option, o)),
+                );
+
+                Entry {
+                    symptom: symptom.clone(),
+                    terms,
+                }
+            })
+            .collect();
+        SearchIndex { entries }
+    }
+
+    /// Matching symptoms, best first; ties keep template order.
+    pub fn search(&self, request: &SymptomSearchRequest) -> Vec<SymptomMatch> {
+        let query = request.query.to_lowercase();
+        let tokens: Vec<&str> = query
+            .split(|c: char| {
+                c.is_whitespace() || c.is_ascii_punctuation() || QUERY_SEPARATORS.contains(c)
+            })
+            .filter(|t| !t.is_empty())
+            .collect();
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<SymptomMatch> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                request
+                    .patient
+                    .as_ref()
+                    .is_none_or(|patient| symptom_applies(&entry.symptom, patient))
+            })
+            .filter_map(|entry| {
+                let mut score = 0.0;
+                let mut best: Option<(f64, &Term)> = None;
+                for token in &tokens {
+                    let Some((token_score, term)) = entry
+                        .terms
+                        .iter()
+                        .map(|term| (term.field.weight() * term.quality(token), term))
+                        .filter(|(s, _)| *s > 0.0)
+                        .max_by(|a, b| a.0.total_cmp(&b.0))
+                    else {
+                        continue;
+                    };
+                    score += token_score;
+                    if best.is_none_or(|(s, _)| token_score > s) {
+                        best = Some((token_score, term));
+                    }
+                }
+                let (_, term) = best?;
+                Some(SymptomMatch {
+                    key: entry.symptom.key.clone(),
+                    name: entry.symptom.name.clone(),
+                    system_category: entry.symptom.system_category.clone(),
+                    score,
+                    matched_field: term.field,
+                    matched_text: term.label.clone(),
+                })
+            })
+            .collect();
+
+        // Stable, so equal scores stay in template order
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(request.limit.unwrap_or(DEFAULT_LIMIT));
+        matches
+    }
+}
@@ -0,0 +1,895 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::applicability::parse_sex;
+use super::error::{TemplateError, ValidationIssue};
+use super::schema::{self, SymptomTemplate};
+
+// Flattened spreadsheet form of the templates, one row per field.
+//
+// Symptom and section columns repeat on every row of the symptom; on import
+// the first row of a symptom (or section) wins. Whatever a column cannot
+// carry exactly stays in the trailing JSON columns, so export followed by
+// import gives back the same templates.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SheetFormat {
+    Csv,
+    Xlsx,
+}
+
+impl SheetFormat {
+    pub fn from_path(path: &Path) -> Option<SheetFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(SheetFormat::Csv),
+            "xlsx" => Some(SheetFormat::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+const LIST_SEPARATOR: char = '|';
+const GROUP_SEPARATOR: char = ';';
+const SHEET_NAME: &str = "症状模板";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Symptom,
+    Section,
+    Field,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Text,
+    // 是 / 否
+    Flag,
+    Integer,
+    // a|b|c
+    List,
+    // a|b;c
+    Groups,
+    // 男|女
+    Genders,
+    // 12-60岁, 6-月, -28天
+    AgeRange,
+    Json,
+}
+
+struct Column {
+    header: &'static str,
+    level: Level,
+    path: &'static [&'static str],
+    kind: Kind,
+    // An empty cell stands for the serde default ("", [], false, 0)
+    defaulted: bool,
+}
+
+const fn column(
+    header: &'static str,
+    level: Level,
+    path: &'static [&'static str],
+    kind: Kind,
+    defaulted: bool,
+) -> Column {
+    Column {
+        header,
+        level,
+        path,
+        kind,
+        defaulted,
+    }
+}
+
+use Kind::*;
+use Level::*;
+
+const COLUMNS: &[Column] = &[
+    column("症状ID", Symptom, &["id"], Text, false),
+    column("症状Key", Symptom, &["key"], Text, false),
+    column("症状名称", Symptom, &["name"], Text, false),
+    column("描述", Symptom, &["description"], Text, true),
+    column("常见症状", Symptom, &["isCommonSymptom"], Flag, true),
+    column("系统分类", Symptom, &["systemCategory"], List, true),
+    column("部位", Symptom, &["bodyParts"], List, true),
+    column(
+        "症状适用性别",
+        Symptom,
+        &["applicablePopulation", "genders"],
+        Genders,
+        true,
+    ),
+    column(
+        "症状适用年龄",
+        Symptom,
+        &["applicablePopulation", "ageRange"],
+        AgeRange,
+        false,
+    ),
+    column("分组ID", Section, &["id"], Text, false),
+    column("分组标题", Section, &["title"], Text, true),
+    column(
+        "分组适用性别",
+        Section,
+        &["applicablePopulation", "genders"],
+        Genders,
+        true,
+    ),
+    column(
+        "分组适用年龄",
+        Section,
+        &["applicablePopulation", "ageRange"],
+        AgeRange,
+        false,
+    ),
+    column("字段ID", Field, &["id"], Text, false),
+    column("字段Key", Field, &["key"], Text, false),
+    column("字段名称", Field, &["label"], Text, true),
+    column("类型", Field, &["type"], Text, false),
+    column("存储Key", Field, &["storageKey"], Text, false),
+    column("必填", Field, &["required"], Flag, false),
+    column("选项", Field, &["props", "options"], List, false),
+    column("单位选项", Field, &["props", "radioOptions"], List, false),
+    column(
+        "互斥组",
+        Field,
+        &["props", "mutualExclusions"],
+        Groups,
+        false,
+    ),
+    column("单位", Field, &["props", "unit"], Text, false),
+    column("提示文字", Field, &["props", "placeholder"], Text, false),
+    column(
+        "字段适用性别",
+        Field,
+        &["applicablePopulation", "genders"],
+        Genders,
+        true,
+    ),
+    column(
+        "字段适用年龄",
+        Field,
+        &["applicablePopulation", "ageRange"],
+        AgeRange,
+        false,
+    ),
+    column("文本生成配置", Field, &["textGenConfig"], Json, false),
+    column("创建时间", Symptom, &["createdAt"], Integer, true),
+    column("更新时间", Symptom, &["updatedAt"], Integer, true),
+];
+
+// Everything else of a symptom, section or field, as a JSON object
+const SYMPTOM_EXTRA: &str = "症状其他(JSON)";
+const SECTION_EXTRA: &str = "分组其他(JSON)";
+const FIELD_EXTRA: &str = "字段其他(JSON)";
+
+fn headers() -> Vec<&'static str> {
+    COLUMNS
+        .iter()
+        .map(|c| c.header)
+        .chain([SYMPTOM_EXTRA, SECTION_EXTRA, FIELD_EXTRA])
+        .collect()
+}
+
+fn age_unit_text(unit: &str) -> Option<&'static str> {
+    match unit {
+        "Y" => Some("岁"),
+        "M" => Some("月"),
+        "D" => Some("天"),
+        _ => None,
+    }
+}
+
+fn age_unit_code(text: &str) -> Option<&'static str> {
+    match text {
+        "岁" | "年" | "Y" | "y" => Some("Y"),
+        "月" | "个月" | "M" | "m" => Some("M"),
+        "天" | "日" | "D" | "d" => Some("D"),
+        _ => None,
+    }
+}
+
+fn number_text(value: &Value) -> Option<String> {
+    let n = value.as_f64()?;
+    Some(if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    })
+}
+
+fn strings(value: &Value) -> Option<Vec<&str>> {
+    value.as_array()?.iter().map(Value::as_str).collect()
+}
+
+fn empty_value(kind: Kind) -> Option<Value> {
+    match kind {
+        Text => Some(Value::String(String::new())),
+        Flag => Some(Value::Bool(false)),
+        Integer => Some(Value::from(0)),
+        List | Genders => Some(Value::Array(Vec::new())),
+        _ => None,
+    }
+}
+
+// Cell text for a value; None when the kind cannot show it
+fn to_text(kind: Kind, value: &Value) -> Option<String> {
+    match kind {
+        Text => value.as_str().map(str::to_string),
+        Flag => value
+            .as_bool()
+            .map(|b| if b { "是" } else { "否" }.to_string()),
+        Integer => value.as_i64().map(|n| n.to_string()),
+        List => Some(strings(value)?.join(&LIST_SEPARATOR.to_string())),
+        Groups => {
+            let groups = value
+                .as_array()?
+                .iter()
+                .map(|g| Some(strings(g)?.join(&LIST_SEPARATOR.to_string())))
+                .collect::<Option<Vec<_>>>()?;
+            Some(groups.join(&GROUP_SEPARATOR.to_string()))
+        }
+        Genders => {
+            let genders = strings(value)?
+                .into_iter()
+                .map(|g| match g {
+                    "1" => Some("男"),
+                    "2" => Some("女"),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(genders.join(&LIST_SEPARATOR.to_string()))
+        }
+        AgeRange => {
+            let range = value.as_object()?;
+            if range
+                .keys()
+                .any(|k| !["min", "max", "unit"].contains(&k.as_str()))
+            {
+                return None;
+            }
+            let bound = |key: &str| match range.get(key) {
+                Some(v) => number_text(v),
+                None => Some(String::new()),
+            };
+            let unit = match range.get("unit") {
+                Some(unit) => age_unit_text(unit.as_str()?)?,
+                None => "",
+            };
+            Some(format!("{}-{}{}", bound("min")?, bound("max")?, unit))
+        }
+        Json => serde_json::to_string(value).ok(),
+    }
+}
+
+fn parse_text(kind: Kind, text: &str) -> Result<Value, String> {
+    let text = text.trim();
+    let list = |text: &str| -> Vec<Value> {
+        text.split(LIST_SEPARATOR)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| Value::String(item.to_string()))
+            .collect()
+    };
+    match kind {
+        Text => Ok(Value::String(text.to_string())),
+        Flag => match text {
+            "是" | "true" | "TRUE" | "1" | "Y" => Ok(Value::Bool(true)),
+            "否" | "false" | "FALSE" | "0" | "N" => Ok(Value::Bool(false)),
+            _ => Err(format!("expected 是 or 否, got \"{}\"", text)),
+        },
+        Integer => text
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| format!("expected a whole number, got \"{}\"", text)),
+        List => Ok(Value::Array(list(text))),
+        Groups => Ok(Value::Array(
+            text.split(GROUP_SEPARATOR)
+                .filter(|group| !group.trim().is_empty())
+                .map(|group| Value::Array(list(group)))
+                .collect(),
+        )),
+        Genders => text
+            .split(LIST_SEPARATOR)
+            .map(str::trim)
+            .filter(|g| !g.is_empty())
+            .map(|g| {
+                parse_sex(g)
+                    .map(|code| Value::String(code.to_string()))
+                    .ok_or_else(|| format!("unknown gender \"{}\"", g))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        AgeRange => {
+            let invalid = || format!("expected an age range like 12-60岁, got \"{}\"", text);
+            let (min, rest) = text.split_once('-').ok_or_else(invalid)?;
+            let split = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let (max, unit) = rest.split_at(split);
+            let mut range = Map::new();
+            for (key, bound) in [("min", min.trim()), ("max", max.trim())] {
+                if !bound.is_empty() {
+                    let n: f64 = bound.parse().map_err(|_| invalid())?;
+                    range.insert(key.to_string(), Value::from(n));
+                }
+            }
+            let unit = unit.trim();
+            if !unit.is_empty() {
+                let code = age_unit_code(unit).ok_or_else(invalid)?;
+                range.insert("unit".to_string(), Value::String(code.to_string()));
+            }
+            Ok(Value::Object(range))
+        }
+        Json => serde_json::from_str(text).map_err(|e| format!("invalid JSON: {}", e)),
+    }
+}
+
+fn get_path<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |v, key| v.get(key))
+}
+
+fn remove_path(map: &mut Map<String, Value>, path: &[&str]) {
+    match path {
+        [key] => {
+            map.remove(*key);
+        }
+        [key, rest @ ..] => {
+            if let Some(Value::Object(inner)) = map.get_mut(*key) {
+                remove_path(inner, rest);
+            }
+        }
+        [] => {}
+    }
+}
+
+fn set_path(map: &mut Map<String, Value>, path: &[&str], value: Value) {
+    match path {
+        [key] => {
+            map.insert(key.to_string(), value);
+        }
+        [key, rest @ ..] => {
+            let inner = map
+                .entry(key.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !inner.is_object() {
+                *inner = Value::Object(Map::new());
+            }
+            if let Value::Object(inner) = inner {
+                set_path(inner, rest, value);
+            }
+        }
+        [] => {}
+    }
+}
+
+// Drop a container that only held column values; `props` and `config` mean
+// the same absent as empty
+fn prune_empty(map: &mut Map<String, Value>, key: &str) {
+    if map
+        .get(key)
+        .and_then(Value::as_object)
+        .is_some_and(Map::is_empty)
+    {
+        map.remove(key);
+    }
+}
+
+// Fill the cells of one level and return what the cells could not carry
+fn export_level(value: &Value, level: Level, row: &mut [String]) -> Map<String, Value> {
+    let mut extra = value.as_object().cloned().unwrap_or_default();
+    for (index, column) in COLUMNS.iter().enumerate() {
+        if column.level != level {
+            continue;
+        }
+        let Some(current) = get_path(value, column.path) else {
+            continue;
+        };
+        if column.defaulted && empty_value(column.kind).as_ref() == Some(current) {
+            remove_path(&mut extra, column.path);
+            continue;
+        }
+        let Some(text) = to_text(column.kind, current) else {
+            continue;
+        };
+        // Only values that read back unchanged leave the JSON column
+        if !text.is_empty() && parse_text(column.kind, &text).ok().as_ref() == Some(current) {
+            row[index] = text;
+            remove_path(&mut extra, column.path);
+        }
+    }
+    extra
+}
+
+fn extra_text(extra: &Map<String, Value>) -> String {
+    if extra.is_empty() {
+        String::new()
+    } else {
+        Value::Object(extra.clone()).to_string()
+    }
+}
+
+/// The header row followed by one row per field.
+pub fn to_rows(templates: &[SymptomTemplate]) -> Vec<Vec<String>> {
+    let headers = headers();
+    let width = headers.len();
+    let extra_index = COLUMNS.len();
+    let mut rows = vec![headers.into_iter().map(str::to_string).collect::<Vec<_>>()];
+
+    for template in templates {
+        let mut symptom = serde_json::to_value(template).unwrap_or_default();
+        let sections = symptom
+            .get_mut("config")
+            .and_then(|c| c.as_object_mut())
+            .and_then(|c| c.remove("sections"))
+            .unwrap_or_default();
+        let mut symptom_row = vec![String::new(); width];
+        let mut symptom_extra = export_level(&symptom, Symptom, &mut symptom_row);
+        prune_empty(&mut symptom_extra, "config");
+        prune_empty(&mut symptom_extra, "applicablePopulation");
+        symptom_row[extra_index] = extra_text(&symptom_extra);
+
+        let sections = sections.as_array().cloned().unwrap_or_default();
+        if sections.is_empty() {
+            rows.push(symptom_row.clone());
+        }
+        for mut section in sections {
+            let fields = section
+                .as_object_mut()
+                .and_then(|s| s.remove("fields"))
+                .unwrap_or_default();
+            let mut section_row = symptom_row.clone();
+            let section_extra = export_level(&section, Section, &mut section_row);
+            section_row[extra_index + 1] = extra_text(&section_extra);
+
+            let fields = fields.as_array().cloned().unwrap_or_default();
+            if fields.is_empty() {
+                rows.push(section_row.clone());
+            }
+            for field in fields {
+                let mut row = section_row.clone();
+                let mut field_extra = export_level(&field, Field, &mut row);
+                prune_empty(&mut field_extra, "props");
+                row[extra_index + 2] = extra_text(&field_extra);
+                rows.push(row);
+            }
+        }
+    }
+    rows
+}
+
+fn sheet_error(row: usize, column: &str, message: String) -> TemplateError {
+    TemplateError::Parse(ValidationIssue {
+        path: "$".to_string(),
+        message: format!("Row {}, {}: {}", row, column, message),
+    })
+}
+
+struct RowReader<'a> {
+    index: HashMap<&'a str, usize>,
+}
+
+impl RowReader<'_> {
+    fn cell<'r>(&self, row: &'r [String], header: &str) -> &'r str {
+        self.index
+            .get(header)
+            .and_then(|&i| row.get(i))
+            .map(|s| s.trim())
+            .unwrap_or_default()
+    }
+
+    // Start from the JSON column and lay the cells of `level` over it
+    fn read_level(
+        &self,
+        row: &[String],
+        row_number: usize,
+        level: Level,
+        extra_header: &str,
+    ) -> Result<Map<String, Value>, TemplateError> {
+        let extra = self.cell(row, extra_header);
+        let mut object = if extra.is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_str(extra) {
+                Ok(Value::Object(map)) => map,
+                Ok(_) => {
+                    return Err(sheet_error(
+                        row_number,
+                        extra_header,
+                        "expected a JSON object".to_string(),
+                    ))
+                }
+                Err(e) => {
+                    return Err(sheet_error(
+                        row_number,
+                        extra_header,
+                        format!("invalid JSON: {}", e),
+                    ))
+                }
+            }
+        };
+        for column in COLUMNS.iter().filter(|c| c.level == level) {
+            let text = self.cell(row, column.header);
+            if text.is_empty() {
+                continue;
+            }
+            let value = parse_text(column.kind, text)
+                .map_err(|e| sheet_error(row_number, column.header, e))?;
+            set_path(&mut object, column.path, value);
+        }
+        Ok(object)
+    }
+}
+
+// First id of the form `<prefix>_<n>` not taken yet
+fn fresh_id(prefix: &str, taken: &[String]) -> String {
+    (0..)
+        .map(|n| format!("{}_{}", prefix, n))
+        .find(|id| !taken.contains(id))
+        .unwrap_or_default()
+}
+
+fn ids<'a>(items: impl IntoIterator<Item = &'a Value>) -> Vec<String> {
+    items
+        .into_iter()
+        .filter_map(|i| i.get("id").and_then(Value::as_str).map(str::to_string))
+        .collect()
+}
+
+// Sections and fields added in the sheet get `<key>_section_<n>` and
+// `<key>_field_<n>` ids, once every existing id is known
+fn assign_missing_ids(symptom: &mut Map<String, Value>) {
+    let key = symptom
+        .get("key")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let Some(Value::Array(sections)) = get_path_mut(symptom, &["config", "sections"]) else {
+        return;
+    };
+
+    let mut section_ids = ids(sections.iter());
+    let mut field_ids = ids(sections
+        .iter()
+        .filter_map(|s| s.get("fields").and_then(Value::as_array))
+        .flatten());
+    for section in sections.iter_mut() {
+        let Value::Object(section) = section else {
+            continue;
+        };
+        if !section.contains_key("id") {
+            let id = fresh_id(&format!("{}_section", key), &section_ids);
+            section_ids.push(id.clone());
+            section.insert("id".into(), id.into());
+        }
+        let Some(Value::Array(fields)) = section.get_mut("fields") else {
+            continue;
+        };
+        for field in fields.iter_mut().filter_map(Value::as_object_mut) {
+            if !field.contains_key("id") {
+                let id = fresh_id(&format!("{}_field", key), &field_ids);
+                field_ids.push(id.clone());
+                field.insert("id".into(), id.into());
+            }
+        }
+    }
+}
+
+/// Rebuild templates from rows produced by `to_rows`, possibly edited.
+///
+/// Columns are found by header, so they may be reordered or left out. Rows
+/// without ids get fresh ones; new symptoms get the current time.
+pub fn from_rows(rows: &[Vec<String>], now: i64) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let Some((header_row, data)) = rows.split_first() else {
+        return Err(sheet_error(1, "-", "the sheet is empty".to_string()));
+    };
+    let reader = RowReader {
+        index: header_row
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (h.trim().trim_start_matches('\u{feff}'), i))
+            .collect(),
+    };
+    if !reader.index.contains_key("症状Key") {
+        return Err(sheet_error(1, "症状Key", "column not found".to_string()));
+    }
+
+    let mut symptoms: Vec<Map<String, Value>> = Vec::new();
+    for (offset, row) in data.iter().enumerate() {
+        let row_number = offset + 2;
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let key = reader.cell(row, "症状Key");
+        if key.is_empty() {
+            return Err(sheet_error(row_number, "症状Key", "missing".to_string()));
+        }
+
+        let position = symptoms
+            .iter()
+            .position(|s| s.get("key").and_then(Value::as_str) == Some(key));
+        let symptom = match position {
+            Some(i) => &mut symptoms[i],
+            None => {
+                let mut symptom = reader.read_level(row, row_number, Symptom, SYMPTOM_EXTRA)?;
+                // A symptom added in the sheet has no id yet
+                if !symptom.contains_key("id") {
+                    symptom.insert("id".into(), uuid::Uuid::new_v4().to_string().into());
+                    for stamp in ["createdAt", "updatedAt"] {
+                        symptom.entry(stamp).or_insert(Value::from(now));
+                    }
+                }
+                set_path(
+                    &mut symptom,
+                    &["config", "sections"],
+                    Value::Array(Vec::new()),
+                );
+                symptoms.push(symptom);
+                symptoms.last_mut().expect("just pushed")
+            }
+        };
+        let Some(Value::Array(sections)) = get_path_mut(symptom, &["config", "sections"]) else {
+            continue;
+        };
+
+        let section_id = reader.cell(row, "分组ID");
+        let section_title = reader.cell(row, "分组标题");
+        if section_id.is_empty() && section_title.is_empty() {
+            continue;
+        }
+        let existing = sections.iter().position(|s| {
+            if section_id.is_empty() {
+                s.get("title").and_then(Value::as_str) == Some(section_title)
+            } else {
+                s.get("id").and_then(Value::as_str) == Some(section_id)
+            }
+        });
+        let section_index = match existing {
+            Some(i) => i,
+            None => {
+                let mut section = reader.read_level(row, row_number, Section, SECTION_EXTRA)?;
+                section.insert("fields".into(), Value::Array(Vec::new()));
+                sections.push(Value::Object(section));
+                sections.len() - 1
+            }
+        };
+
+        let has_field = ["字段ID", "字段Key", "字段名称", "类型", FIELD_EXTRA]
+            .iter()
+            .any(|h| !reader.cell(row, h).is_empty());
+        if !has_field {
+            continue;
+        }
+        let mut field = reader.read_level(row, row_number, Field, FIELD_EXTRA)?;
+        if !field.contains_key("storageKey") {
+            if let Some(field_key) = field.get("key").cloned() {
+                field.insert("storageKey".into(), field_key);
+            }
+        }
+        if let Some(Value::Array(fields)) = sections[section_index].get_mut("fields") {
+            fields.push(Value::Object(field));
+        }
+    }
+
+    for symptom in &mut symptoms {
+        assign_missing_ids(symptom);
+    }
+    schema::from_value(Value::Array(
+        symptoms.into_iter().map(Value::Object).collect(),
+    ))
+}
+
+fn get_path_mut<'a>(map: &'a mut Map<String, Value>, path: &[&str]) -> Option<&'a mut Value> {
+    let (first, rest) = path.split_first()?;
+    rest.iter()
+        .try_fold(map.get_mut(*first)?, |v, key| v.get_mut(*key))
+}
+
+fn io_error(path: &Path, e: impl std::fmt::Display) -> TemplateError {
+    TemplateError::Storage(format!("Failed to access {}: {}", path.display(), e))
+}
+
+/// Write the templates as a spreadsheet in `format`.
+pub fn write(
+    templates: &[SymptomTemplate],
+    path: &Path,
+    format: SheetFormat,
+) -> Result<(), TemplateError> {
+    let rows = to_rows(templates);
+    match format {
+        SheetFormat::Csv => {
+            let mut bytes = Vec::new();
+            // BOM so Excel opens the Chinese text as UTF-8
+            bytes.extend_from_slice("\u{feff}".as_bytes());
+            {
+                let mut writer = csv::Writer::from_writer(&mut bytes);
+                for row in &rows {
+                    writer.write_record(row).map_err(|e| io_error(path, e))?;
+                }
+                writer.flush().map_err(|e| io_error(path, e))?;
+            }
+            std::fs::write(path, bytes).map_err(|e| io_error(path, e))
+        }
+        SheetFormat::Xlsx => {
+            let mut workbook = rust_xlsxwriter::Workbook::new();
+            let sheet = workbook.add_worksheet();
+            sheet.set_name(SHEET_NAME).map_err(|e| io_error(path, e))?;
+            let header = rust_xlsxwriter::Format::new().set_bold();
+            let wrap = rust_xlsxwriter::Format::new().set_text_wrap();
+            for (r, row) in rows.iter().enumerate() {
+                for (c, cell) in row.iter().enumerate() {
+                    let format = if r == 0 { &header } else { &wrap };
+                    sheet
+                        .write_string_with_format(r as u32, c as u16, cell, format)
+                        .map_err(|e| io_error(path, e))?;
+                }
+            }
+            for (c, header) in headers().iter().enumerate() {
+                let width = if header.contains("JSON") || *header == "选项" {
+                    40.0
+                } else {
+                    14.0
+                };
+                sheet
+                    .set_column_width(c as u16, width)
+                    .map_err(|e| io_error(path, e))?;
+            }
+            sheet
+                .set_freeze_panes(1, 3)
+                .map_err(|e| io_error(path, e))?;
+            workbook.save(path).map_err(|e| io_error(path, e))
+        }
+    }
+}
+
+fn read_rows(
+    path: &Path,
+    bytes: &[u8],
+    format: SheetFormat,
+) -> Result<Vec<Vec<String>>, TemplateError> {
+    match format {
+        SheetFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(bytes);
+            reader
+                .records()
+                .map(|record| {
+                    record
+                        .map(|r| r.iter().map(str::to_string).collect())
+                        .map_err(|e| io_error(path, e))
+                })
+                .collect()
+        }
+        SheetFormat::Xlsx => {
+            use calamine::{Data, Reader};
+            let mut workbook =
+                calamine::Xlsx::new(std::io::Cursor::new(bytes)).map_err(|e| io_error(path, e))?;
+            let range = workbook
+                .worksheet_range_at(0)
+                .ok_or_else(|| io_error(path, "the workbook has no sheets"))?
+                .map_err(|e| io_error(path, e))?;
+            Ok(range
+                .rows()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| match cell {
+                            Data::Empty => String::new(),
+                            // Numbers typed over a cell, e.g. a timestamp
+                            Data::Float(f) => {
+                                number_text(&Value::from(*f)).unwrap_or_else(|| f.to_string())
+                            }
+                            other => other.to_string(),
+                        })
+                        .collect()
+                })
+                .collect())
+        }
+    }
+}
+
+/// Read templates from a spreadsheet written by `write`.
+pub fn read(
+    path: &Path,
+    format: SheetFormat,
+    now: i64,
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    let bytes = std::fs::read(path).map_err(|e| io_error(path, e))?;
+    read_bytes(path, &bytes, format, now)
+}
+
+/// `read` for file contents already in memory; `path` only names the file in errors.
+pub fn read_bytes(
+    path: &Path,
+    bytes: &[u8],
+    format: SheetFormat,
+    now: i64,
+) -> Result<Vec<SymptomTemplate>, TemplateError> {
+    from_rows(&read_rows(path, bytes, format)?, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::templates::repository::bundled_templates;
+
+    // The bundled templates, with the columns they leave empty filled in on
+    // the first symptom
+    fn templates() -> Vec<SymptomTemplate> {
+        let mut templates = serde_json::to_value(bundled_templates().unwrap()).unwrap();
+        let symptom = &mut templates[0];
+        symptom["description"] = json!("持续性发热,伴\"畏寒\"\n夜间加重");
+        symptom["applicablePopulation"] =
+            json!({ "genders": ["2"], "ageRange": { "min": 0.5, "max": 60, "unit": "Y" } });
+        symptom["reviewedBy"] = json!({ "name": "张三", "at": 1_760_745_600_000_i64 });
+
+        let section = &mut symptom["config"]["sections"][0];
+        section["applicablePopulation"] =
+            json!({ "genders": ["1", "2"], "ageRange": { "max": 28, "unit": "D" } });
+        section["collapsed"] = json!(true);
+
+        let field = &mut section["fields"][0];
+        field["required"] = json!(true);
+        field["applicablePopulation"] = json!({ "genders": ["1"], "ageRange": { "min": 12 } });
+        field["textGenConfig"] = json!({
+            "targets": ["chiefComplaint", "historyOfPresentIllness"],
+            "template": "{label}{value}",
+            "separator": ",",
+            "optionConfig": { "ignoreValues": ["不详"], "valueMap": { "天": "日" } }
+        });
+        field["props"]["mutualExclusions"] = json!([["小时", "天"], ["周", "月"]]);
+        field["props"]["maxLength"] = json!(20);
+        field["hint"] = json!({ "zh": "按起病计" });
+
+        schema::from_value(templates).unwrap()
+    }
+
+    fn round_trip(format: SheetFormat, extension: &str) {
+        let templates = templates();
+        let path =
+            std::env::temp_dir().join(format!("templates-{}.{}", uuid::Uuid::new_v4(), extension));
+        write(&templates, &path, format).unwrap();
+        let read_back = read(&path, format, 0);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(
+            serde_json::to_value(read_back.unwrap()).unwrap(),
+            serde_json::to_value(&templates).unwrap()
+        );
+    }
+
+    #[test]
+    fn fills_columns_before_the_json_columns() {
+        let rows = to_rows(&templates());
+        let cell = |header: &str| {
+            let index = rows[0].iter().position(|h| h == header).unwrap();
+            rows[1][index].as_str()
+        };
+        assert_eq!(cell("症状适用性别"), "女");
+        assert_eq!(cell("症状适用年龄"), "0.5-60岁");
+        assert_eq!(cell("分组适用年龄"), "-28天");
+        assert_eq!(cell("字段适用年龄"), "12-");
+        assert_eq!(cell("互斥组"), "小时|天;周|月");
+        assert!(cell("文本生成配置").contains("historyOfPresentIllness"));
+        assert!(cell(SYMPTOM_EXTRA).contains("reviewedBy"));
+        assert!(cell(SECTION_EXTRA).contains("collapsed"));
+        assert!(cell(FIELD_EXTRA).contains("maxLength"));
+    }
+
+    #[test]
+    fn csv_round_trips_the_bundled_templates() {
+        round_trip(SheetFormat::Csv, "csv");
+    }
+
+    #[test]
+    fn xlsx_round_trips_the_bundled_templates() {
+        round_trip(SheetFormat::Xlsx, "xlsx");
+    }
+}
@@ -0,0 +1,561 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::applicability::{is_applicable, PatientProfile};
+use super::schema::{ApplicablePopulation, SymptomTemplate, TextGenConfig, TextTarget};
+
+const DEFAULT_SEPARATOR: &str = "、";
+
+// Answers that say nothing about the symptom and are left out of the fallback text
+const UNINFORMATIVE_VALUES: [&str; 6] = ["不清楚", "无", "以上都无", "未查", "不详", "不记得"];
+// Same for the general-condition answers
+const UNINFORMATIVE_GENERAL: [&str; 3] = ["其他", "不清楚", "不详"];
+// Fields the fallback text covers in the opening sentence instead
+const INTRO_FIELDS: [&str; 2] = ["onsetTime", "precipitatingFactor"];
+
+// Integral numbers print without a fraction, as in JavaScript
+fn number_text(number: &serde_json::Number) -> String {
+    match number.as_f64() {
+        Some(n) if n.fract() == 0.0 && n.abs() < 1e15 => format!("{}", n as i64),
+        _ => number.to_string(),
+    }
+}
+
+// Text of a scalar answer; None for empty strings, null and non-scalars
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) if n.as_f64() != Some(0.0) => Some(number_text(n)),
+        _ => None,
+    }
+}
+
+// "3" + "天" from an input_radio answer `{ inputValue, radioValue }`
+fn input_radio_text(value: &Value) -> Option<String> {
+    let input = scalar_text(value.get("inputValue")?)?;
+    let radio = scalar_text(value.get("radioValue")?)?;
+    Some(format!("{}{}", input, radio))
+}
+
+/// Render one answer with the field's `textGenConfig`.
+///
+/// Returns None when the answer is empty or every value is in `ignoreValues`.
+pub fn field_text(config: &TextGenConfig, value: &Value, label: &str) -> Option<String> {
+    let separator = config.separator.as_deref().unwrap_or(DEFAULT_SEPARATOR);
+    let option_config = config.option_config.clone().unwrap_or_default();
+    let keep = |v: &String| !option_config.ignore_values.contains(v);
+    let mapped = |v: String| option_config.value_map.get(&v).cloned().unwrap_or(v);
+
+    let values: Vec<String> = match value {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::Number(n) => Some(number_text(n)),
+                other => other.as_str().map(str::to_string),
+            })
+            .filter(keep)
+            .map(mapped)
+            .collect(),
+        Value::Object(map) if map.contains_key("inputValue") => input_radio_text(value)
+            .filter(keep)
+            .map(mapped)
+            .into_iter()
+            .collect(),
+        Value::String(s) if !s.is_empty() => Some(s.clone())
+            .filter(keep)
+            .map(mapped)
+            .into_iter()
+            .collect(),
+        Value::Number(n) => vec![number_text(n)],
+        _ => Vec::new(),
+    };
+    if values.is_empty() {
+        return None;
+    }
+
+    let text = config.template.replace("{value}", &values.join(separator));
+    if label.is_empty() {
+        Some(text)
+    } else {
+        Some(text.replace("{label}", label))
+    }
+}
+
+/// Texts of every configured field of `symptom` for `target`, in template order.
+///
+/// Sections and fields that do not apply to the patient are skipped; without
+/// a patient every field is used.
+pub fn symptom_texts(
+    symptom: &SymptomTemplate,
+    answers: &Map<String, Value>,
+    target: TextTarget,
+    patient: Option<&PatientProfile>,
+) -> Vec<String> {
+    let applies = |population: Option<&ApplicablePopulation>| {
+        patient.is_none_or(|patient| is_applicable(population, patient))
+    };
+
+    symptom
+        .config
+        .sections
+        .iter()
+        .filter(|section| applies(section.applicable_population.as_ref()))
+        .flat_map(|section| &section.fields)
+        .filter(|field| applies(field.applicable_population.as_ref()))
+        .filter_map(|field| {
+            let config = field.text_gen_config.as_ref()?;
+            if !config.targets.contains(&target) {
+                return None;
+            }
+            field_text(config, answers.get(&field.storage_key)?, &field.label)
+        })
+        .collect()
+}
+
+// Plain listing of the answers for symptoms without textGenConfig
+fn fallback_detail(
+    symptom: &SymptomTemplate,
+    answers: &Map<String, Value>,
+    patient: Option<&PatientProfile>,
+) -> String {
+    let informative = |v: &String| !UNINFORMATIVE_VALUES.contains(&v.as_str());
+    let mut details = Vec::new();
+
+    for section in &symptom.config.sections {
+        if patient.is_some_and(|p| !is_applicable(section.applicable_population.as_ref(), p)) {
+            continue;
+        }
+        for field in &section.fields {
+            if INTRO_FIELDS.contains(&field.key.as_str())
+                || patient.is_some_and(|p| !is_applicable(field.applicable_population.as_ref(), p))
+            {
+                continue;
+            }
+            let formatted = match answers.get(&field.key) {
+                Some(Value::Array(items)) => {
+                    let items: Vec<String> = items
+                        .iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .filter(informative)
+                        .collect();
+                    if items.is_empty() {
+                        continue;
+                    }
+                    if symptom.key == "cough" && field.key == "colorFeature" {
+                        format!("咳{}", items.join(DEFAULT_SEPARATOR))
+                    } else {
+                        items.join(DEFAULT_SEPARATOR)
+                    }
+                }
+                Some(value @ (Value::String(_) | Value::Number(_))) => {
+                    let text = match value {
+                        Value::Number(n) => number_text(n),
+                        other => other.as_str().unwrap_or_default().to_string(),
+                    };
+                    if text.is_empty() || !informative(&text) {
+                        continue;
+                    }
+                    if symptom.key == "fever" && field.key == "maximumBodyTemperature" {
+                        format!("最高体温{}℃", text)
+                    } else {
+                        text
+                    }
+                }
+                _ => continue,
+            };
+            details.push(formatted);
+        }
+    }
+    details.join("，")
+}
+
+// "一般情况：…" from the general-condition answers, merging normal urination and stool
+fn general_condition(answers: &Map<String, Value>) -> Option<String> {
+    let answer = |key: &str| {
+        answers
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|v| !v.is_empty() && !UNINFORMATIVE_GENERAL.contains(v))
+    };
+
+    let mut parts: Vec<&str> = ["spirit", "sleep", "appetite"]
+        .into_iter()
+        .filter_map(answer)
+        .collect();
+    if answer("urination") == Some("小便正常") && answer("stool") == Some("大便正常") {
+        parts.push("二便正常");
+    } else {
+        parts.extend(answer("urination"));
+        parts.extend(answer("stool"));
+    }
+    parts.extend(answer("weight"));
+
+    (!parts.is_empty()).then(|| format!("一般情况：{}。", parts.join("，")))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymptomAnswers {
+    pub key: String,
+    // Keyed by field storageKey, in the shape the consultation form stores them
+    #[serde(default)]
+    pub answers: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextGenRequest {
+    #[serde(default)]
+    pub patient: Option<PatientProfile>,
+    // In the order the symptoms were selected; the first one opens the history
+    pub symptoms: Vec<SymptomAnswers>,
+    // General-condition answers (spirit, sleep, appetite, urination, stool, weight)
+    #[serde(default)]
+    pub general: Option<Map<String, Value>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedText {
+    pub chief_complaint: String,
+    pub history_of_present_illness: String,
+    // Requested symptom keys with no template; left out of the text
+    pub unknown_symptoms: Vec<String>,
+}
+
+/// Generate the chief complaint and present illness for a set of answers.
+///
+/// Symptoms with `textGenConfig` use it; the others fall back to the onset
+/// time and a plain listing of their answers, as the consultation page did.
+pub fn generate(templates: &[SymptomTemplate], request: &TextGenRequest) -> GeneratedText {
+    let patient = request.patient.as_ref();
+    let mut unknown_symptoms = Vec::new();
+    let selected: Vec<(&SymptomTemplate, &Map<String, Value>)> = request
+        .symptoms
+        .iter()
+        .filter_map(|s| match templates.iter().find(|t| t.key == s.key) {
+            Some(template) => Some((template, &s.answers)),
+            None => {
+                unknown_symptoms.push(s.key.clone());
+                None
+            }
+        })
+        .collect();
+
+    let complaints: Vec<String> = selected
+        .iter()
+        .map(|(symptom, answers)| {
+            let texts = symptom_texts(symptom, answers, TextTarget::ChiefComplaint, patient);
+            if !texts.is_empty() {
+                return format!("{}{}", symptom.name, texts.concat());
+            }
+            let onset = answers.get("onsetTime").and_then(input_radio_text);
+            format!("{}{}", symptom.name, onset.unwrap_or_default())
+        })
+        .collect();
+    let chief_complaint = if complaints.is_empty() {
+        String::new()
+    } else {
+        format!("{}。", complaints.join("，"))
+    };
+
+    let mut history = Vec::new();
+    if let Some((_, first)) = selected.first() {
+        let duration = first
+            .get("onsetTime")
+            .and_then(input_radio_text)
+            .unwrap_or_else(|| "近日".to_string());
+        let precipitating = match first.get("precipitatingFactor") {
+            Some(Value::Array(items)) if !items.is_empty() => items
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(DEFAULT_SEPARATOR),
+            Some(Value::String(s)) if !s.is_empty() && s != "不清楚" && s != "没有原因" => {
+                s.clone()
+            }
+            _ => "无明显诱因".to_string(),
+        };
+        let names: Vec<&str> = selected.iter().map(|(s, _)| s.name.as_str()).collect();
+        history.push(format!(
+            "患者于{}前，{}出现{}。",
+            duration,
+            precipitating,
+            names.join(DEFAULT_SEPARATOR)
+        ));
+    }
+
+    for (symptom, answers) in &selected {
+        let texts = symptom_texts(
+            symptom,
+            answers,
+            TextTarget::HistoryOfPresentIllness,
+            patient,
+        );
+        if !texts.is_empty() {
+            history.push(format!("{}：{}。", symptom.name, texts.join("，")));
+            continue;
+        }
+        let detail = fallback_detail(symptom, answers, patient);
+        if !detail.is_empty() {
+            history.push(format!("{}，{}。", symptom.name, detail));
+        }
+    }
+    history.extend(request.general.as_ref().and_then(general_condition));
+
+    GeneratedText {
+        chief_complaint,
+        history_of_present_illness: history.join("\n"),
+        unknown_symptoms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::templates::repository::bundled_templates;
+    use crate::templates::schema::{FieldType, OptionConfig};
+
+    fn config(template: &str) -> TextGenConfig {
+        TextGenConfig {
+            targets: vec![TextTarget::HistoryOfPresentIllness],
+            template: template.to_string(),
+            separator: None,
+            option_config: None,
+        }
+    }
+
+    fn with_options(template: &str, ignore: &[&str], map: &[(&str, &str)]) -> TextGenConfig {
+        TextGenConfig {
+            option_config: Some(OptionConfig {
+                ignore_values: ignore.iter().map(|v| v.to_string()).collect(),
+                value_map: map
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            }),
+            ..config(template)
+        }
+    }
+
+    fn answers(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    fn request(key: &str, answers: Map<String, Value>) -> TextGenRequest {
+        TextGenRequest {
+            patient: None,
+            symptoms: vec![SymptomAnswers {
+                key: key.to_string(),
+                answers,
+            }],
+            general: None,
+        }
+    }
+
+    // An answer of the field's type, built from its own options
+    fn synthetic_answer(field: &crate::templates::schema::Field) -> Value {
+        let choices = field.choices();
+        match field.field_type {
+            FieldType::Radio => json!(choices.first().cloned().unwrap_or_default()),
+            FieldType::Checkbox => json!(choices.iter().take(2).collect::<Vec<_>>()),
+            FieldType::InputRadio => json!({
+                "inputValue": "3",
+                "radioValue": choices.first().cloned().unwrap_or_default(),
+            }),
+            FieldType::Number => json!(38.5),
+            FieldType::Input => json!("自述"),
+        }
+    }
+
+    #[test]
+    fn generates_text_for_every_bundled_symptom() {
+        let templates = bundled_templates().unwrap();
+        assert!(!templates.is_empty());
+
+        for symptom in &templates {
+            let mut answers = Map::new();
+            for field in symptom.config.sections.iter().flat_map(|s| &s.fields) {
+                answers.insert(field.storage_key.clone(), synthetic_answer(field));
+            }
+            let onset = answers
+                .get("onsetTime")
+                .and_then(input_radio_text)
+                .unwrap_or_default();
+
+            let text = generate(&templates, &request(&symptom.key, answers));
+            assert!(text.unknown_symptoms.is_empty(), "{}", symptom.key);
+            assert_eq!(
+                text.chief_complaint,
+                format!("{}{}。", symptom.name, onset),
+                "{}",
+                symptom.key
+            );
+            let mut lines = text.history_of_present_illness.lines();
+            let intro = lines.next().unwrap_or_default();
+            assert!(intro.starts_with("患者于"), "{}: {}", symptom.key, intro);
+            assert!(intro.ends_with(&format!("出现{}。", symptom.name)));
+            assert!(
+                lines.all(|line| line.starts_with(&symptom.name)),
+                "{}",
+                symptom.key
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_symptoms_are_reported() {
+        let templates = bundled_templates().unwrap();
+        let text = generate(&templates, &request("noSuchSymptom", Map::new()));
+        assert_eq!(text.unknown_symptoms, vec!["noSuchSymptom"]);
+        assert_eq!(text.chief_complaint, "");
+        assert_eq!(text.history_of_present_illness, "");
+    }
+
+    #[test]
+    fn field_text_drops_ignored_values() {
+        let config = with_options("{value}", &["不清楚"], &[]);
+        assert_eq!(field_text(&config, &json!(["不清楚"]), ""), None);
+        assert_eq!(field_text(&config, &json!("不清楚"), ""), None);
+        assert_eq!(
+            field_text(&config, &json!(["咳嗽", "不清楚", "咳痰"]), ""),
+            Some("咳嗽、咳痰".to_string())
+        );
+    }
+
+    #[test]
+    fn field_text_maps_values() {
+        let config = with_options("{value}", &[], &[("是", "有吸烟史")]);
+        assert_eq!(
+            field_text(&config, &json!("是"), ""),
+            Some("有吸烟史".to_string())
+        );
+        assert_eq!(
+            field_text(&config, &json!("否"), ""),
+            Some("否".to_string())
+        );
+    }
+
+    #[test]
+    fn field_text_uses_custom_separator() {
+        let config = TextGenConfig {
+            separator: Some("，".to_string()),
+            ..config("伴{value}")
+        };
+        assert_eq!(
+            field_text(&config, &json!(["头痛", "乏力"]), ""),
+            Some("伴头痛，乏力".to_string())
+        );
+    }
+
+    #[test]
+    fn field_text_fills_label() {
+        let config = config("{label}{value}℃");
+        assert_eq!(
+            field_text(&config, &json!(38.5), "最高体温"),
+            Some("最高体温38.5℃".to_string())
+        );
+        assert_eq!(
+            field_text(&config, &json!(39.0), "最高体温"),
+            Some("最高体温39℃".to_string())
+        );
+    }
+
+    #[test]
+    fn field_text_keeps_numeric_zero() {
+        let config = config("发作{value}次");
+        assert_eq!(
+            field_text(&config, &json!(0), ""),
+            Some("发作0次".to_string())
+        );
+        assert_eq!(field_text(&config, &json!(""), ""), None);
+        assert_eq!(field_text(&config, &Value::Null, ""), None);
+    }
+
+    #[test]
+    fn field_text_needs_both_input_radio_parts() {
+        let config = config("{value}");
+        assert_eq!(
+            field_text(
+                &config,
+                &json!({ "inputValue": "3", "radioValue": "天" }),
+                ""
+            ),
+            Some("3天".to_string())
+        );
+        assert_eq!(field_text(&config, &json!({ "inputValue": "3" }), ""), None);
+        assert_eq!(
+            field_text(
+                &config,
+                &json!({ "inputValue": "", "radioValue": "天" }),
+                ""
+            ),
+            None
+        );
+        assert_eq!(
+            field_text(&config, &json!({ "inputValue": 3, "radioValue": "" }), ""),
+            None
+        );
+    }
+
+    #[test]
+    fn fallback_prefixes_cough_color() {
+        let templates = bundled_templates().unwrap();
+        let text = generate(
+            &templates,
+            &request(
+                "cough",
+                answers(json!({ "colorFeature": ["痰中带血", "不清楚"] })),
+            ),
+        );
+        assert_eq!(
+            text.history_of_present_illness,
+            "患者于近日前，无明显诱因出现咳嗽。\n咳嗽，咳痰中带血。"
+        );
+    }
+
+    #[test]
+    fn fallback_formats_fever_temperature() {
+        let templates = bundled_templates().unwrap();
+        let text = generate(
+            &templates,
+            &request(
+                "fever",
+                answers(json!({
+                    "onsetTime": { "inputValue": "2", "radioValue": "天" },
+                    "precipitatingFactor": "受凉后",
+                    "maximumBodyTemperature": 39.2,
+                })),
+            ),
+        );
+        assert_eq!(text.chief_complaint, "发热2天。");
+        assert_eq!(
+            text.history_of_present_illness,
+            "患者于2天前，受凉后出现发热。\n发热，最高体温39.2℃。"
+        );
+    }
+
+    #[test]
+    fn general_condition_merges_normal_urination_and_stool() {
+        let general = answers(json!({
+            "spirit": "精神好",
+            "sleep": "不清楚",
+            "urination": "小便正常",
+            "stool": "大便正常",
+            "weight": "体重无明显变化",
+        }));
+        assert_eq!(
+            general_condition(&general).as_deref(),
+            Some("一般情况：精神好，二便正常，体重无明显变化。")
+        );
+
+        let general = answers(json!({ "urination": "小便正常", "stool": "便秘" }));
+        assert_eq!(
+            general_condition(&general).as_deref(),
+            Some("一般情况：小便正常，便秘。")
+        );
+        assert_eq!(general_condition(&Map::new()), None);
+    }
+}
@@ -10,7 +10,34 @@ import type {
   SessionStatistics,
   FeedbackStatistics,
   PerformanceStatistics,
-  ExportFormat
+  ExportFormat,
+  ExportSummary,
+  RetentionPolicy,
+  PurgeReport,
+  DatabaseSizeReport,
+  EncryptionStatus,
+  RecommendationStatistics,
+  TargetType,
+  ModificationAnalysis,
+  OperationType,
+  OperationStatistics,
+  SessionClickPath,
+  FunnelDefinition,
+  FunnelReport,
+  WriteItem,
+  BatchWriteReport,
+  SessionInfo,
+  SessionLifecyclePolicy,
+  SessionSweepReport,
+  ImportReport,
+  WorkstationSummary,
+  SyncConfig,
+  SyncStatus,
+  SyncReport,
+  CostSettings,
+  CostReport,
+  TemplateUsageEvent,
+  TemplateUsageStatistics
 } from '../types/feedback';
 
 class FeedbackService {
@@ -21,13 +48,15 @@ class FeedbackService {
   async startSession(
     sessionType: SessionType,
     patientId?: string,
-    patientName?: string
+    patientName?: string,
+    metadata?: Record<string, any>
   ): Promise<string> {
     try {
       const sessionId = await invoke<string>('create_session', {
         sessionType,
         patientId: patientId || null,
-        patientName: patientName || null
+        patientName: patientName || null,
+        metadata: metadata || null
       });
       this.currentSessionId = sessionId;
       console.log(`[FeedbackService] Session started: ${sessionId} (${sessionType})`);
@@ -49,7 +78,7 @@ class FeedbackService {
         return;
       }
 
-      const endTime = Math.floor(Date.now() / 1000); // Convert to Unix timestamp in seconds
+      const endTime = Date.now(); // Unix timestamp in milliseconds
       await invoke('update_session_status', {
         sessionId: targetSessionId,
         status,
@@ -67,6 +96,19 @@ class FeedbackService {
     }
   }
 
+  // Continue a session created by the backend (e.g. for an HIS consultation push)
+  adoptSession(sessionId: string): void {
+    this.currentSessionId = sessionId;
+    console.log(`[FeedbackService] Session adopted: ${sessionId}`);
+  }
+
+  // Forget a session the backend has already closed, so endSession doesn't touch it
+  detachSession(sessionId?: string | null): void {
+    if (sessionId && sessionId === this.currentSessionId) {
+      this.currentSessionId = null;
+    }
+  }
+
   getCurrentSessionId(): string | null {
     return this.currentSessionId;
   }
@@ -147,7 +189,8 @@ class FeedbackService {
         matchConfidence: rec.matchConfidence || null,
         promptTokens: rec.promptTokens || null,
         completionTokens: rec.completionTokens || null,
-        latencyMs: rec.latencyMs || null
+        latencyMs: rec.latencyMs || null,
+        llmModel: rec.llmModel || null
       });
 
       console.log(`[FeedbackService] Recommendation saved: ${recId} (${rec.recType})`);
@@ -164,13 +207,17 @@ class FeedbackService {
     try {
       const sessionId = log.sessionId || this.currentSessionId;
 
-      await invoke('log_operation', {
-        sessionId: sessionId || null,
-        operationType: log.operationType,
-        operationName: log.operationName,
-        details: log.details ? JSON.stringify(log.details) : null,
-        success: log.success !== false,
-        durationMs: log.durationMs || null
+      // Buffered in the backend and written in batches
+      await invoke('enqueue_writes', {
+        items: [{
+          kind: 'log',
+          sessionId: sessionId || null,
+          operationType: log.operationType,
+          operationName: log.operationName,
+          details: log.details ? JSON.stringify(log.details) : null,
+          success: log.success !== false,
+          durationMs: log.durationMs || null
+        }]
       });
 
       console.log(`[FeedbackService] Operation logged: ${log.operationType} - ${log.operationName}`);
@@ -186,12 +233,15 @@ class FeedbackService {
     try {
       const sessionId = metric.sessionId || this.currentSessionId;
 
-      await invoke('record_performance_metric', {
-        sessionId: sessionId || null,
-        metricType: metric.metricType,
-        metricValue: metric.metricValue,
-        unit: metric.unit,
-        context: metric.context ? JSON.stringify(metric.context) : null
+      await invoke('enqueue_writes', {
+        items: [{
+          kind: 'metric',
+          sessionId: sessionId || null,
+          metricType: metric.metricType,
+          metricValue: metric.metricValue,
+          unit: metric.unit,
+          context: metric.context ? JSON.stringify(metric.context) : null
+        }]
       });
 
       console.log(`[FeedbackService] Metric recorded: ${metric.metricType} = ${metric.metricValue} ${metric.unit}`);
@@ -201,16 +251,75 @@ class FeedbackService {
     }
   }
 
+  // Template Usage
+
+  // Symptoms and fields shown or answered in the consultation form; the backend
+  // keeps the first time of each per session
+  async recordTemplateUsage(events: TemplateUsageEvent[], sessionId?: string): Promise<void> {
+    try {
+      const targetSessionId = sessionId || this.currentSessionId;
+      if (!targetSessionId || events.length === 0) return;
+
+      await invoke('enqueue_writes', {
+        items: events.map(e => ({
+          kind: 'templateUsage',
+          sessionId: targetSessionId,
+          symptomKey: e.symptomKey,
+          fieldKey: e.fieldKey || null,
+          event: e.event
+        }))
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to record template usage:', error);
+      // Don't throw - usage recording failures shouldn't break the app
+    }
+  }
+
+  // Batch Writes
+
+  async saveBatch(items: WriteItem[]): Promise<BatchWriteReport> {
+    try {
+      const report = await invoke<BatchWriteReport>('save_batch', { items });
+      if (report.failed > 0) {
+        console.warn(`[FeedbackService] Batch write: ${report.failed} of ${items.length} items rejected`);
+      }
+      return report;
+    } catch (error) {
+      console.error('[FeedbackService] Failed to save batch:', error);
+      throw error;
+    }
+  }
+
+  async enqueueWrites(items: WriteItem[]): Promise<number> {
+    try {
+      return await invoke<number>('enqueue_writes', { items });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to enqueue writes:', error);
+      throw error;
+    }
+  }
+
+  async flushWrites(): Promise<BatchWriteReport> {
+    try {
+      return await invoke<BatchWriteReport>('flush_writes');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to flush writes:', error);
+      throw error;
+    }
+  }
+
   // Statistics Queries
 
   async getSessionStatistics(
     startDate?: number,
-    endDate?: number
+    endDate?: number,
+    sourceWorkstation?: string
   ): Promise<SessionStatistics> {
     try {
       const stats = await invoke<SessionStatistics>('get_session_statistics', {
         startDate: startDate || null,
-        endDate: endDate || null
+        endDate: endDate || null,
+        sourceWorkstation: sourceWorkstation || null
       });
       return stats;
     } catch (error) {
@@ -221,12 +330,14 @@ class FeedbackService {
 
   async getFeedbackStatistics(
     startDate?: number,
-    endDate?: number
+    endDate?: number,
+    sourceWorkstation?: string
   ): Promise<FeedbackStatistics> {
     try {
       const stats = await invoke<FeedbackStatistics>('get_feedback_statistics', {
         startDate: startDate || null,
-        endDate: endDate || null
+        endDate: endDate || null,
+        sourceWorkstation: sourceWorkstation || null
       });
       return stats;
     } catch (error) {
@@ -237,12 +348,14 @@ class FeedbackService {
 
   async getPerformanceStatistics(
     startDate?: number,
-    endDate?: number
+    endDate?: number,
+    sourceWorkstation?: string
   ): Promise<PerformanceStatistics> {
     try {
       const stats = await invoke<PerformanceStatistics>('get_performance_statistics', {
         startDate: startDate || null,
-        endDate: endDate || null
+        endDate: endDate || null,
+        sourceWorkstation: sourceWorkstation || null
       });
       return stats;
     } catch (error) {
@@ -251,26 +364,364 @@ class FeedbackService {
     }
   }
 
+  async getRecommendationStatistics(
+    startDate?: number,
+    endDate?: number,
+    llmModel?: string,
+    sourceWorkstation?: string
+  ): Promise<RecommendationStatistics> {
+    try {
+      return await invoke<RecommendationStatistics>('get_recommendation_statistics', {
+        startDate: startDate || null,
+        endDate: endDate || null,
+        llmModel: llmModel || null,
+        topN: null,
+        bucketCount: null,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get recommendation statistics:', error);
+      throw error;
+    }
+  }
+
+  async getModificationAnalysis(
+    startDate?: number,
+    endDate?: number,
+    targetType?: TargetType,
+    includeItems = false,
+    sourceWorkstation?: string
+  ): Promise<ModificationAnalysis> {
+    try {
+      return await invoke<ModificationAnalysis>('get_modification_analysis', {
+        startDate: startDate || null,
+        endDate: endDate || null,
+        targetType: targetType || null,
+        topN: null,
+        includeItems,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get modification analysis:', error);
+      throw error;
+    }
+  }
+
+  async getOperationStatistics(
+    startDate?: number,
+    endDate?: number,
+    operationType?: OperationType,
+    sourceWorkstation?: string
+  ): Promise<OperationStatistics[]> {
+    try {
+      return await invoke<OperationStatistics[]>('get_operation_statistics', {
+        startDate: startDate || null,
+        endDate: endDate || null,
+        operationType: operationType || null,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get operation statistics:', error);
+      throw error;
+    }
+  }
+
+  async getSessionClickPaths(
+    sessionId?: string,
+    startDate?: number,
+    endDate?: number,
+    limit?: number,
+    sourceWorkstation?: string
+  ): Promise<SessionClickPath[]> {
+    try {
+      return await invoke<SessionClickPath[]>('get_session_click_paths', {
+        sessionId: sessionId || null,
+        startDate: startDate || null,
+        endDate: endDate || null,
+        limit: limit || null,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get session click paths:', error);
+      throw error;
+    }
+  }
+
+  async getOperationFunnels(): Promise<FunnelDefinition[]> {
+    try {
+      return await invoke<FunnelDefinition[]>('get_operation_funnels');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get operation funnels:', error);
+      throw error;
+    }
+  }
+
+  async setOperationFunnels(funnels: FunnelDefinition[]): Promise<void> {
+    try {
+      await invoke('set_operation_funnels', { funnels });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to save operation funnels:', error);
+      throw error;
+    }
+  }
+
+  async getFunnelStatistics(
+    funnelName?: string,
+    steps?: string[],
+    startDate?: number,
+    endDate?: number,
+    sourceWorkstation?: string
+  ): Promise<FunnelReport> {
+    try {
+      return await invoke<FunnelReport>('get_funnel_statistics', {
+        funnelName: funnelName || null,
+        steps: steps || null,
+        startDate: startDate || null,
+        endDate: endDate || null,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get funnel statistics:', error);
+      throw error;
+    }
+  }
+
+  async getTemplateUsageStatistics(
+    startDate?: number,
+    endDate?: number,
+    sourceWorkstation?: string
+  ): Promise<TemplateUsageStatistics> {
+    try {
+      return await invoke<TemplateUsageStatistics>('get_template_usage_statistics', {
+        startDate: startDate || null,
+        endDate: endDate || null,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get template usage statistics:', error);
+      throw error;
+    }
+  }
+
   // Data Export
 
   async exportData(
     format: ExportFormat = 'json',
     startDate?: number,
-    endDate?: number
-  ): Promise<string> {
+    endDate?: number,
+    outputPath?: string,
+    anonymize: boolean = false
+  ): Promise<ExportSummary | null> {
     try {
-      const data = await invoke<string>('export_data', {
+      // Without an outputPath the backend asks for a destination with a save dialog
+      const summary = await invoke<ExportSummary | null>('export_data', {
         format,
+        outputPath: outputPath || null,
         startDate: startDate || null,
-        endDate: endDate || null
+        endDate: endDate || null,
+        anonymize,
+        anonymizationKey: null
       });
-      console.log(`[FeedbackService] Data exported as ${format}`);
-      return data;
+      if (summary) {
+        console.log(`[FeedbackService] Data exported as ${format} to ${summary.path}`);
+      }
+      return summary;
     } catch (error) {
       console.error('[FeedbackService] Failed to export data:', error);
       throw error;
     }
   }
+
+  // Merge an export from another workstation; without inputPath the backend
+  // asks for the file with an open dialog
+  async importData(
+    inputPath?: string,
+    workstationId?: string,
+    workstationName?: string
+  ): Promise<ImportReport | null> {
+    try {
+      const report = await invoke<ImportReport | null>('import_data', {
+        inputPath: inputPath || null,
+        workstationId: workstationId || null,
+        workstationName: workstationName || null
+      });
+      if (report) {
+        console.log(`[FeedbackService] Imported ${report.path} from workstation ${report.workstationId}`);
+      }
+      return report;
+    } catch (error) {
+      console.error('[FeedbackService] Failed to import data:', error);
+      throw error;
+    }
+  }
+
+  async listWorkstations(): Promise<WorkstationSummary[]> {
+    try {
+      return await invoke<WorkstationSummary[]>('list_workstations');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to list workstations:', error);
+      throw error;
+    }
+  }
+
+  // Central Sync
+
+  async getSyncConfig(): Promise<SyncConfig> {
+    try {
+      return await invoke<SyncConfig>('get_sync_config');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get sync config:', error);
+      throw error;
+    }
+  }
+
+  async setSyncConfig(config: SyncConfig): Promise<void> {
+    try {
+      await invoke('set_sync_config', { config });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to save sync config:', error);
+      throw error;
+    }
+  }
+
+  async getSyncStatus(): Promise<SyncStatus> {
+    try {
+      return await invoke<SyncStatus>('get_sync_status');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get sync status:', error);
+      throw error;
+    }
+  }
+
+  async syncNow(): Promise<SyncReport> {
+    try {
+      const report = await invoke<SyncReport>('sync_now');
+      console.log('[FeedbackService] Sync finished:', report);
+      return report;
+    } catch (error) {
+      console.error('[FeedbackService] Failed to sync:', error);
+      throw error;
+    }
+  }
+
+  // LLM Cost
+
+  async getCostSettings(): Promise<CostSettings> {
+    try {
+      return await invoke<CostSettings>('get_cost_settings');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get cost settings:', error);
+      throw error;
+    }
+  }
+
+  async setCostSettings(costSettings: CostSettings): Promise<void> {
+    try {
+      await invoke('set_cost_settings', { costSettings });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to save cost settings:', error);
+      throw error;
+    }
+  }
+
+  async getCostReport(
+    startDate?: number,
+    endDate?: number,
+    sourceWorkstation?: string
+  ): Promise<CostReport> {
+    try {
+      return await invoke<CostReport>('get_cost_report', {
+        startDate: startDate || null,
+        endDate: endDate || null,
+        sourceWorkstation: sourceWorkstation || null
+      });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get cost report:', error);
+      throw error;
+    }
+  }
+
+  // Session Lifecycle
+
+  async getSessionLifecyclePolicy(): Promise<SessionLifecyclePolicy> {
+    try {
+      return await invoke<SessionLifecyclePolicy>('get_session_lifecycle_policy');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to get session lifecycle policy:', error);
+      throw error;
+    }
+  }
+
+  async setSessionLifecyclePolicy(policy: SessionLifecyclePolicy): Promise<void> {
+    try {
+      await invoke('set_session_lifecycle_policy', { policy });
+    } catch (error) {
+      console.error('[FeedbackService] Failed to save session lifecycle policy:', error);
+      throw error;
+    }
+  }
+
+  async sweepIdleSessions(): Promise<SessionSweepReport> {
+    try {
+      return await invoke<SessionSweepReport>('sweep_idle_sessions');
+    } catch (error) {
+      console.error('[FeedbackService] Failed to sweep idle sessions:', error);
+      throw error;
+    }
+  }
+
+  // Reopen the session interrupted by the last shutdown/crash and make it current
+  async resumeInterruptedSession(): Promise<SessionInfo | null> {
+    try {
+      const session = await invoke<SessionInfo | null>('resume_interrupted_session');
+      if (session) {
+        this.adoptSession(session.sessionId);
+      }
+      return session;
+    } catch (error) {
+      console.error('[FeedbackService] Failed to resume interrupted session:', error);
+      throw error;
+    }
+  }
+
+  // Data Retention
+
+  async getRetentionPolicy(): Promise<RetentionPolicy> {
+    return invoke<RetentionPolicy>('get_retention_policy');
+  }
+
+  async setRetentionPolicy(policy: RetentionPolicy): Promise<void> {
+    await invoke('set_retention_policy', { policy });
+  }
+
+  async purgeExpiredData(): Promise<PurgeReport> {
+    const report = await invoke<PurgeReport>('purge_expired_data');
+    console.log('[FeedbackService] Purge finished:', report);
+    return report;
+  }
+
+  async deletePatientData(patientId: string): Promise<Record<string, number>> {
+    return invoke<Record<string, number>>('delete_patient_data', { patientId });
+  }
+
+  async getDatabaseSize(): Promise<DatabaseSizeReport> {
+    return invoke<DatabaseSizeReport>('get_database_size');
+  }
+
+  async vacuumDatabase(): Promise<DatabaseSizeReport> {
+    return invoke<DatabaseSizeReport>('vacuum_database');
+  }
+
+  // Encryption
+
+  async getEncryptionStatus(): Promise<EncryptionStatus> {
+    return invoke<EncryptionStatus>('get_encryption_status');
+  }
+
+  async rekeyDatabase(): Promise<void> {
+    await invoke('rekey_database');
+    console.log('[FeedbackService] Database key rotated');
+  }
 }
 
 // Export singleton instance
@@ -13,6 +13,13 @@ export interface ChatMessage {
   createdAt?: number;
 }
 
+// 模型名和 token 用量，用于成本统计；服务端未返回用量时 token 字段为空
+export interface ChatUsage {
+  model: string;
+  promptTokens?: number;
+  completionTokens?: number;
+}
+
 export const DEFAULT_LLM_CONFIG = {
   baseUrl: "https://api.openai.com/v1",
   model: "gpt-4o-mini",
@@ -125,18 +132,20 @@ function createPayloadMessages(messages: ChatMessage[]) {
   });
 }
 
+// 返回服务端在流末尾给出的模型和 token 用量
 export async function chatStream(
   messages: ChatMessage[],
   onChunk: (chunk: string) => void,
   apiKey?: string,
   retryConfig?: RetryConfig,
   onRetry?: (attempt: number, error: any) => void
-): Promise<void> {
+): Promise<ChatUsage> {
   const { key, baseUrl, model } = getConfigAndKey(apiKey);
   const payloadMessages = createPayloadMessages(messages);
 
   // 使用重试机制包装整个流式请求
-  await retryWithBackoff(async () => {
+  return await retryWithBackoff(async () => {
+    const usage: ChatUsage = { model };
     const res = await fetch(`${baseUrl}/chat/completions`, {
       method: "POST",
       headers: {
@@ -147,6 +156,7 @@ export async function chatStream(
         model: model,
         messages: payloadMessages,
         stream: true,
+        stream_options: { include_usage: true },
       }),
     });
 
@@ -179,12 +189,18 @@ export async function chatStream(
           if (!trimmed || !trimmed.startsWith("data: ")) continue;
 
           const dataStr = trimmed.slice(6);
-          if (dataStr === "[DONE]") return;
+          if (dataStr === "[DONE]") return usage;
 
           try {
             const json = JSON.parse(dataStr);
             const content = json.choices?.[0]?.delta?.content || "";
             if (content) onChunk(content);
+            // 用量在最后一个数据块中（choices 为空）
+            if (json.model) usage.model = json.model;
+            if (json.usage) {
+              usage.promptTokens = json.usage.prompt_tokens;
+              usage.completionTokens = json.usage.completion_tokens;
+            }
           } catch (e) {
             console.warn("解析流式数据失败:", e);
           }
@@ -195,6 +211,7 @@ export async function chatStream(
       reader.cancel();
       throw error;
     }
+    return usage;
   }, retryConfig || DEFAULT_RETRY_CONFIG, onRetry);
 }
 
@@ -229,6 +246,11 @@ export async function chatStreamWithFallback(
   }
 }
 
+export interface ChatResult {
+  content: string;
+  usage: ChatUsage;
+}
+
 // 文本与图像的对话（基于 Chat Completions）
 export async function chat(
   messages: ChatMessage[],
@@ -236,6 +258,16 @@ export async function chat(
   retryConfig?: RetryConfig,
   onRetry?: (attempt: number, error: any) => void
 ): Promise<string> {
+  return (await chatWithUsage(messages, apiKey, retryConfig, onRetry)).content;
+}
+
+// 同 chat，另返回实际使用的模型和 token 用量
+export async function chatWithUsage(
+  messages: ChatMessage[],
+  apiKey?: string,
+  retryConfig?: RetryConfig,
+  onRetry?: (attempt: number, error: any) => void
+): Promise<ChatResult> {
   const { key, baseUrl, model } = getConfigAndKey(apiKey);
   const payloadMessages = createPayloadMessages(messages);
 
@@ -258,7 +290,14 @@ export async function chat(
       error.status = res.status;
       throw error;
     }
-    return data?.choices?.[0]?.message?.content ?? "";
+    return {
+      content: data?.choices?.[0]?.message?.content ?? "",
+      usage: {
+        model: data?.model || model,
+        promptTokens: data?.usage?.prompt_tokens,
+        completionTokens: data?.usage?.completion_tokens
+      }
+    };
   }, retryConfig || DEFAULT_RETRY_CONFIG, onRetry);
 }
 
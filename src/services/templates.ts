@@ -0,0 +1,140 @@
+import { invoke } from '@tauri-apps/api/core';
+import bundledTemplates from '../assets/templates.json';
+import type {
+  BundleRefreshReport,
+  ConflictResolution,
+  RevisionInfo,
+  SymptomMatch,
+  SymptomTemplate,
+  TemplateDiff,
+  TemplateImportPreview,
+  TemplateImportResult,
+  TemplateSubscriptionConfig,
+  TemplateSubscriptionStatus,
+  ValidationIssue
+} from '../types/templates';
+
+/**
+ * 症状模板服务
+ * 模板保存在应用数据目录（首次运行时由内置 templates.json 初始化），
+ * 所有读写都经由 Rust 端的模板仓库完成
+ */
+class TemplateService {
+  async load(): Promise<SymptomTemplate[]> {
+    try {
+      return await invoke<SymptomTemplate[]>('load_templates');
+    } catch (error) {
+      // 模板仓库不可用时退回内置模板，保证问诊界面可用
+      console.error('[TemplateService] Failed to load templates, using bundled copy:', error);
+      return JSON.parse(JSON.stringify(bundledTemplates));
+    }
+  }
+
+  // 按患者性别、年龄裁剪后的模板：不适用的症状、分组和字段已移除
+  async loadForPatient(patient: Record<string, any>): Promise<SymptomTemplate[]> {
+    try {
+      return await invoke<SymptomTemplate[]>('load_applicable_templates', { patient });
+    } catch (error) {
+      console.error('[TemplateService] Failed to load applicable templates:', error);
+      return this.load();
+    }
+  }
+
+  // 按名称、key、系统分类、部位和选项搜索症状，支持拼音全拼和首字母，按匹配程度排序
+  async search(query: string, limit?: number, patient?: Record<string, any>): Promise<SymptomMatch[]> {
+    return invoke<SymptomMatch[]>('search_templates', { request: { query, limit, patient } });
+  }
+
+  // 保存前在 Rust 端整体校验，失败时抛出 TemplateCommandError（含 issues 列表）
+  // 每次保存生成一个历史版本；内容未变化时返回 null
+  async save(templates: SymptomTemplate[], note?: string, author?: string): Promise<RevisionInfo | null> {
+    const content = JSON.stringify(templates, null, 2);
+    return invoke<RevisionInfo | null>('save_templates', { content, author, note });
+  }
+
+  // 只校验不保存，返回空数组表示通过
+  async validate(templates: SymptomTemplate[]): Promise<ValidationIssue[]> {
+    const content = JSON.stringify(templates);
+    return invoke<ValidationIssue[]>('validate_templates', { content });
+  }
+
+  // 放弃本地修改，恢复为内置模板
+  async reset(author?: string): Promise<SymptomTemplate[]> {
+    return invoke<SymptomTemplate[]>('reset_templates', { author });
+  }
+
+  // 历史版本，按时间倒序
+  async listRevisions(): Promise<RevisionInfo[]> {
+    return invoke<RevisionInfo[]>('list_template_revisions');
+  }
+
+  // 比较两个版本；不传 to 时与当前模板比较
+  async diffRevisions(from: string, to?: string): Promise<TemplateDiff> {
+    return invoke<TemplateDiff>('diff_template_revisions', { from, to });
+  }
+
+  // 恢复到指定版本（会生成一个新版本，可再次回退）
+  async restoreRevision(revisionId: string, author?: string): Promise<SymptomTemplate[]> {
+    return invoke<SymptomTemplate[]>('restore_template_revision', { revisionId, author });
+  }
+
+  // 导入第一步：选择导出的模板文件，返回新增/更新/冲突列表，不写入；取消选择时返回 null
+  async previewImport(inputPath?: string): Promise<TemplateImportPreview | null> {
+    return invoke<TemplateImportPreview | null>('preview_template_import', { inputPath });
+  }
+
+  // 导入第二步：按症状 key 指定冲突处理方式（未指定的保留本地）并合并保存；
+  // 传入预览得到的 sha256，文件在预览后被修改时拒绝导入
+  async applyImport(
+    preview: Pick<TemplateImportPreview, 'path' | 'sha256'>,
+    resolutions: Record<string, ConflictResolution>,
+    author?: string
+  ): Promise<TemplateImportResult> {
+    return invoke<TemplateImportResult>('apply_template_import', {
+      inputPath: preview.path,
+      sha256: preview.sha256,
+      resolutions,
+      author
+    });
+  }
+
+  // 导出为表格（xlsx / csv，由保存对话框中的扩展名决定），可编辑后经 previewImport 导回
+  // 返回写入的路径；取消时返回 null
+  async exportSheet(templates: SymptomTemplate[]): Promise<string | null> {
+    const content = JSON.stringify(templates);
+    return invoke<string | null>('export_templates_sheet', { content });
+  }
+
+  // 纸质问诊单 HTML；keys 为空时包含全部症状
+  async renderQuestionnaire(templates: SymptomTemplate[], keys?: string[]): Promise<string> {
+    const content = JSON.stringify(templates);
+    return invoke<string>('render_template_questionnaire', { content, keys });
+  }
+
+  async exportQuestionnaire(templates: SymptomTemplate[], keys?: string[]): Promise<string | null> {
+    const content = JSON.stringify(templates);
+    return invoke<string | null>('export_template_questionnaire', { content, keys });
+  }
+
+  // 科室模板订阅：模板包作为只读基础层，本地修改覆盖其上
+  async getSubscription(): Promise<TemplateSubscriptionConfig> {
+    return invoke<TemplateSubscriptionConfig>('get_template_subscription');
+  }
+
+  async setSubscription(config: TemplateSubscriptionConfig): Promise<void> {
+    await invoke('set_template_subscription', { config });
+  }
+
+  // 当前生效的模板包版本、上次检查结果和本地覆盖的症状
+  async getSubscriptionStatus(): Promise<TemplateSubscriptionStatus> {
+    return invoke<TemplateSubscriptionStatus>('get_template_subscription_status');
+  }
+
+  // force: 服务器上的模板包比当前生效的旧时也强制应用（回退版本）
+  async refreshBundle(force = false): Promise<BundleRefreshReport> {
+    return invoke<BundleRefreshReport>('refresh_template_bundle', { force });
+  }
+}
+
+export const templateService = new TemplateService();
+export default templateService;
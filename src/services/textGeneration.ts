@@ -1,3 +1,5 @@
+import { invoke } from '@tauri-apps/api/core';
+
 /**
  * 文本生成服务
  * 处理 templates.json 中的 textGenConfig 配置，用于生成主诉和现病史文本
@@ -21,174 +23,48 @@ export interface TextGenConfig {
     };
 }
 
-export interface ApplicablePopulation {
-    /** 适用性别：'1'-男, '2'-女，空数组表示不限 */
-    genders?: string[];
-    /** 年龄范围 */
-    ageRange?: {
-        min?: number;
-        max?: number;
-        unit?: 'Y' | 'M' | 'D'; // 岁/月/天
-    };
-}
-
+/** 患者信息（HIS PatientInfo），编码字段缺失时由 Rust 端解析 sdSexText / ageText */
 export interface PatientInfo {
     sdSex?: string;
+    sdSexText?: string;
     ageNum?: number;
     ageUnit?: string;
+    ageText?: string;
     [key: string]: any;
 }
 
-// === 核心函数 ===
-
-/**
- * 判断字段是否适用于当前患者
- */
-export function isFieldApplicable(
-    field: { applicablePopulation?: ApplicablePopulation },
-    patientInfo: PatientInfo
-): boolean {
-    const pop = field.applicablePopulation;
-
-    // 无配置或空配置，默认适用
-    if (!pop) return true;
-
-    // 性别检查
-    if (pop.genders && pop.genders.length > 0) {
-        if (!pop.genders.includes(patientInfo.sdSex || '')) {
-            return false;
-        }
-    }
-
-    // 年龄检查
-    if (pop.ageRange) {
-        const patientAge = normalizeAge(patientInfo.ageNum, patientInfo.ageUnit);
-        const minAge = pop.ageRange.min !== undefined
-            ? normalizeAge(pop.ageRange.min, pop.ageRange.unit || 'Y')
-            : 0;
-        const maxAge = pop.ageRange.max !== undefined
-            ? normalizeAge(pop.ageRange.max, pop.ageRange.unit || 'Y')
-            : Infinity;
-
-        if (patientAge < minAge || patientAge > maxAge) {
-            return false;
-        }
-    }
-
-    return true;
+export interface SymptomAnswers {
+    /** 症状 key */
+    key: string;
+    /** 按字段 storageKey 保存的回答，与问诊表单 formData 结构相同 */
+    answers: Record<string, any>;
 }
 
-/**
- * 将年龄标准化为天数，便于比较
- */
-function normalizeAge(value: number | undefined, unit?: string): number {
-    if (value === undefined) return 0;
-    switch (unit) {
-        case 'Y': return value * 365;
-        case 'M': return value * 30;
-        case 'D': return value;
-        default: return value * 365; // 默认按年
-    }
+export interface TextGenRequest {
+    /** 用于过滤不适用的分组和字段；不传则全部生成 */
+    patient?: PatientInfo;
+    /** 按选择顺序排列，第一个症状用于现病史开头 */
+    symptoms: SymptomAnswers[];
+    /** 一般情况回答（精神、睡眠、食欲、二便、体重） */
+    general?: Record<string, any>;
 }
 
-/**
- * 根据 textGenConfig 生成字段文本
- * @param config 文本生成配置
- * @param value 字段值（可能是字符串、数组或对象）
- * @returns 生成的文本，如果值无效则返回 null
- */
-export function generateFieldText(
-    config: TextGenConfig,
-    value: any,
-    label?: string
-): string | null {
-    if (value === undefined || value === null || value === '') {
-        return null;
-    }
-
-    const separator = config.separator || '、';
-    const ignoreValues = config.optionConfig?.ignoreValues || [];
-    const valueMap = config.optionConfig?.valueMap || {};
-
-    let processedValues: string[] = [];
-
-    // 处理不同类型的值
-    if (Array.isArray(value)) {
-        // 多选值
-        processedValues = value
-            .filter(v => !ignoreValues.includes(v))
-            .map(v => valueMap[v] || v);
-    } else if (typeof value === 'object' && value.inputValue !== undefined) {
-        // input_radio 类型: { inputValue, radioValue }
-        if (value.inputValue && value.radioValue) {
-            const combined = `${value.inputValue}${value.radioValue}`;
-            if (!ignoreValues.includes(combined)) {
-                processedValues = [valueMap[combined] || combined];
-            }
-        }
-    } else if (typeof value === 'string') {
-        // 单选值
-        if (!ignoreValues.includes(value)) {
-            processedValues = [valueMap[value] || value];
-        }
-    } else if (typeof value === 'number') {
-        // 数值
-        processedValues = [String(value)];
-    }
-
-    // 过滤后无有效值
-    if (processedValues.length === 0) {
-        return null;
-    }
-
-    // 拼接值
-    const combinedValue = processedValues.join(separator);
-
-    // 应用模板
-    // 应用模板
-    let result = config.template.replace(/{value}/g, combinedValue);
-    if (label) {
-        result = result.replace(/{label}/g, label);
-    }
-
-    return result;
+export interface GeneratedRecordText {
+    chiefComplaint: string;
+    historyOfPresentIllness: string;
+    /** 模板中不存在的症状 key，未参与生成 */
+    unknownSymptoms: string[];
 }
 
+// === 核心函数 ===
+
 /**
- * 为症状的所有字段生成文本
- * @param symptom 症状配置
- * @param formData 表单数据
- * @param target 目标类型（主诉或现病史）
- * @returns 生成的文本数组
+ * 生成主诉和现病史文本
+ * 由 Rust 端按当前症状模板生成（textGenConfig 及无配置时的回退逻辑），
+ * 与 HIS 调用 /api/templates/generate-text 得到的结果一致
  */
-export function generateTextsForSymptom(
-    symptom: any,
-    formData: Record<string, any>,
-    target: 'chiefComplaint' | 'historyOfPresentIllness'
-): string[] {
-    const results: string[] = [];
-
-    if (!symptom.config?.sections) return results;
-
-    symptom.config.sections.forEach((section: any) => {
-        section.fields.forEach((field: any) => {
-            const textGenConfig = field.textGenConfig as TextGenConfig | undefined;
-
-            // 无配置或不匹配目标
-            if (!textGenConfig || !textGenConfig.targets.includes(target)) {
-                return;
-            }
-
-            const value = formData[field.storageKey];
-            const text = generateFieldText(textGenConfig, value, field.label);
-
-            if (text) {
-                results.push(text);
-            }
-        });
-    });
-
-    return results;
+export async function generateRecordText(request: TextGenRequest): Promise<GeneratedRecordText> {
+    return invoke<GeneratedRecordText>('generate_record_text', { request });
 }
 
 /**
@@ -32,6 +32,7 @@ export interface MessageExtended {
   llmModel?: string;
   latencyMs?: number;
   createdAt?: number;
+  seq?: number;
 }
 
 export interface FeedbackInfo {
@@ -57,6 +58,7 @@ export interface RecommendationExtended {
   promptTokens?: number;
   completionTokens?: number;
   latencyMs?: number;
+  llmModel?: string;
   createdAt?: number;
   // UI state
   feedback?: FeedbackInfo;
@@ -84,6 +86,40 @@ export interface PerformanceMetric {
   createdAt?: number;
 }
 
+// Batch Writes
+// `id` and `createdAt` are optional; the backend fills them in when omitted
+interface WriteItemBase {
+  id?: string;
+  createdAt?: number;
+}
+
+export type WriteItem =
+  | ({ kind: 'message'; images?: string } & WriteItemBase &
+      Pick<MessageExtended, 'sessionId' | 'role' | 'content' | 'tokenCount' | 'llmModel' | 'latencyMs'>)
+  | ({ kind: 'feedback' } & WriteItemBase &
+      Pick<FeedbackInfo, 'sessionId' | 'targetType' | 'targetId' | 'feedbackType' | 'rating' | 'reason' | 'originalValue' | 'modifiedValue'>)
+  | ({ kind: 'recommendation'; content: string; matched?: boolean } & WriteItemBase &
+      Pick<RecommendationExtended, 'sessionId' | 'recType' | 'matchConfidence' | 'promptTokens' | 'completionTokens' | 'latencyMs' | 'llmModel'>)
+  | ({ kind: 'log'; details?: string } & WriteItemBase &
+      Pick<OperationLog, 'sessionId' | 'operationType' | 'operationName' | 'success' | 'durationMs'>)
+  | ({ kind: 'metric'; context?: string } & WriteItemBase &
+      Pick<PerformanceMetric, 'sessionId' | 'metricType' | 'metricValue' | 'unit'>)
+  | ({ kind: 'templateUsage'; sessionId: string } & WriteItemBase & TemplateUsageEvent);
+
+export interface WriteItemResult {
+  index: number;
+  id: string | null;
+  error: FeedbackCommandError | null;
+}
+
+export interface BatchWriteReport {
+  written: number;
+  failed: number;
+  results: WriteItemResult[];
+  // Buffered writes discarded since the last flush because the buffer was full
+  dropped: number;
+}
+
 // Statistics Models
 export interface SessionStatistics {
   totalSessions: number;
@@ -130,7 +166,16 @@ export interface PerformanceStatistics {
 }
 
 // Export Format
-export type ExportFormat = 'csv' | 'json';
+export type ExportFormat = 'json' | 'ndjson' | 'csv' | 'sqlite';
+
+export interface ExportSummary {
+  path: string;
+  format: ExportFormat;
+  rowCounts: Record<string, number>;
+  bytesWritten: number;
+  exportedAt: number;
+  anonymized: boolean;
+}
 
 export interface ExportOptions {
   format: ExportFormat;
@@ -142,3 +187,332 @@ export interface ExportOptions {
   includeLogs?: boolean;
   includeMetrics?: boolean;
 }
+
+// Session Lifecycle
+export interface SessionLifecyclePolicy {
+  idleTimeoutMinutes: number;
+  sweepIntervalMinutes: number;
+}
+
+export interface SessionSweepReport {
+  idleCancelled: number;
+  interruptedCancelled: number;
+  sweptAt: number;
+}
+
+// Multi-workstation import
+export interface WorkstationInfo {
+  id: string;
+  name: string;
+}
+
+export interface ImportReport {
+  importId: string;
+  workstationId: string;
+  workstationName?: string | null;
+  path: string;
+  format: 'json' | 'ndjson' | 'sqlite' | 'csv';
+  insertedRows: Record<string, number>;
+  skippedRows: Record<string, number>;
+  importedAt: number;
+}
+
+export interface WorkstationSummary {
+  workstationId: string;
+  name?: string | null;
+  local: boolean;
+  sessionCount: number;
+  importCount: number;
+  lastImportedAt?: number | null;
+}
+
+// Central sync
+export interface SyncConfig {
+  enabled: boolean;
+  endpoint?: string | null;
+  apiToken?: string | null;
+  intervalMinutes: number;
+  batchSize: number;
+  maxRetries: number;
+  anonymize: boolean;
+}
+
+export interface SyncState {
+  highWaterMark: number;
+  lastAttemptAt?: number | null;
+  lastSuccessAt?: number | null;
+  lastError?: string | null;
+  consecutiveFailures: number;
+  uploadedRows: number;
+}
+
+export interface SyncStatus {
+  enabled: boolean;
+  endpoint?: string | null;
+  running: boolean;
+  state: SyncState;
+  pending: Record<string, number>;
+  pendingTotal: number;
+  oldestPendingAt?: number | null;
+}
+
+export interface SyncReport {
+  batches: number;
+  uploadedRows: number;
+  highWaterMark: number;
+  remaining: number;
+  error?: string | null;
+  finishedAt: number;
+}
+
+// LLM cost accounting
+export interface ModelPrice {
+  // Exact model name, or a prefix ending in '*' (e.g. "deepseek-*")
+  model: string;
+  inputPer1k: number;
+  outputPer1k: number;
+}
+
+export interface CostSettings {
+  currency: string;
+  prices: ModelPrice[];
+  dailyBudget?: number | null;
+  monthlyBudget?: number | null;
+  alertThresholds: number[];
+}
+
+export interface CostBreakdown {
+  key: string;
+  inputTokens: number;
+  outputTokens: number;
+  cost: number;
+  unpricedTokens: number;
+}
+
+export interface BudgetStatus {
+  dailyBudget?: number | null;
+  dailySpent: number;
+  monthlyBudget?: number | null;
+  monthlySpent: number;
+}
+
+export interface CostReport {
+  currency: string;
+  total: CostBreakdown;
+  byDay: CostBreakdown[];
+  byModel: CostBreakdown[];
+  bySessionType: CostBreakdown[];
+  byDoctor: CostBreakdown[];
+  unpricedModels: string[];
+  budget: BudgetStatus;
+}
+
+// Payload of the 'llm-budget-warning' event
+export interface BudgetWarning {
+  period: 'daily' | 'monthly';
+  periodKey: string;
+  thresholdPercent: number;
+  budget: number;
+  spent: number;
+  currency: string;
+}
+
+// Retention
+export interface RetentionPolicy {
+  sessionsDays?: number | null;
+  messagesDays?: number | null;
+  messageImagesDays?: number | null;
+  feedbacksDays?: number | null;
+  recommendationsDays?: number | null;
+  operationLogsDays?: number | null;
+  performanceMetricsDays?: number | null;
+  templateUsageDays?: number | null;
+  purgeIntervalHours: number;
+  vacuumAfterPurge: boolean;
+}
+
+export interface PurgeReport {
+  deletedRows: Record<string, number>;
+  imagesCleared: number;
+  vacuumed: boolean;
+  purgedAt: number;
+}
+
+export interface DatabaseSizeReport {
+  fileSizeBytes: number;
+  pageSize: number;
+  pageCount: number;
+  freelistCount: number;
+  rowCounts: Record<string, number>;
+}
+
+// Error object returned by the feedback commands
+export interface FeedbackCommandError {
+  kind: 'invalidValue' | 'outOfRange' | 'sessionNotFound' | 'database';
+  message: string;
+  field?: string;
+  value?: string;
+  allowed?: string[];
+  sessionId?: string;
+}
+
+export interface EncryptionStatus {
+  supported: boolean;
+  encrypted: boolean;
+  keySource?: 'environment' | 'keyFile' | null;
+  keyPath?: string | null;
+}
+
+// Recommendation Analytics
+export interface RecommendationTypeStatistics {
+  recType: RecommendationType;
+  total: number;
+  matchedCount: number;
+  withOutcome: number;
+  adoptedCount: number;
+  rejectedCount: number;
+  modifiedCount: number;
+  acceptanceRate?: number | null;
+  totalPromptTokens: number;
+  totalCompletionTokens: number;
+  avgPromptTokens?: number | null;
+  avgCompletionTokens?: number | null;
+  avgLatencyMs?: number | null;
+}
+
+export interface ConfidenceBucket {
+  lower: number;
+  upper: number;
+  count: number;
+  avgConfidence?: number | null;
+  matchRate?: number | null;
+  acceptanceRate?: number | null;
+}
+
+export interface RecommendationContentCount {
+  recType: RecommendationType;
+  content: string;
+  count: number;
+}
+
+export interface RecommendationStatistics {
+  totalRecommendations: number;
+  byType: RecommendationTypeStatistics[];
+  calibration: ConfidenceBucket[];
+  topRejected: RecommendationContentCount[];
+  topModified: RecommendationContentCount[];
+}
+
+export interface ModificationDiff {
+  feedbackId: string;
+  targetType: TargetType;
+  originalLength: number;
+  modifiedLength: number;
+  editDistance: number;
+  normalizedDistance: number;
+  insertedChars: number;
+  deletedChars: number;
+  insertedSegments: string[];
+  deletedSegments: string[];
+}
+
+export interface FragmentCount {
+  text: string;
+  count: number;
+}
+
+export interface ModificationTargetSummary {
+  targetType: TargetType;
+  count: number;
+  unchangedCount: number;
+  avgEditDistance: number;
+  avgNormalizedDistance: number;
+  totalInsertedChars: number;
+  totalDeletedChars: number;
+  topInsertions: FragmentCount[];
+  topDeletions: FragmentCount[];
+}
+
+export interface ModificationAnalysis {
+  totalModified: number;
+  byTargetType: ModificationTargetSummary[];
+  items: ModificationDiff[];
+}
+
+export interface OperationStatistics {
+  operationType: OperationType;
+  operationName: string;
+  total: number;
+  errorCount: number;
+  errorRate: number | null;
+  avgDurationMs: number | null;
+  maxDurationMs: number | null;
+}
+
+export interface ClickPathStep {
+  operationType: OperationType;
+  operationName: string;
+  success: boolean;
+  durationMs: number | null;
+  createdAt: number;
+}
+
+export interface SessionClickPath {
+  sessionId: string;
+  steps: ClickPathStep[];
+}
+
+export interface FunnelDefinition {
+  name: string;
+  steps: string[];
+}
+
+export interface FunnelStepResult {
+  operationName: string;
+  sessions: number;
+  conversionFromPrevious: number | null;
+  conversionFromStart: number | null;
+  avgMsFromPrevious: number | null;
+}
+
+export interface FunnelReport {
+  name: string;
+  totalSessions: number;
+  steps: FunnelStepResult[];
+}
+
+// Template usage
+export type UsageEvent = 'shown' | 'answered';
+
+// fieldKey is omitted for the symptom itself
+export interface TemplateUsageEvent {
+  symptomKey: string;
+  fieldKey?: string;
+  event: UsageEvent;
+}
+
+export interface FieldUsageStatistics {
+  fieldKey: string;
+  label: string | null;
+  shown: number;
+  answered: number;
+  skipRate: number | null;
+  avgAnswerMs: number | null;
+}
+
+export interface SymptomUsageStatistics {
+  symptomKey: string;
+  name: string | null;
+  sessions: number;
+  answeredSessions: number;
+  usageRate: number | null;
+  skipRate: number | null;
+  avgAnswerMs: number | null;
+  fields: FieldUsageStatistics[];
+}
+
+export interface TemplateUsageStatistics {
+  totalSessions: number;
+  symptoms: SymptomUsageStatistics[];
+  unusedSymptoms: string[];
+}
@@ -0,0 +1,212 @@
+// Type definitions for symptom templates
+
+export type FieldType = 'radio' | 'checkbox' | 'input_radio' | 'number' | 'input';
+// HIS sex codes: '1' male, '2' female
+export type GenderCode = '1' | '2';
+export type AgeUnit = 'Y' | 'M' | 'D';
+export type TextTarget = 'chiefComplaint' | 'historyOfPresentIllness';
+
+export interface AgeRange {
+  min?: number;
+  max?: number;
+  unit?: AgeUnit;
+}
+
+export interface ApplicablePopulation {
+  genders: GenderCode[];
+  ageRange?: AgeRange;
+  [key: string]: any;
+}
+
+export interface TextGenConfig {
+  targets: TextTarget[];
+  template: string;
+  separator?: string;
+  optionConfig?: {
+    ignoreValues: string[];
+    valueMap: Record<string, string>;
+  };
+}
+
+export interface FieldProps {
+  placeholder?: string;
+  options?: string[];
+  radioOptions?: string[];
+  mutualExclusions?: string[][];
+  unit?: string;
+  [key: string]: any;
+}
+
+export interface TemplateField {
+  id: string;
+  key: string;
+  label: string;
+  type: FieldType;
+  props: FieldProps;
+  storageKey: string;
+  required?: boolean;
+  applicablePopulation?: ApplicablePopulation;
+  textGenConfig?: TextGenConfig;
+}
+
+export interface TemplateSection {
+  id: string;
+  title: string;
+  fields: TemplateField[];
+  applicablePopulation?: ApplicablePopulation;
+}
+
+export interface SymptomTemplate {
+  id: string;
+  key: string;
+  name: string;
+  description: string;
+  isCommonSymptom: boolean;
+  systemCategory: string[];
+  bodyParts: string[];
+  config: {
+    title?: string;
+    sections: TemplateSection[];
+  };
+  applicablePopulation: ApplicablePopulation;
+  createdAt: number;
+  updatedAt: number;
+  [key: string]: any;
+}
+
+// One problem found by validation, e.g. path `$[3].config.sections[0].fields[2].storageKey`
+export interface ValidationIssue {
+  path: string;
+  message: string;
+}
+
+// Error object returned by the template commands
+export interface TemplateCommandError {
+  kind: 'parse' | 'invalid' | 'storage';
+  message: string;
+  issues?: ValidationIssue[];
+}
+
+// Template revision history
+export interface RevisionInfo {
+  id: string;
+  createdAt: number;
+  author: string;
+  note: string;
+  symptomCount: number;
+  sha256: string;
+}
+
+export interface PropertyChange {
+  property: string;
+  before: any;
+  after: any;
+}
+
+export interface FieldChange {
+  key: string;
+  label: string;
+  changes: PropertyChange[];
+}
+
+export interface SymptomChange {
+  key: string;
+  name: string;
+  changes: PropertyChange[];
+  fieldsAdded: { key: string; label: string }[];
+  fieldsRemoved: { key: string; label: string }[];
+  fieldsModified: FieldChange[];
+}
+
+export interface TemplateDiff {
+  from: string;
+  // Revision id, or 'current' for the templates in use
+  to: string;
+  added: { key: string; name: string }[];
+  removed: { key: string; name: string }[];
+  modified: SymptomChange[];
+}
+
+// Template import
+export type ConflictResolution = 'keepLocal' | 'takeIncoming';
+
+export interface ImportConflict {
+  key: string;
+  name: string;
+  localUpdatedAt: number;
+  incomingUpdatedAt: number;
+  changes?: SymptomChange | null;
+}
+
+export interface TemplateImportPreview {
+  path: string;
+  // Passed back to applyImport, which rejects the file if it changed since the preview
+  sha256: string;
+  added: { key: string; name: string }[];
+  updated: { key: string; name: string }[];
+  conflicts: ImportConflict[];
+  unchanged: number;
+}
+
+export interface TemplateImportResult {
+  added: number;
+  updated: number;
+  keptLocal: number;
+  tookIncoming: number;
+  unchanged: number;
+  revisionId?: string | null;
+}
+
+// Department template subscription
+export interface TemplateSubscriptionConfig {
+  enabled: boolean;
+  url?: string | null;
+  // Defaults to `<url>.sig`
+  signatureUrl?: string | null;
+  // minisign public key the bundle must be signed with
+  publicKey?: string | null;
+  apiToken?: string | null;
+  intervalMinutes: number;
+}
+
+export interface BundleInfo {
+  version: string;
+  name?: string | null;
+  publishedAt?: number | null;
+  sha256: string;
+  symptomCount: number;
+  signedComment: string;
+}
+
+export interface TemplateSubscriptionStatus {
+  enabled: boolean;
+  url?: string | null;
+  running: boolean;
+  active?: BundleInfo | null;
+  state: {
+    lastCheckedAt?: number | null;
+    lastSuccessAt?: number | null;
+    lastError?: string | null;
+    consecutiveFailures: number;
+  };
+  // Local symptoms that override or add to the bundle
+  overrides: { key: string; name: string }[];
+}
+
+export interface BundleRefreshReport {
+  changed: boolean;
+  active?: BundleInfo | null;
+  checkedAt: number;
+}
+
+export type SymptomMatchField = 'name' | 'key' | 'systemCategory' | 'bodyPart' | 'option';
+
+// search_templates result, best match first
+export interface SymptomMatch {
+  key: string;
+  name: string;
+  systemCategory: string[];
+  score: number;
+  matchedField: SymptomMatchField;
+  matchedText: string;
+}